@@ -0,0 +1,86 @@
+//! Guards the number of heap allocations of the conversion paths, which the
+//! timing benchmarks can't catch. Fails when a path allocates more than its
+//! budget.
+//!
+//! Run with `cargo bench --features unstable-ffi --bench allocations`.
+
+mod common;
+
+use std::process::ExitCode;
+
+use mc_api::NameInterner;
+
+use common::{count_allocations, CountingAlloc, RawTopology};
+
+#[global_allocator]
+static ALLOC: CountingAlloc = CountingAlloc;
+
+struct Check {
+    name: &'static str,
+    allocations: usize,
+    budget: usize,
+}
+
+fn main() -> ExitCode {
+    let raw = RawTopology::cameras(4);
+    let entities = raw.entities.len();
+    let mut checks = Vec::new();
+
+    // One buffer per section and one name per entity.
+    let (allocations, _) = count_allocations(|| raw.convert());
+    checks.push(Check {
+        name: "from_raw_parts",
+        allocations,
+        budget: 4 + entities,
+    });
+
+    // Names already pooled are shared, not copied.
+    let mut interner = NameInterner::new();
+    raw.convert().intern_names(&mut interner);
+    let mut topology = raw.convert();
+    let (allocations, _) = count_allocations(|| topology.intern_names(&mut interner));
+    checks.push(Check {
+        name: "intern_names (warm)",
+        allocations,
+        budget: 0,
+    });
+
+    // The whole interned conversion: pooled names are looked up in the
+    // kernel structs, only the section buffers are allocated.
+    let (allocations, _) = count_allocations(|| raw.convert_interned(&mut interner));
+    checks.push(Check {
+        name: "from_raw_parts_interned (warm)",
+        allocations,
+        budget: 4,
+    });
+
+    // The steady state of `topology_into`: an unchanged topology reuses the
+    // section buffers and every entity name.
+    let mut topology = raw.convert();
+    raw.assign(&mut topology);
+    let (allocations, _) = count_allocations(|| raw.assign(&mut topology));
+    checks.push(Check {
+        name: "assign_raw_parts (warm)",
+        allocations,
+        budget: 0,
+    });
+
+    let mut failed = false;
+    for check in &checks {
+        let verdict = if check.allocations <= check.budget {
+            "ok"
+        } else {
+            failed = true;
+            "OVER BUDGET"
+        };
+        println!(
+            "{:<32} {:>4} allocations (budget {:>4})  {verdict}",
+            check.name, check.allocations, check.budget
+        );
+    }
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
@@ -0,0 +1,202 @@
+//! Raw `MEDIA_IOC_G_TOPOLOGY` buffers shared by the benchmarks, so they
+//! measure the conversion without a device.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use mc_api::{consts, ffi};
+
+/// Kernel structs of a topology, as `MEDIA_IOC_G_TOPOLOGY` fills them in.
+pub struct RawTopology {
+    pub topology: ffi::media_v2_topology,
+    pub entities: Vec<ffi::media_v2_entity>,
+    pub interfaces: Vec<ffi::media_v2_interface>,
+    pub pads: Vec<ffi::media_v2_pad>,
+    pub links: Vec<ffi::media_v2_link>,
+}
+
+impl RawTopology {
+    /// `cameras` sensor -> CSI-2 receiver -> ISP -> video node pipelines, each
+    /// subdevice and video node with its interface, like a multi-camera
+    /// SoC reports them.
+    pub fn cameras(cameras: u32) -> RawTopology {
+        let mut raw = RawTopology {
+            topology: unsafe { std::mem::zeroed() },
+            entities: Vec::new(),
+            interfaces: Vec::new(),
+            pads: Vec::new(),
+            links: Vec::new(),
+        };
+        raw.topology.topology_version = 42;
+        let mut next_id = 1;
+        let mut id = || {
+            next_id += 1;
+            next_id
+        };
+
+        for camera in 0..cameras {
+            let stages = [
+                (
+                    format!("imx219 {camera}-0010"),
+                    consts::MEDIA_ENT_F_CAM_SENSOR,
+                    false,
+                ),
+                (
+                    format!("csi2-{camera}"),
+                    consts::MEDIA_ENT_F_VID_IF_BRIDGE,
+                    true,
+                ),
+                (
+                    format!("isp-{camera}"),
+                    consts::MEDIA_ENT_F_PROC_VIDEO_ISP,
+                    true,
+                ),
+                (format!("video-{camera}"), consts::MEDIA_ENT_F_IO_V4L, true),
+            ];
+            let mut previous_source = None;
+            for (index, (name, function, has_sink)) in stages.iter().enumerate() {
+                let entity_id = id();
+                raw.entities.push(entity(entity_id, name, *function));
+
+                if *has_sink {
+                    let sink_id = id();
+                    raw.pads
+                        .push(pad(sink_id, entity_id, 0, consts::MEDIA_PAD_FL_SINK));
+                    if let Some(source_id) = previous_source {
+                        raw.links.push(link(
+                            id(),
+                            source_id,
+                            sink_id,
+                            consts::MEDIA_LNK_FL_ENABLED,
+                        ));
+                    }
+                }
+                if index + 1 < stages.len() {
+                    let source_id = id();
+                    raw.pads
+                        .push(pad(source_id, entity_id, 1, consts::MEDIA_PAD_FL_SOURCE));
+                    previous_source = Some(source_id);
+                }
+
+                let is_video = *function == consts::MEDIA_ENT_F_IO_V4L;
+                let intf_type = if is_video {
+                    consts::MEDIA_INTF_T_V4L_VIDEO
+                } else {
+                    consts::MEDIA_INTF_T_V4L_SUBDEV
+                };
+                let intf_id = id();
+                raw.interfaces
+                    .push(interface(intf_id, intf_type, 81, camera * 4 + index as u32));
+                raw.links.push(link(
+                    id(),
+                    intf_id,
+                    entity_id,
+                    consts::MEDIA_LNK_FL_INTERFACE_LINK | consts::MEDIA_LNK_FL_ENABLED,
+                ));
+            }
+        }
+
+        raw.topology.num_entities = raw.entities.len() as u32;
+        raw.topology.num_interfaces = raw.interfaces.len() as u32;
+        raw.topology.num_pads = raw.pads.len() as u32;
+        raw.topology.num_links = raw.links.len() as u32;
+        raw
+    }
+
+    pub fn convert_interned(&self, interner: &mut mc_api::NameInterner) -> mc_api::MediaV2Topology {
+        mc_api::MediaV2Topology::from_raw_parts_interned(
+            &self.topology,
+            &self.entities,
+            &self.interfaces,
+            &self.pads,
+            &self.links,
+            interner,
+        )
+    }
+
+    pub fn assign(&self, topology: &mut mc_api::MediaV2Topology) {
+        topology.assign_raw_parts(
+            &self.topology,
+            &self.entities,
+            &self.interfaces,
+            &self.pads,
+            &self.links,
+        );
+    }
+
+    pub fn convert(&self) -> mc_api::MediaV2Topology {
+        mc_api::MediaV2Topology::from_raw_parts(
+            &self.topology,
+            &self.entities,
+            &self.interfaces,
+            &self.pads,
+            &self.links,
+        )
+    }
+}
+
+fn entity(id: u32, name: &str, function: u32) -> ffi::media_v2_entity {
+    let mut entity: ffi::media_v2_entity = unsafe { std::mem::zeroed() };
+    entity.id = id;
+    entity.function = function;
+    for (dst, src) in entity.name.iter_mut().zip(name.bytes()) {
+        *dst = src as _;
+    }
+    entity
+}
+
+fn pad(id: u32, entity_id: u32, index: u32, flags: u32) -> ffi::media_v2_pad {
+    let mut pad: ffi::media_v2_pad = unsafe { std::mem::zeroed() };
+    pad.id = id;
+    pad.entity_id = entity_id;
+    pad.index = index;
+    pad.flags = flags;
+    pad
+}
+
+fn link(id: u32, source_id: u32, sink_id: u32, flags: u32) -> ffi::media_v2_link {
+    let mut link: ffi::media_v2_link = unsafe { std::mem::zeroed() };
+    link.id = id;
+    link.source_id = source_id;
+    link.sink_id = sink_id;
+    link.flags = flags;
+    link
+}
+
+fn interface(id: u32, intf_type: u32, major: u32, minor: u32) -> ffi::media_v2_interface {
+    let mut intf: ffi::media_v2_interface = unsafe { std::mem::zeroed() };
+    intf.id = id;
+    intf.intf_type = intf_type;
+    intf.__bindgen_anon_1.devnode = ffi::media_v2_intf_devnode { major, minor };
+    intf
+}
+
+/// Global allocator counting the allocations made through it.
+pub struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+/// Number of allocations `f` makes.
+pub fn count_allocations<T>(f: impl FnOnce() -> T) -> (usize, T) {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let value = f();
+    (ALLOCATIONS.load(Ordering::Relaxed) - before, value)
+}
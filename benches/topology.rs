@@ -0,0 +1,54 @@
+//! Cost of turning the kernel structs of a topology into the crate's types.
+//!
+//! Run with `cargo bench --features unstable-ffi --bench topology`.
+
+#[allow(dead_code)]
+mod common;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use mc_api::{MediaGraph, NameInterner};
+
+use common::RawTopology;
+
+fn conversion(c: &mut Criterion) {
+    let mut group = c.benchmark_group("from_raw_parts");
+    for cameras in [1, 4, 16] {
+        let raw = RawTopology::cameras(cameras);
+        group.throughput(Throughput::Elements(raw.entities.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(cameras), &raw, |b, raw| {
+            b.iter(|| raw.convert())
+        });
+    }
+    group.finish();
+}
+
+fn interning(c: &mut Criterion) {
+    let raw = RawTopology::cameras(16);
+    let mut interner = NameInterner::new();
+    raw.convert().intern_names(&mut interner);
+    c.bench_function("intern_names", |b| {
+        b.iter_batched(
+            || raw.convert(),
+            |mut topology| {
+                topology.intern_names(&mut interner);
+                topology
+            },
+            criterion::BatchSize::SmallInput,
+        )
+    });
+}
+
+fn graph(c: &mut Criterion) {
+    let topology = RawTopology::cameras(16).convert();
+    c.bench_function("MediaGraph::new", |b| {
+        b.iter_batched(
+            || topology.clone(),
+            MediaGraph::new,
+            criterion::BatchSize::SmallInput,
+        )
+    });
+    c.bench_function("fingerprint", |b| b.iter(|| topology.fingerprint()));
+}
+
+criterion_group!(benches, conversion, interning, graph);
+criterion_main!(benches);
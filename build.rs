@@ -2,8 +2,11 @@ use std::env;
 use std::path::PathBuf;
 
 fn main() {
+    #[cfg(feature = "capi")]
+    generate_c_header();
+
     let generate_bindings = env::var_os("MC_API_BINDGEN");
-    if let None = generate_bindings {
+    if generate_bindings.is_none() {
         return;
     }
 
@@ -19,7 +22,7 @@ fn main() {
         .header("wrapper.h")
         // Tell cargo to invalidate the built crate whenever any of the
         // included header files changed.
-        .parse_callbacks(Box::new(bindgen::CargoCallbacks))
+        .parse_callbacks(Box::new(bindgen::CargoCallbacks::new()))
         // Finish the builder and generate the bindings.
         .generate()
         // Unwrap the Result and panic on failure.
@@ -31,3 +34,21 @@ fn main() {
         .write_to_file(out_path.join("bindings.rs"))
         .expect("Couldn't write bindings!");
 }
+
+/// Writes the header of the C API, `$OUT_DIR/mc_api.h`, from `src/capi.rs`.
+/// A test checks that the committed `include/mc_api.h` matches it.
+#[cfg(feature = "capi")]
+fn generate_c_header() {
+    println!("cargo:rerun-if-changed=src/capi.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+
+    let crate_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    let config = cbindgen::Config::from_file(crate_dir.join("cbindgen.toml"))
+        .expect("Couldn't read cbindgen.toml");
+    cbindgen::Builder::new()
+        .with_crate(&crate_dir)
+        .with_config(config)
+        .generate()
+        .expect("Unable to generate the C header")
+        .write_to_file(PathBuf::from(env::var("OUT_DIR").unwrap()).join("mc_api.h"));
+}
@@ -0,0 +1,20 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use mc_api::{parse_links_fuzz, parse_pad_formats_fuzz, EntityRef, LinkRequest};
+
+/// Names with both quote characters can't be written in media-ctl syntax.
+fn printable(entity: &EntityRef) -> bool {
+    !matches!(entity, EntityRef::Name(name) if name.contains('\'') && name.contains('"'))
+}
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(links) = parse_links_fuzz(data) {
+        for link in links {
+            if printable(&link.source.entity) && printable(&link.sink.entity) {
+                assert_eq!(link.to_string().parse::<LinkRequest>().ok(), Some(link));
+            }
+        }
+    }
+    let _ = parse_pad_formats_fuzz(data);
+});
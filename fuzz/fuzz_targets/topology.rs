@@ -0,0 +1,17 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use mc_api::{parse_topology_fuzz, MediaGraph, MediaV2Topology};
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(topology) = parse_topology_fuzz(data) else {
+        return;
+    };
+    let _ = topology.validate();
+    let json = topology.to_json();
+    let parsed = MediaV2Topology::from_json(&json).expect("dumps read back");
+    assert_eq!(parsed.to_json(), json);
+    let graph = MediaGraph::new(topology);
+    let _ = graph.detect_cycles();
+    let _ = graph.topological_order();
+});
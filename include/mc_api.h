@@ -0,0 +1,134 @@
+/* Generated by cbindgen from src/capi.rs, do not edit. */
+
+#ifndef MC_API_H
+#define MC_API_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+/**
+ * Device information, `struct media_device_info` with the strings
+ * NUL-terminated.
+ */
+struct mc_device_info {
+  char driver[16];
+  char model[32];
+  char serial[40];
+  char bus_info[32];
+  uint32_t media_version;
+  uint32_t hw_revision;
+  uint32_t driver_version;
+};
+
+struct mc_entity {
+  uint32_t id;
+  /**
+   * NUL-terminated.
+   */
+  char name[64];
+  /**
+   * `MEDIA_ENT_F_*`.
+   */
+  uint32_t function;
+  uint32_t flags;
+};
+
+struct mc_interface {
+  uint32_t id;
+  /**
+   * `MEDIA_INTF_T_*`.
+   */
+  uint32_t intf_type;
+  uint32_t flags;
+  /**
+   * Device node numbers, 0 for interfaces without one.
+   */
+  uint32_t major;
+  uint32_t minor;
+};
+
+struct mc_pad {
+  uint32_t id;
+  uint32_t entity_id;
+  uint32_t flags;
+  uint32_t index;
+};
+
+struct mc_link {
+  uint32_t id;
+  uint32_t source_id;
+  uint32_t sink_id;
+  uint32_t flags;
+};
+
+/**
+ * Graph of a media device, as `MEDIA_IOC_G_TOPOLOGY` reports it. Allocated
+ * by `mc_get_topology`, released with `mc_free_topology`.
+ */
+struct mc_topology {
+  uint64_t topology_version;
+  struct mc_entity *entities;
+  size_t num_entities;
+  struct mc_interface *interfaces;
+  size_t num_interfaces;
+  struct mc_pad *pads;
+  size_t num_pads;
+  struct mc_link *links;
+  size_t num_links;
+};
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Fills `info` with the information of the media device open as `fd`.
+ *
+ * # Safety
+ *
+ * `fd` must be an open descriptor or negative, which fails with `-EBADF`,
+ * and `info` valid for writes.
+ */
+int mc_get_device_info(int fd,
+                       struct mc_device_info *info);
+
+/**
+ * Reads the topology of the media device open as `fd` into a new
+ * `mc_topology`, stored in `*topology`.
+ *
+ * # Safety
+ *
+ * `fd` must be an open descriptor or negative, which fails with `-EBADF`,
+ * and `topology` valid for writes.
+ */
+int mc_get_topology(int fd,
+                    struct mc_topology **topology);
+
+/**
+ * Releases a topology returned by `mc_get_topology`. Does nothing if
+ * `topology` is NULL.
+ *
+ * # Safety
+ *
+ * `topology` must come from `mc_get_topology` and not be released yet.
+ */
+void mc_free_topology(struct mc_topology *topology);
+
+/**
+ * Sets the `MEDIA_LNK_FL_*` `flags` of the data link between two pads,
+ * addressed by ID. Fails with `-EBADF` if `fd` was opened read-only.
+ *
+ * # Safety
+ *
+ * `fd` must be an open descriptor or negative, which fails with `-EBADF`.
+ */
+int mc_setup_link(int fd,
+                  uint32_t source_pad_id,
+                  uint32_t sink_pad_id,
+                  uint32_t flags);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* MC_API_H */
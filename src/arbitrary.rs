@@ -0,0 +1,207 @@
+use proptest::{
+    arbitrary::{any, Arbitrary},
+    collection::vec,
+    sample::Index,
+    strategy::{BoxedStrategy, Just, Strategy},
+};
+
+use crate::{
+    EntityFlags, EntityFunction, EntityId, InterfaceId, InterfaceType, LinkFlags, LinkId,
+    MediaV2Entity, MediaV2Interface, MediaV2IntfDevnode, MediaV2Link, MediaV2Pad, MediaV2Topology,
+    PadFlags, PadId, TopologyBuilder,
+};
+
+/// Size limits of the topologies generated by the [`Arbitrary`]
+/// implementation of [`MediaV2Topology`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TopologyParams {
+    pub max_entities: usize,
+    pub max_pads_per_entity: usize,
+    pub max_data_links: usize,
+    pub max_interfaces: usize,
+}
+
+impl Default for TopologyParams {
+    fn default() -> TopologyParams {
+        TopologyParams {
+            max_entities: 8,
+            max_pads_per_entity: 4,
+            max_data_links: 12,
+            max_interfaces: 4,
+        }
+    }
+}
+
+/// Name, function, connector flag and pads of an entity, each pad given as
+/// whether it is a source.
+type EntityShape = (String, EntityFunction, bool, Vec<bool>);
+
+fn entity_shape(max_pads: usize) -> impl Strategy<Value = EntityShape> {
+    (
+        "[a-z][a-z0-9 _-]{0,15}",
+        any::<EntityFunction>(),
+        any::<bool>(),
+        vec(any::<bool>(), 0..=max_pads),
+    )
+}
+
+fn data_link_flags() -> impl Strategy<Value = LinkFlags> {
+    proptest::prop_oneof![
+        Just(LinkFlags::empty()),
+        Just(LinkFlags::ENABLED),
+        Just(LinkFlags::ENABLED | LinkFlags::IMMUTABLE),
+        Just(LinkFlags::DYNAMIC),
+    ]
+}
+
+/// Generates topologies as the kernel reports them: IDs unique and typed
+/// like the kernel assigns them, unique entity names, data links from source
+/// to sink pads, and each interface bound to an entity by an interface link.
+impl Arbitrary for MediaV2Topology {
+    type Parameters = TopologyParams;
+    type Strategy = BoxedStrategy<MediaV2Topology>;
+
+    fn arbitrary_with(params: TopologyParams) -> BoxedStrategy<MediaV2Topology> {
+        (
+            any::<u64>(),
+            vec(
+                entity_shape(params.max_pads_per_entity),
+                0..=params.max_entities,
+            ),
+            vec(
+                (any::<Index>(), any::<Index>(), data_link_flags()),
+                0..=params.max_data_links,
+            ),
+            vec(
+                (any::<Index>(), any::<InterfaceType>(), any::<(u32, u32)>()),
+                0..=params.max_interfaces,
+            ),
+        )
+            .prop_map(|(version, entities, links, interfaces)| {
+                build_topology(version, &entities, &links, &interfaces)
+            })
+            .boxed()
+    }
+}
+
+fn build_topology(
+    version: u64,
+    entities: &[EntityShape],
+    links: &[(Index, Index, LinkFlags)],
+    interfaces: &[(Index, InterfaceType, (u32, u32))],
+) -> MediaV2Topology {
+    let names: Vec<String> = entities
+        .iter()
+        .enumerate()
+        .map(|(i, (name, ..))| format!("{name}-{i}"))
+        .collect();
+    let mut bound: Vec<Vec<(InterfaceType, u32, u32)>> = vec![Vec::new(); entities.len()];
+    if !entities.is_empty() {
+        for (entity, intf_type, (major, minor)) in interfaces {
+            bound[entity.index(entities.len())].push((*intf_type, *major, *minor));
+        }
+    }
+
+    let mut sources = Vec::new();
+    let mut sinks = Vec::new();
+    let mut builder = TopologyBuilder::new().topology_version(version);
+    for ((name, (_, function, connector, pads)), bound) in names.iter().zip(entities).zip(&bound) {
+        builder = builder.entity(name, *function);
+        if *connector {
+            builder = builder.entity_flags(EntityFlags::CONNECTOR);
+        }
+        for (index, &is_source) in pads.iter().enumerate() {
+            let pad = (name.as_str(), index as u32);
+            if is_source {
+                sources.push(pad);
+                builder = builder.pad(PadFlags::SOURCE);
+            } else {
+                sinks.push(pad);
+                builder = builder.pad(PadFlags::SINK);
+            }
+        }
+        for &(intf_type, major, minor) in bound {
+            builder = builder.interface(intf_type, major, minor);
+        }
+    }
+    if !sources.is_empty() && !sinks.is_empty() {
+        for (source, sink, flags) in links {
+            builder = builder.link(*source.get(&sources), *sink.get(&sinks), *flags);
+        }
+    }
+    builder
+        .build()
+        .expect("generated links only reference generated pads")
+}
+
+impl Arbitrary for MediaV2Entity {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<MediaV2Entity>;
+
+    fn arbitrary_with(_: ()) -> BoxedStrategy<MediaV2Entity> {
+        (
+            any::<EntityId>(),
+            "[a-z][a-z0-9 _-]{0,30}",
+            any::<EntityFunction>(),
+            any::<u32>(),
+        )
+            .prop_map(|(id, name, function, flags)| {
+                MediaV2Entity::new(id, name, function).with_flags(flags)
+            })
+            .boxed()
+    }
+}
+
+impl Arbitrary for MediaV2Interface {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<MediaV2Interface>;
+
+    fn arbitrary_with(_: ()) -> BoxedStrategy<MediaV2Interface> {
+        (
+            any::<InterfaceId>(),
+            any::<InterfaceType>(),
+            any::<u32>(),
+            any::<(u32, u32)>(),
+        )
+            .prop_map(|(id, intf_type, flags, (major, minor))| {
+                let interface = MediaV2Interface::new(id, intf_type).with_flags(flags);
+                if intf_type.is_devnode() {
+                    interface.with_devnode(MediaV2IntfDevnode::new(major, minor))
+                } else {
+                    interface
+                }
+            })
+            .boxed()
+    }
+}
+
+impl Arbitrary for MediaV2Pad {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<MediaV2Pad>;
+
+    fn arbitrary_with(_: ()) -> BoxedStrategy<MediaV2Pad> {
+        (
+            any::<PadId>(),
+            any::<EntityId>(),
+            any::<u16>(),
+            any::<u32>(),
+        )
+            .prop_map(|(id, entity_id, index, flags)| {
+                MediaV2Pad::new(id, entity_id, index.into()).with_flags(flags)
+            })
+            .boxed()
+    }
+}
+
+impl Arbitrary for MediaV2Link {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<MediaV2Link>;
+
+    fn arbitrary_with(_: ()) -> BoxedStrategy<MediaV2Link> {
+        (any::<LinkId>(), any::<u32>(), any::<u32>(), any::<u32>())
+            .prop_map(|(id, source_id, sink_id, flags)| {
+                MediaV2Link::new(id, source_id, sink_id).with_flags(flags)
+            })
+            .boxed()
+    }
+}
@@ -0,0 +1,132 @@
+//! Async counterparts of the blocking API for tokio applications.
+//!
+//! Device ioctls are short but blocking, so they run on tokio's blocking
+//! thread pool with a duplicate of the device descriptor. Request completion
+//! is awaited on the reactor without occupying a thread, and so are hotplug
+//! events, see [`AsyncDeviceMonitor`].
+
+use std::{
+    io, panic,
+    path::Path,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use futures_core::Stream;
+use nix::errno::Errno;
+use tokio::io::{unix::AsyncFd, Interest};
+
+use crate::{
+    DeviceEvent, DeviceMonitor, LinkChange, MediaDevice, MediaDeviceInfo, MediaError,
+    MediaV2Topology, PipelineConfig, Request,
+};
+
+/// Runs `f` on the blocking pool with a duplicate of `device`. Panics of `f`
+/// are propagated to the caller.
+async fn blocking<T, F>(device: &MediaDevice, f: F) -> Result<T, MediaError>
+where
+    T: Send + 'static,
+    F: FnOnce(&MediaDevice) -> Result<T, MediaError> + Send + 'static,
+{
+    let device = device.try_clone()?;
+    match tokio::task::spawn_blocking(move || f(&device)).await {
+        Ok(res) => res,
+        Err(err) => panic::resume_unwind(err.into_panic()),
+    }
+}
+
+fn poll_error(err: io::Error) -> MediaError {
+    MediaError::Poll(Errno::from_i32(err.raw_os_error().unwrap_or(0)))
+}
+
+impl MediaDevice {
+    pub async fn device_info_async(&self) -> Result<MediaDeviceInfo, MediaError> {
+        blocking(self, MediaDevice::device_info).await
+    }
+
+    pub async fn topology_async(&self) -> Result<MediaV2Topology, MediaError> {
+        blocking(self, MediaDevice::topology).await
+    }
+
+    /// See [`MediaDevice::apply`].
+    pub async fn apply_async(
+        &self,
+        config: &PipelineConfig,
+    ) -> Result<Vec<LinkChange>, MediaError> {
+        let config = config.clone();
+        blocking(self, move |device| device.apply(&config)).await
+    }
+}
+
+impl Request {
+    /// Waits for the request to complete, see [`Request::wait`].
+    pub async fn completion(&self) -> Result<(), MediaError> {
+        let fd = AsyncFd::with_interest(self.fd(), Interest::PRIORITY).map_err(poll_error)?;
+        loop {
+            let mut guard = fd.ready(Interest::PRIORITY).await.map_err(poll_error)?;
+            if self.is_done()? {
+                return Ok(());
+            }
+            guard.clear_ready();
+        }
+    }
+}
+
+/// A [`DeviceMonitor`] driven by the tokio reactor, as a never-ending
+/// [`Stream`] of hotplug events.
+///
+/// With `futures::StreamExt`, `while let Some(event) = monitor.next().await`
+/// handles cameras as they are attached or removed. As with
+/// [`DeviceMonitor`], nodes present at creation are not reported.
+#[derive(Debug)]
+pub struct AsyncDeviceMonitor {
+    monitor: AsyncFd<DeviceMonitor>,
+}
+
+impl AsyncDeviceMonitor {
+    /// Must be called within a tokio runtime.
+    pub fn new() -> Result<AsyncDeviceMonitor, MediaError> {
+        DeviceMonitor::new()?.into_async()
+    }
+
+    /// Monitors `dir` instead of `/dev`.
+    pub fn watch(dir: impl AsRef<Path>) -> Result<AsyncDeviceMonitor, MediaError> {
+        DeviceMonitor::watch(dir)?.into_async()
+    }
+
+    /// Waits for the next event.
+    pub async fn next_event(&mut self) -> Result<DeviceEvent, MediaError> {
+        std::future::poll_fn(|cx| self.poll_event(cx)).await
+    }
+
+    fn poll_event(&mut self, cx: &mut Context<'_>) -> Poll<Result<DeviceEvent, MediaError>> {
+        loop {
+            if let Some(event) = self.monitor.get_mut().pop_event() {
+                return Poll::Ready(Ok(event));
+            }
+            let mut guard = ready!(self.monitor.poll_read_ready_mut(cx)).map_err(poll_error)?;
+            // Only give up readiness once inotify has been drained, the
+            // reactor is edge-triggered.
+            if !guard.get_inner_mut().read_events()? {
+                guard.clear_ready();
+            }
+        }
+    }
+}
+
+impl DeviceMonitor {
+    /// Registers the monitor with the tokio reactor, must be called within a
+    /// tokio runtime.
+    pub fn into_async(self) -> Result<AsyncDeviceMonitor, MediaError> {
+        let monitor = AsyncFd::with_interest(self, Interest::READABLE).map_err(poll_error)?;
+        Ok(AsyncDeviceMonitor { monitor })
+    }
+}
+
+impl Stream for AsyncDeviceMonitor {
+    type Item = Result<DeviceEvent, MediaError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().poll_event(cx).map(Some)
+    }
+}
@@ -0,0 +1,273 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use nix::errno::Errno;
+
+use crate::{
+    plan, LinkFlags, LinkSetupError, MediaDevice, MediaDeviceInfo, MediaError, MediaGraph,
+    MediaV2Topology, PadId, Request,
+};
+
+/// The operations of a media device that application code usually depends
+/// on, so it can be tested against a [`MockBackend`] instead of a
+/// `/dev/mediaN` node.
+pub trait MediaBackend {
+    fn device_info(&self) -> Result<MediaDeviceInfo, MediaError>;
+
+    fn topology(&self) -> Result<MediaV2Topology, MediaError>;
+
+    /// See [`MediaDevice::setup_link`].
+    fn setup_link(
+        &self,
+        source_pad_id: PadId,
+        sink_pad_id: PadId,
+        flags: u32,
+    ) -> Result<(), MediaError>;
+
+    fn request_alloc(&self) -> Result<Request, MediaError>;
+}
+
+impl MediaBackend for MediaDevice {
+    fn device_info(&self) -> Result<MediaDeviceInfo, MediaError> {
+        MediaDevice::device_info(self)
+    }
+
+    fn topology(&self) -> Result<MediaV2Topology, MediaError> {
+        MediaDevice::topology(self)
+    }
+
+    fn setup_link(
+        &self,
+        source_pad_id: PadId,
+        sink_pad_id: PadId,
+        flags: u32,
+    ) -> Result<(), MediaError> {
+        MediaDevice::setup_link(self, source_pad_id, sink_pad_id, flags)
+    }
+
+    fn request_alloc(&self) -> Result<Request, MediaError> {
+        self.alloc_request()
+    }
+}
+
+/// A [`MediaBackend`] operation, for scripting failures of a
+/// [`MockBackend`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum MockOp {
+    DeviceInfo,
+    Topology,
+    SetupLink,
+    RequestAlloc,
+}
+
+impl MockOp {
+    fn ioctl(&self) -> &'static str {
+        match self {
+            MockOp::DeviceInfo => "MEDIA_IOC_DEVICE_INFO",
+            MockOp::Topology => "MEDIA_IOC_G_TOPOLOGY",
+            MockOp::SetupLink => "MEDIA_IOC_SETUP_LINK",
+            MockOp::RequestAlloc => "MEDIA_IOC_REQUEST_ALLOC",
+        }
+    }
+}
+
+/// An in-memory [`MediaBackend`] serving a scripted device.
+///
+/// Link setups are applied to the scripted topology like the kernel would,
+/// so a later [`MediaBackend::topology`] reflects them, and are recorded for
+/// [`MockBackend::setup_link_calls`]. Requests are backed by a pipe: they can
+/// be held and polled, but queueing them fails.
+#[derive(Debug)]
+pub struct MockBackend {
+    device_info: MediaDeviceInfo,
+    state: Mutex<MockState>,
+    failures: HashMap<MockOp, Errno>,
+    link_failures: HashMap<(PadId, PadId), LinkSetupError>,
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    topology: MediaV2Topology,
+    setup_link_calls: Vec<(PadId, PadId, u32)>,
+}
+
+impl MockBackend {
+    pub fn new(device_info: MediaDeviceInfo, topology: MediaV2Topology) -> MockBackend {
+        MockBackend {
+            device_info,
+            state: Mutex::new(MockState {
+                topology,
+                setup_link_calls: Vec::new(),
+            }),
+            failures: HashMap::new(),
+            link_failures: HashMap::new(),
+        }
+    }
+
+    /// Makes every call of `op` fail as if the ioctl returned `errno`.
+    pub fn fail(mut self, op: MockOp, errno: Errno) -> MockBackend {
+        self.failures.insert(op, errno);
+        self
+    }
+
+    /// Makes setting up the link between the two pads fail with `cause`,
+    /// e.g. [`LinkSetupError::Streaming`].
+    pub fn fail_link(mut self, source: PadId, sink: PadId, cause: LinkSetupError) -> MockBackend {
+        self.link_failures.insert((source, sink), cause);
+        self
+    }
+
+    /// Reads a fixture of the form
+    ///
+    /// ```json
+    /// {
+    ///   "device_info": {"driver": "vimc", "model": "VIMC MDEV", ...},
+    ///   "topology": {"schema": 1, ...},
+    ///   "failures": {"request_alloc": 25},
+    ///   "link_failures": [{"source": 5, "sink": 9, "errno": 16}]
+    /// }
+    /// ```
+    ///
+    /// `device_info` uses the serde format of [`MediaDeviceInfo`] and
+    /// `topology` the dump format of [`MediaV2Topology::to_json`]. Failures
+    /// are given as errno values. All fields but `topology` are optional.
+    #[cfg(feature = "json")]
+    pub fn from_json(json: &str) -> Result<MockBackend, MediaError> {
+        #[derive(serde::Deserialize)]
+        struct Fixture {
+            device_info: Option<MediaDeviceInfo>,
+            topology: serde_json::Value,
+            #[serde(default)]
+            failures: HashMap<MockOp, i32>,
+            #[serde(default)]
+            link_failures: Vec<LinkFailure>,
+        }
+
+        #[derive(serde::Deserialize)]
+        struct LinkFailure {
+            source: u32,
+            sink: u32,
+            errno: i32,
+        }
+
+        let fixture: Fixture = serde_json::from_str(json).map_err(MediaError::Json)?;
+        let device_info = fixture
+            .device_info
+            .unwrap_or_else(|| MediaDeviceInfo::new("mock", "mock", ""));
+        let topology = MediaV2Topology::from_json(&fixture.topology.to_string())?;
+        let mut mock = MockBackend::new(device_info, topology);
+        for (op, errno) in fixture.failures {
+            mock = mock.fail(op, Errno::from_i32(errno));
+        }
+        for failure in fixture.link_failures {
+            mock = mock.fail_link(
+                PadId(failure.source),
+                PadId(failure.sink),
+                LinkSetupError::from_errno(Errno::from_i32(failure.errno)),
+            );
+        }
+        Ok(mock)
+    }
+
+    /// The `(source, sink, flags)` of every link setup, in call order,
+    /// including the failed ones.
+    pub fn setup_link_calls(&self) -> Vec<(PadId, PadId, u32)> {
+        self.state().setup_link_calls.clone()
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, MockState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn check(&self, op: MockOp) -> Result<(), MediaError> {
+        match self.failures.get(&op) {
+            Some(&errno) => Err(MediaError::ioctl(op.ioctl(), errno)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl MediaBackend for MockBackend {
+    fn device_info(&self) -> Result<MediaDeviceInfo, MediaError> {
+        self.check(MockOp::DeviceInfo)?;
+        Ok(self.device_info.clone())
+    }
+
+    fn topology(&self) -> Result<MediaV2Topology, MediaError> {
+        self.check(MockOp::Topology)?;
+        Ok(self.state().topology.clone())
+    }
+
+    fn setup_link(
+        &self,
+        source_pad_id: PadId,
+        sink_pad_id: PadId,
+        flags: u32,
+    ) -> Result<(), MediaError> {
+        let mut state = self.state();
+        state
+            .setup_link_calls
+            .push((source_pad_id, sink_pad_id, flags));
+        self.check(MockOp::SetupLink)?;
+
+        let graph = MediaGraph::new(state.topology.clone());
+        let index = mock_setup_link(
+            &graph,
+            source_pad_id,
+            sink_pad_id,
+            flags,
+            self.link_failures.get(&(source_pad_id, sink_pad_id)),
+        )?;
+        let link = &mut state.topology.links[index];
+        let mut link_flags = link.link_flags();
+        link_flags.set(LinkFlags::ENABLED, flags & LinkFlags::ENABLED.bits() != 0);
+        link.flags = link_flags.bits();
+        Ok(())
+    }
+
+    fn request_alloc(&self) -> Result<Request, MediaError> {
+        self.check(MockOp::RequestAlloc)?;
+        Request::detached().map_err(|errno| MediaError::ioctl(MockOp::RequestAlloc.ioctl(), errno))
+    }
+}
+
+/// Checks a link setup like the kernel does, returning the index of the link
+/// to update.
+fn mock_setup_link(
+    graph: &MediaGraph,
+    source_pad_id: PadId,
+    sink_pad_id: PadId,
+    flags: u32,
+    failure: Option<&LinkSetupError>,
+) -> Result<usize, MediaError> {
+    let find_pad = |id: PadId| graph.pad(id).ok_or(MediaError::UnknownPad(id));
+    let source = find_pad(source_pad_id)?;
+    let sink = find_pad(sink_pad_id)?;
+    let link_error = |cause| MediaError::LinkSetup {
+        source: plan::pad_ref(graph, source),
+        sink: plan::pad_ref(graph, sink),
+        cause,
+    };
+    if let Some(&cause) = failure {
+        return Err(link_error(cause));
+    }
+    let index = graph
+        .topology()
+        .links
+        .iter()
+        .position(|link| {
+            link.source_pad() == Some(source_pad_id) && link.sink_pad() == Some(sink_pad_id)
+        })
+        .ok_or_else(|| link_error(LinkSetupError::Invalid))?;
+    let link = &graph.topology().links[index];
+    let enabled = flags & LinkFlags::ENABLED.bits() != 0;
+    if link.link_flags().contains(LinkFlags::IMMUTABLE)
+        && link.link_flags().contains(LinkFlags::ENABLED) != enabled
+    {
+        return Err(link_error(LinkSetupError::Invalid));
+    }
+    Ok(index)
+}
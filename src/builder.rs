@@ -0,0 +1,341 @@
+//! Constructors for the kernel model types, for tests and tools working on
+//! topologies that don't come from a device. Fields the kernel may add later
+//! get a default, so these don't break when the UAPI grows.
+
+use std::sync::Arc;
+
+use crate::{
+    EntityFlags, EntityFunction, EntityId, EntityRef, InterfaceId, InterfaceType, KernelStruct,
+    KernelVersion, LinkFlags, LinkId, MediaDeviceInfo, MediaError, MediaGraph, MediaV2Entity,
+    MediaV2Interface, MediaV2IntfDevnode, MediaV2Link, MediaV2Pad, MediaV2Topology, PadFlags,
+    PadId, PadRef,
+};
+
+impl MediaDeviceInfo {
+    pub fn new(
+        driver: impl Into<String>,
+        model: impl Into<String>,
+        bus_info: impl Into<String>,
+    ) -> MediaDeviceInfo {
+        MediaDeviceInfo {
+            driver: driver.into(),
+            model: model.into(),
+            serial: String::new(),
+            bus_info: bus_info.into(),
+            media_version: 0,
+            hw_version: 0,
+            driver_version: 0,
+            driver_raw: None,
+            model_raw: None,
+            serial_raw: None,
+            bus_info_raw: None,
+            kernel: KernelStruct::default(),
+        }
+    }
+
+    pub fn with_serial(mut self, serial: impl Into<String>) -> MediaDeviceInfo {
+        self.serial = serial.into();
+        self
+    }
+
+    pub fn with_media_version(mut self, version: impl Into<KernelVersion>) -> MediaDeviceInfo {
+        self.media_version = u32::from(version.into());
+        self
+    }
+
+    pub fn with_hw_version(mut self, version: u32) -> MediaDeviceInfo {
+        self.hw_version = version;
+        self
+    }
+
+    pub fn with_driver_version(mut self, version: impl Into<KernelVersion>) -> MediaDeviceInfo {
+        self.driver_version = u32::from(version.into());
+        self
+    }
+}
+
+impl MediaV2Entity {
+    pub fn new(id: EntityId, name: impl Into<Arc<str>>, function: EntityFunction) -> MediaV2Entity {
+        MediaV2Entity {
+            id,
+            name: name.into(),
+            function,
+            flags: 0,
+            name_raw: None,
+            kernel: KernelStruct::default(),
+        }
+    }
+
+    pub fn with_flags(mut self, flags: u32) -> MediaV2Entity {
+        self.flags = flags;
+        self
+    }
+}
+
+impl MediaV2IntfDevnode {
+    pub fn new(major: u32, minor: u32) -> MediaV2IntfDevnode {
+        MediaV2IntfDevnode { major, minor }
+    }
+}
+
+impl MediaV2Interface {
+    pub fn new(id: InterfaceId, intf_type: InterfaceType) -> MediaV2Interface {
+        MediaV2Interface {
+            id,
+            intf_type,
+            flags: 0,
+            devnode: None,
+            kernel: KernelStruct::default(),
+        }
+    }
+
+    pub fn with_flags(mut self, flags: u32) -> MediaV2Interface {
+        self.flags = flags;
+        self
+    }
+
+    pub fn with_devnode(mut self, devnode: MediaV2IntfDevnode) -> MediaV2Interface {
+        self.devnode = Some(devnode);
+        self
+    }
+}
+
+impl MediaV2Pad {
+    pub fn new(id: PadId, entity_id: EntityId, index: u32) -> MediaV2Pad {
+        MediaV2Pad {
+            id,
+            entity_id,
+            flags: 0,
+            index,
+            kernel: KernelStruct::default(),
+        }
+    }
+
+    pub fn with_flags(mut self, flags: u32) -> MediaV2Pad {
+        self.flags = flags;
+        self
+    }
+}
+
+impl MediaV2Link {
+    /// A link of any kind, given by `flags`, between the raw object IDs, see
+    /// [`MediaV2Link::source_id`].
+    pub fn new(id: LinkId, source_id: u32, sink_id: u32) -> MediaV2Link {
+        MediaV2Link {
+            id,
+            source_id,
+            sink_id,
+            flags: 0,
+            kernel: KernelStruct::default(),
+        }
+    }
+
+    pub fn with_flags(mut self, flags: u32) -> MediaV2Link {
+        self.flags = flags;
+        self
+    }
+}
+
+impl MediaV2Topology {
+    pub fn new(topology_version: u64) -> MediaV2Topology {
+        MediaV2Topology {
+            topology_version,
+            entities: Vec::new(),
+            interfaces: Vec::new(),
+            pads: Vec::new(),
+            links: Vec::new(),
+        }
+    }
+
+    pub fn with_entity(mut self, entity: MediaV2Entity) -> MediaV2Topology {
+        self.entities.push(entity);
+        self
+    }
+
+    pub fn with_interface(mut self, interface: MediaV2Interface) -> MediaV2Topology {
+        self.interfaces.push(interface);
+        self
+    }
+
+    pub fn with_pad(mut self, pad: MediaV2Pad) -> MediaV2Topology {
+        self.pads.push(pad);
+        self
+    }
+
+    pub fn with_link(mut self, link: MediaV2Link) -> MediaV2Topology {
+        self.links.push(link);
+        self
+    }
+}
+
+/// Builds synthetic topologies fluently, assigning object IDs the way the
+/// kernel does: one counter shared by all objects, with the object type in
+/// the top byte (`0` entities, `1` pads, `2` links, `3` interfaces).
+///
+/// Pads, interfaces and [`TopologyBuilder::link_to`] apply to the entity
+/// added last, and pads are indexed in the order they are added:
+///
+/// ```
+/// use mc_api::{EntityFunction, LinkFlags, PadFlags, TopologyBuilder};
+///
+/// let topology = TopologyBuilder::new()
+///     .entity("imx219 1-0010", EntityFunction::CamSensor)
+///     .pad(PadFlags::SOURCE)
+///     .link_to("csi2", 0, LinkFlags::ENABLED | LinkFlags::IMMUTABLE)
+///     .entity("csi2", EntityFunction::VidIfBridge)
+///     .pad(PadFlags::SINK)
+///     .build()?;
+/// assert_eq!(topology.data_links().count(), 1);
+/// # Ok::<(), mc_api::MediaError>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct TopologyBuilder {
+    topology: MediaV2Topology,
+    next_id: u32,
+    links: Vec<(PadRef, PadRef, LinkFlags)>,
+}
+
+impl TopologyBuilder {
+    pub fn new() -> TopologyBuilder {
+        TopologyBuilder {
+            topology: MediaV2Topology::new(0),
+            next_id: 0,
+            links: Vec::new(),
+        }
+    }
+
+    pub fn topology_version(mut self, topology_version: u64) -> TopologyBuilder {
+        self.topology.topology_version = topology_version;
+        self
+    }
+
+    pub fn entity(mut self, name: &str, function: EntityFunction) -> TopologyBuilder {
+        let id = EntityId(self.next_id(0));
+        self.topology
+            .entities
+            .push(MediaV2Entity::new(id, name, function));
+        self
+    }
+
+    /// Sets the flags of the last entity.
+    pub fn entity_flags(mut self, flags: EntityFlags) -> TopologyBuilder {
+        self.current_entity().flags = flags.bits();
+        self
+    }
+
+    /// Adds a pad to the last entity.
+    pub fn pad(mut self, flags: PadFlags) -> TopologyBuilder {
+        let entity_id = self.current_entity().id;
+        let index = self
+            .topology
+            .pads
+            .iter()
+            .filter(|pad| pad.entity_id == entity_id)
+            .count() as u32;
+        let id = PadId(self.next_id(1));
+        self.topology
+            .pads
+            .push(MediaV2Pad::new(id, entity_id, index).with_flags(flags.bits()));
+        self
+    }
+
+    /// Adds an interface bound to the last entity by an enabled, immutable
+    /// interface link, like the V4L2 core creates them.
+    pub fn interface(
+        mut self,
+        intf_type: InterfaceType,
+        major: u32,
+        minor: u32,
+    ) -> TopologyBuilder {
+        let entity_id = self.current_entity().id;
+        let intf_id = InterfaceId(self.next_id(3));
+        self.topology.interfaces.push(
+            MediaV2Interface::new(intf_id, intf_type)
+                .with_devnode(MediaV2IntfDevnode::new(major, minor)),
+        );
+        let link_id = LinkId(self.next_id(2));
+        let flags = LinkFlags::INTERFACE_LINK | LinkFlags::ENABLED | LinkFlags::IMMUTABLE;
+        self.topology.links.push(
+            MediaV2Link::new(link_id, intf_id.into(), entity_id.into()).with_flags(flags.bits()),
+        );
+        self
+    }
+
+    /// Adds a data link from the pad added last to pad `index` of the entity
+    /// named `sink`, which may be added later.
+    pub fn link_to(mut self, sink: &str, index: u32, flags: LinkFlags) -> TopologyBuilder {
+        let pad = self
+            .topology
+            .pads
+            .last()
+            .expect("TopologyBuilder::link_to called before adding a pad");
+        let entity = self
+            .topology
+            .entities
+            .iter()
+            .find(|entity| entity.id == pad.entity_id)
+            .expect("pads belong to an added entity");
+        let source = PadRef {
+            entity: EntityRef::Name(entity.name.to_string()),
+            index: pad.index,
+        };
+        self.links.push((source, pad_ref(sink, index), flags));
+        self
+    }
+
+    /// Adds a data link between pads given as entity name and pad index.
+    pub fn link(
+        mut self,
+        source: (&str, u32),
+        sink: (&str, u32),
+        flags: LinkFlags,
+    ) -> TopologyBuilder {
+        self.links
+            .push((pad_ref(source.0, source.1), pad_ref(sink.0, sink.1), flags));
+        self
+    }
+
+    /// Resolves the data links, failing with [`MediaError::UnknownEntity`] or
+    /// [`MediaError::UnknownPadRef`] if one names a missing pad.
+    pub fn build(mut self) -> Result<MediaV2Topology, MediaError> {
+        let graph = MediaGraph::new(self.topology.clone());
+        for (source, sink, flags) in std::mem::take(&mut self.links) {
+            let source = source.resolve(&graph)?.id;
+            let sink = sink.resolve(&graph)?.id;
+            let id = LinkId(self.next_id(2));
+            self.topology
+                .links
+                .push(MediaV2Link::new(id, source.into(), sink.into()).with_flags(flags.bits()));
+        }
+        Ok(self.topology)
+    }
+
+    pub fn build_graph(self) -> Result<MediaGraph, MediaError> {
+        self.build().map(MediaGraph::new)
+    }
+
+    fn next_id(&mut self, object_type: u32) -> u32 {
+        self.next_id += 1;
+        (object_type << 24) | self.next_id
+    }
+
+    fn current_entity(&mut self) -> &mut MediaV2Entity {
+        self.topology
+            .entities
+            .last_mut()
+            .expect("TopologyBuilder needs an entity first")
+    }
+}
+
+impl Default for TopologyBuilder {
+    fn default() -> TopologyBuilder {
+        TopologyBuilder::new()
+    }
+}
+
+fn pad_ref(entity: &str, index: u32) -> PadRef {
+    PadRef {
+        entity: EntityRef::Name(entity.to_string()),
+        index,
+    }
+}
@@ -0,0 +1,96 @@
+use std::os::fd::{AsFd, BorrowedFd};
+
+use crate::{MediaDevice, MediaError, MediaV2Topology};
+
+/// A [`MediaDevice`] remembering its last topology snapshot.
+///
+/// Every [`CachedTopology::get`] issues only the cheap
+/// [`MediaDevice::topology_version`] probe; the topology is fetched and
+/// converted again only when the kernel reports a different
+/// `topology_version`. On kernels without `MEDIA_IOC_G_TOPOLOGY`, which have
+/// no version to compare, every call fetches the topology.
+///
+/// The kernel bumps the version when objects are added or removed, not when
+/// `MEDIA_IOC_SETUP_LINK` changes link flags, so a cached snapshot can show
+/// stale link states. Call [`CachedTopology::invalidate`] after changing
+/// links.
+#[derive(Debug)]
+pub struct CachedTopology {
+    device: MediaDevice,
+    snapshot: Option<MediaV2Topology>,
+}
+
+impl CachedTopology {
+    pub fn new(device: MediaDevice) -> CachedTopology {
+        CachedTopology {
+            device,
+            snapshot: None,
+        }
+    }
+
+    /// The current topology, re-read only if it changed since the last call.
+    pub fn get(&mut self) -> Result<&MediaV2Topology, MediaError> {
+        self.refresh()?;
+        Ok(self.snapshot.get_or_insert_with(MediaV2Topology::default))
+    }
+
+    /// Brings the snapshot up to date. Returns whether it was re-read.
+    pub fn refresh(&mut self) -> Result<bool, MediaError> {
+        let probe = match self.device.topology_version() {
+            Ok(probe) => Some(probe),
+            Err(MediaError::UnsupportedKernel { .. }) => None,
+            Err(err) => return Err(err),
+        };
+        let topology = match (probe, &self.snapshot) {
+            (Some(probe), Some(snapshot))
+                if probe.topology_version == snapshot.topology_version() =>
+            {
+                return Ok(false)
+            }
+            // The probe counts fit the graph unless it grows before the
+            // fetch, in which case the hint fetch re-sizes its buffers.
+            (Some(probe), _) => self.device.topology_with_hint(probe.counts, 0)?,
+            (None, _) => self.device.topology()?,
+        };
+        self.snapshot = Some(topology);
+        Ok(true)
+    }
+
+    /// The last snapshot read, without probing the device.
+    pub fn last(&self) -> Option<&MediaV2Topology> {
+        self.snapshot.as_ref()
+    }
+
+    /// Drops the snapshot, so the next [`CachedTopology::get`] re-reads the
+    /// topology.
+    pub fn invalidate(&mut self) {
+        self.snapshot = None;
+    }
+
+    pub fn device(&self) -> &MediaDevice {
+        &self.device
+    }
+
+    pub fn into_device(self) -> MediaDevice {
+        self.device
+    }
+}
+
+impl AsFd for CachedTopology {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.device.as_fd()
+    }
+}
+
+impl From<MediaDevice> for CachedTopology {
+    fn from(device: MediaDevice) -> CachedTopology {
+        CachedTopology::new(device)
+    }
+}
+
+impl MediaDevice {
+    /// Wraps the device into a [`CachedTopology`].
+    pub fn cached(self) -> CachedTopology {
+        CachedTopology::new(self)
+    }
+}
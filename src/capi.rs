@@ -0,0 +1,293 @@
+//! C API of the `capi` feature, exported by the cdylib. `include/mc_api.h` is
+//! generated from this module by cbindgen, as `cbindgen.toml` describes.
+//!
+//! The functions take a media device descriptor opened by the caller and
+//! return 0 on success or a negative errno, like the kernel. Errors without
+//! an errno of their own are reported as `-EIO`, unknown pads as `-ENOENT`.
+//!
+//! Besides `raw`, this is the only module with `unsafe` code: the C
+//! callers' pointers and descriptors can't be checked.
+#![allow(non_camel_case_types)]
+
+use std::{
+    os::{
+        fd::BorrowedFd,
+        raw::{c_char, c_int},
+    },
+    ptr,
+};
+
+use nix::errno::Errno;
+
+use crate::{
+    get_device_info_from_fd, get_topology_from_fd, setup_link_from_fd, MediaError, MediaV2Topology,
+    PadId,
+};
+
+/// Device information, `struct media_device_info` with the strings
+/// NUL-terminated.
+#[repr(C)]
+pub struct mc_device_info {
+    pub driver: [c_char; 16],
+    pub model: [c_char; 32],
+    pub serial: [c_char; 40],
+    pub bus_info: [c_char; 32],
+    pub media_version: u32,
+    pub hw_revision: u32,
+    pub driver_version: u32,
+}
+
+#[repr(C)]
+pub struct mc_entity {
+    pub id: u32,
+    /// NUL-terminated.
+    pub name: [c_char; 64],
+    /// `MEDIA_ENT_F_*`.
+    pub function: u32,
+    pub flags: u32,
+}
+
+#[repr(C)]
+pub struct mc_interface {
+    pub id: u32,
+    /// `MEDIA_INTF_T_*`.
+    pub intf_type: u32,
+    pub flags: u32,
+    /// Device node numbers, 0 for interfaces without one.
+    pub major: u32,
+    pub minor: u32,
+}
+
+#[repr(C)]
+pub struct mc_pad {
+    pub id: u32,
+    pub entity_id: u32,
+    pub flags: u32,
+    pub index: u32,
+}
+
+#[repr(C)]
+pub struct mc_link {
+    pub id: u32,
+    pub source_id: u32,
+    pub sink_id: u32,
+    pub flags: u32,
+}
+
+/// Graph of a media device, as `MEDIA_IOC_G_TOPOLOGY` reports it. Allocated
+/// by `mc_get_topology`, released with `mc_free_topology`.
+#[repr(C)]
+pub struct mc_topology {
+    pub topology_version: u64,
+    pub entities: *mut mc_entity,
+    pub num_entities: usize,
+    pub interfaces: *mut mc_interface,
+    pub num_interfaces: usize,
+    pub pads: *mut mc_pad,
+    pub num_pads: usize,
+    pub links: *mut mc_link,
+    pub num_links: usize,
+}
+
+/// Fills `info` with the information of the media device open as `fd`.
+///
+/// # Safety
+///
+/// `fd` must be an open descriptor or negative, which fails with `-EBADF`,
+/// and `info` valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn mc_get_device_info(fd: c_int, info: *mut mc_device_info) -> c_int {
+    if fd < 0 {
+        return -(Errno::EBADF as c_int);
+    }
+    if info.is_null() {
+        return -(Errno::EINVAL as c_int);
+    }
+    let device_info = match get_device_info_from_fd(BorrowedFd::borrow_raw(fd)) {
+        Ok(device_info) => device_info,
+        Err(err) => return error_code(&err),
+    };
+    info.write(mc_device_info {
+        driver: c_string(device_info.driver_bytes()),
+        model: c_string(device_info.model_bytes()),
+        serial: c_string(device_info.serial_bytes()),
+        bus_info: c_string(device_info.bus_info_bytes()),
+        media_version: device_info.media_version().into(),
+        hw_revision: device_info.hw_version(),
+        driver_version: device_info.driver_version().into(),
+    });
+    0
+}
+
+/// Reads the topology of the media device open as `fd` into a new
+/// `mc_topology`, stored in `*topology`.
+///
+/// # Safety
+///
+/// `fd` must be an open descriptor or negative, which fails with `-EBADF`,
+/// and `topology` valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn mc_get_topology(fd: c_int, topology: *mut *mut mc_topology) -> c_int {
+    if fd < 0 {
+        return -(Errno::EBADF as c_int);
+    }
+    if topology.is_null() {
+        return -(Errno::EINVAL as c_int);
+    }
+    match get_topology_from_fd(BorrowedFd::borrow_raw(fd)) {
+        Ok(media_topology) => {
+            topology.write(Box::into_raw(Box::new(to_c_topology(&media_topology))));
+            0
+        }
+        Err(err) => error_code(&err),
+    }
+}
+
+/// Releases a topology returned by `mc_get_topology`. Does nothing if
+/// `topology` is NULL.
+///
+/// # Safety
+///
+/// `topology` must come from `mc_get_topology` and not be released yet.
+#[no_mangle]
+pub unsafe extern "C" fn mc_free_topology(topology: *mut mc_topology) {
+    if topology.is_null() {
+        return;
+    }
+    let topology = Box::from_raw(topology);
+    free_slice(topology.entities, topology.num_entities);
+    free_slice(topology.interfaces, topology.num_interfaces);
+    free_slice(topology.pads, topology.num_pads);
+    free_slice(topology.links, topology.num_links);
+}
+
+/// Sets the `MEDIA_LNK_FL_*` `flags` of the data link between two pads,
+/// addressed by ID. Fails with `-EBADF` if `fd` was opened read-only.
+///
+/// # Safety
+///
+/// `fd` must be an open descriptor or negative, which fails with `-EBADF`.
+#[no_mangle]
+pub unsafe extern "C" fn mc_setup_link(
+    fd: c_int,
+    source_pad_id: u32,
+    sink_pad_id: u32,
+    flags: u32,
+) -> c_int {
+    if fd < 0 {
+        return -(Errno::EBADF as c_int);
+    }
+    match setup_link_from_fd(
+        BorrowedFd::borrow_raw(fd),
+        PadId(source_pad_id),
+        PadId(sink_pad_id),
+        flags,
+    ) {
+        Ok(()) => 0,
+        Err(err) => error_code(&err),
+    }
+}
+
+fn error_code(err: &MediaError) -> c_int {
+    let errno = match err {
+        MediaError::UnknownPad(_) => Errno::ENOENT,
+        err => err.errno().unwrap_or(Errno::EIO),
+    };
+    -(errno as c_int)
+}
+
+/// `bytes` NUL-terminated, truncated to fit.
+fn c_string<const N: usize>(bytes: &[u8]) -> [c_char; N] {
+    let mut string = [0; N];
+    for (c, &b) in string.iter_mut().zip(bytes.iter().take(N - 1)) {
+        *c = b as c_char;
+    }
+    string
+}
+
+fn to_c_topology(topology: &MediaV2Topology) -> mc_topology {
+    let (entities, num_entities) = into_raw_slice(
+        topology
+            .entities
+            .iter()
+            .map(|entity| mc_entity {
+                id: entity.id().into(),
+                name: c_string(entity.name_bytes()),
+                function: entity.function().into(),
+                flags: entity.flags(),
+            })
+            .collect(),
+    );
+    let (interfaces, num_interfaces) = into_raw_slice(
+        topology
+            .interfaces
+            .iter()
+            .map(|intf| mc_interface {
+                id: intf.id().into(),
+                intf_type: intf.intf_type().into(),
+                flags: intf.flags(),
+                major: intf.devnode().map_or(0, |devnode| devnode.major()),
+                minor: intf.devnode().map_or(0, |devnode| devnode.minor()),
+            })
+            .collect(),
+    );
+    let (pads, num_pads) = into_raw_slice(
+        topology
+            .pads
+            .iter()
+            .map(|pad| mc_pad {
+                id: pad.id().into(),
+                entity_id: pad.entity_id().into(),
+                flags: pad.flags(),
+                index: pad.index(),
+            })
+            .collect(),
+    );
+    let (links, num_links) = into_raw_slice(
+        topology
+            .links
+            .iter()
+            .map(|link| mc_link {
+                id: link.id().into(),
+                source_id: link.source_id(),
+                sink_id: link.sink_id(),
+                flags: link.flags(),
+            })
+            .collect(),
+    );
+    mc_topology {
+        topology_version: topology.topology_version(),
+        entities,
+        num_entities,
+        interfaces,
+        num_interfaces,
+        pads,
+        num_pads,
+        links,
+        num_links,
+    }
+}
+
+fn into_raw_slice<T>(items: Vec<T>) -> (*mut T, usize) {
+    let len = items.len();
+    (Box::into_raw(items.into_boxed_slice()).cast(), len)
+}
+
+/// # Safety
+///
+/// `ptr` and `len` must come from [`into_raw_slice`], once.
+unsafe fn free_slice<T>(ptr: *mut T, len: usize) {
+    drop(Box::from_raw(ptr::slice_from_raw_parts_mut(ptr, len)));
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn header_is_up_to_date() {
+        assert!(
+            include_str!("../include/mc_api.h")
+                == include_str!(concat!(env!("OUT_DIR"), "/mc_api.h")),
+            "include/mc_api.h is stale, regenerate it as cbindgen.toml says"
+        );
+    }
+}
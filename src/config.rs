@@ -0,0 +1,169 @@
+use crate::{
+    EntityRef, LinkChange, LinkFlags, LinkMismatch, LinkRequest, MediaDevice, MediaError,
+    PadFormatProperties, PadRef, SyntaxError,
+};
+
+/// Desired state of a pipeline, identified by entity names and pad indexes
+/// so it can be written once per board and applied on every boot.
+///
+/// In TOML:
+///
+/// ```toml
+/// [[links]]
+/// source = "imx219 1-0010"
+/// source_pad = 0
+/// sink = "csi"
+/// sink_pad = 0
+/// enabled = true
+///
+/// [[formats]]
+/// entity = "imx219 1-0010"
+/// pad = 0
+/// format = "fmt:SRGGB10_1X10/3280x2464 field:none"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct PipelineConfig {
+    pub links: Vec<LinkConfig>,
+    pub formats: Vec<PadFormatConfig>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LinkConfig {
+    pub source: String,
+    pub source_pad: u32,
+    pub sink: String,
+    pub sink_pad: u32,
+    pub enabled: bool,
+}
+
+impl LinkConfig {
+    pub fn to_request(&self) -> LinkRequest {
+        let flags = if self.enabled {
+            LinkFlags::ENABLED
+        } else {
+            LinkFlags::empty()
+        };
+        LinkRequest {
+            source: PadRef {
+                entity: EntityRef::Name(self.source.clone()),
+                index: self.source_pad,
+            },
+            sink: PadRef {
+                entity: EntityRef::Name(self.sink.clone()),
+                index: self.sink_pad,
+            },
+            flags: flags.bits(),
+        }
+    }
+}
+
+/// Format of a pad, in the media-ctl `-V` property syntax.
+///
+/// Pad formats are V4L2 subdevice state, which [`MediaDevice::apply`]
+/// leaves to the caller, e.g. through a `Subdev` with the `subdev` feature.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PadFormatConfig {
+    pub entity: String,
+    pub pad: u32,
+    pub format: String,
+}
+
+impl PadFormatConfig {
+    pub fn pad(&self) -> PadRef {
+        PadRef {
+            entity: EntityRef::Name(self.entity.clone()),
+            index: self.pad,
+        }
+    }
+
+    pub fn properties(&self) -> Result<PadFormatProperties, SyntaxError> {
+        self.format.parse()
+    }
+}
+
+/// How [`MediaDevice::apply_with`] applies a configuration.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct ApplyOptions {
+    /// Restore the changed links if a link setup fails, see
+    /// [`MediaDevice::apply_links_transaction`].
+    pub transaction: bool,
+    /// Re-read the topology afterwards and report the links that don't have
+    /// the requested state.
+    pub verify: bool,
+}
+
+/// Outcome of [`MediaDevice::apply_with`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ApplyReport {
+    /// Links that were changed, in order.
+    pub changes: Vec<LinkChange>,
+    /// Requested links not in the requested state after applying, always
+    /// empty unless [`ApplyOptions::verify`] is set.
+    pub mismatches: Vec<LinkMismatch>,
+}
+
+impl ApplyReport {
+    pub fn is_verified(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+impl PipelineConfig {
+    #[cfg(feature = "toml")]
+    pub fn from_toml(toml: &str) -> Result<PipelineConfig, MediaError> {
+        toml::from_str(toml).map_err(MediaError::Toml)
+    }
+
+    #[cfg(feature = "yaml")]
+    pub fn from_yaml(yaml: &str) -> Result<PipelineConfig, MediaError> {
+        serde_yaml::from_str(yaml).map_err(MediaError::Yaml)
+    }
+
+    pub fn link_requests(&self) -> Vec<LinkRequest> {
+        self.links.iter().map(LinkConfig::to_request).collect()
+    }
+}
+
+impl MediaDevice {
+    /// Sets up the links of `config`, skipping those already in the desired
+    /// state, see [`MediaDevice::apply_links`].
+    pub fn apply(&self, config: &PipelineConfig) -> Result<Vec<LinkChange>, MediaError> {
+        self.apply_links(&config.link_requests())
+    }
+
+    /// Like [`MediaDevice::apply`], restoring the changed links if a link
+    /// setup fails, see [`MediaDevice::apply_links_transaction`].
+    pub fn apply_transaction(
+        &self,
+        config: &PipelineConfig,
+    ) -> Result<Vec<LinkChange>, MediaError> {
+        self.apply_links_transaction(&config.link_requests())
+    }
+
+    /// Applies `config` as selected by `options`.
+    pub fn apply_with(
+        &self,
+        config: &PipelineConfig,
+        options: ApplyOptions,
+    ) -> Result<ApplyReport, MediaError> {
+        let links = config.link_requests();
+        let changes = if options.transaction {
+            self.apply_links_transaction(&links)?
+        } else {
+            self.apply_links(&links)?
+        };
+        let mismatches = if options.verify {
+            self.verify_links(&links)?
+        } else {
+            Vec::new()
+        };
+        Ok(ApplyReport {
+            changes,
+            mismatches,
+        })
+    }
+}
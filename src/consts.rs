@@ -0,0 +1,195 @@
+//! Raw values of the media controller UAPI headers (`linux/media.h` and
+//! `linux/media-bus-format.h`), for code that needs the exact kernel
+//! constants. The crate's typed wrappers,
+//! such as [`EntityFunction`](crate::EntityFunction) and
+//! [`LinkFlags`](crate::LinkFlags), are built from these.
+
+/// Entity functions.
+pub use crate::media_ffi::{
+    MEDIA_ENT_F_ATV_DECODER, MEDIA_ENT_F_AUDIO_CAPTURE, MEDIA_ENT_F_AUDIO_MIXER,
+    MEDIA_ENT_F_AUDIO_PLAYBACK, MEDIA_ENT_F_BASE, MEDIA_ENT_F_CAM_SENSOR, MEDIA_ENT_F_DTV_CA,
+    MEDIA_ENT_F_DTV_DECODER, MEDIA_ENT_F_DTV_DEMOD, MEDIA_ENT_F_DTV_NET_DECAP,
+    MEDIA_ENT_F_DV_DECODER, MEDIA_ENT_F_DV_ENCODER, MEDIA_ENT_F_FLASH, MEDIA_ENT_F_IF_AUD_DECODER,
+    MEDIA_ENT_F_IF_VID_DECODER, MEDIA_ENT_F_IO_DTV, MEDIA_ENT_F_IO_SWRADIO, MEDIA_ENT_F_IO_V4L,
+    MEDIA_ENT_F_IO_VBI, MEDIA_ENT_F_LENS, MEDIA_ENT_F_OLD_BASE, MEDIA_ENT_F_OLD_SUBDEV_BASE,
+    MEDIA_ENT_F_PROC_VIDEO_COMPOSER, MEDIA_ENT_F_PROC_VIDEO_DECODER,
+    MEDIA_ENT_F_PROC_VIDEO_ENCODER, MEDIA_ENT_F_PROC_VIDEO_ISP, MEDIA_ENT_F_PROC_VIDEO_LUT,
+    MEDIA_ENT_F_PROC_VIDEO_PIXEL_ENC_CONV, MEDIA_ENT_F_PROC_VIDEO_PIXEL_FORMATTER,
+    MEDIA_ENT_F_PROC_VIDEO_SCALER, MEDIA_ENT_F_PROC_VIDEO_STATISTICS, MEDIA_ENT_F_TS_DEMUX,
+    MEDIA_ENT_F_TUNER, MEDIA_ENT_F_UNKNOWN, MEDIA_ENT_F_V4L2_SUBDEV_UNKNOWN,
+    MEDIA_ENT_F_VID_IF_BRIDGE, MEDIA_ENT_F_VID_MUX,
+};
+
+// The header only defines the connector functions for in-kernel use, but
+// drivers report them to userspace all the same.
+pub const MEDIA_ENT_F_CONN_RF: u32 = MEDIA_ENT_F_BASE + 0x30001;
+pub const MEDIA_ENT_F_CONN_SVIDEO: u32 = MEDIA_ENT_F_BASE + 0x30002;
+pub const MEDIA_ENT_F_CONN_COMPOSITE: u32 = MEDIA_ENT_F_BASE + 0x30003;
+
+/// Entity flags.
+pub use crate::media_ffi::{MEDIA_ENT_FL_CONNECTOR, MEDIA_ENT_FL_DEFAULT};
+
+/// Pad flags.
+pub use crate::media_ffi::{MEDIA_PAD_FL_MUST_CONNECT, MEDIA_PAD_FL_SINK, MEDIA_PAD_FL_SOURCE};
+
+/// Link flags and link types.
+pub use crate::media_ffi::{
+    MEDIA_LNK_FL_ANCILLARY_LINK, MEDIA_LNK_FL_DATA_LINK, MEDIA_LNK_FL_DYNAMIC,
+    MEDIA_LNK_FL_ENABLED, MEDIA_LNK_FL_IMMUTABLE, MEDIA_LNK_FL_INTERFACE_LINK,
+    MEDIA_LNK_FL_LINK_TYPE,
+};
+
+/// Interface types.
+pub use crate::media_ffi::{
+    MEDIA_INTF_T_ALSA_BASE, MEDIA_INTF_T_ALSA_COMPRESS, MEDIA_INTF_T_ALSA_CONTROL,
+    MEDIA_INTF_T_ALSA_HWDEP, MEDIA_INTF_T_ALSA_PCM_CAPTURE, MEDIA_INTF_T_ALSA_PCM_PLAYBACK,
+    MEDIA_INTF_T_ALSA_RAWMIDI, MEDIA_INTF_T_ALSA_SEQUENCER, MEDIA_INTF_T_ALSA_TIMER,
+    MEDIA_INTF_T_DVB_BASE, MEDIA_INTF_T_DVB_CA, MEDIA_INTF_T_DVB_DEMUX, MEDIA_INTF_T_DVB_DVR,
+    MEDIA_INTF_T_DVB_FE, MEDIA_INTF_T_DVB_NET, MEDIA_INTF_T_V4L_BASE, MEDIA_INTF_T_V4L_RADIO,
+    MEDIA_INTF_T_V4L_SUBDEV, MEDIA_INTF_T_V4L_SWRADIO, MEDIA_INTF_T_V4L_TOUCH,
+    MEDIA_INTF_T_V4L_VBI, MEDIA_INTF_T_V4L_VIDEO,
+};
+
+/// Entity types of the legacy `MEDIA_IOC_ENUM_ENTITIES` API.
+pub use crate::media_ffi::{
+    MEDIA_ENT_SUBTYPE_MASK, MEDIA_ENT_TYPE_MASK, MEDIA_ENT_TYPE_SHIFT, MEDIA_ENT_T_DEVNODE,
+    MEDIA_ENT_T_DEVNODE_ALSA, MEDIA_ENT_T_DEVNODE_DVB, MEDIA_ENT_T_DEVNODE_FB,
+    MEDIA_ENT_T_DEVNODE_UNKNOWN, MEDIA_ENT_T_DEVNODE_V4L, MEDIA_ENT_T_UNKNOWN,
+    MEDIA_ENT_T_V4L2_SUBDEV, MEDIA_ENT_T_V4L2_SUBDEV_DECODER, MEDIA_ENT_T_V4L2_SUBDEV_FLASH,
+    MEDIA_ENT_T_V4L2_SUBDEV_LENS, MEDIA_ENT_T_V4L2_SUBDEV_SENSOR, MEDIA_ENT_T_V4L2_SUBDEV_TUNER,
+    MEDIA_ENT_T_V4L2_VIDEO,
+};
+
+pub use crate::media_ffi::{MEDIA_API_VERSION, MEDIA_ENT_ID_FLAG_NEXT};
+
+/// Media bus formats (`linux/media-bus-format.h`), the codes of subdevice
+/// pad formats.
+pub const MEDIA_BUS_FMT_FIXED: u32 = 0x0001;
+
+// RGB
+pub const MEDIA_BUS_FMT_RGB444_1X12: u32 = 0x1016;
+pub const MEDIA_BUS_FMT_RGB444_2X8_PADHI_BE: u32 = 0x1001;
+pub const MEDIA_BUS_FMT_RGB444_2X8_PADHI_LE: u32 = 0x1002;
+pub const MEDIA_BUS_FMT_RGB555_2X8_PADHI_BE: u32 = 0x1003;
+pub const MEDIA_BUS_FMT_RGB555_2X8_PADHI_LE: u32 = 0x1004;
+pub const MEDIA_BUS_FMT_RGB565_1X16: u32 = 0x1017;
+pub const MEDIA_BUS_FMT_BGR565_2X8_BE: u32 = 0x1005;
+pub const MEDIA_BUS_FMT_BGR565_2X8_LE: u32 = 0x1006;
+pub const MEDIA_BUS_FMT_RGB565_2X8_BE: u32 = 0x1007;
+pub const MEDIA_BUS_FMT_RGB565_2X8_LE: u32 = 0x1008;
+pub const MEDIA_BUS_FMT_RGB666_1X18: u32 = 0x1009;
+pub const MEDIA_BUS_FMT_RBG888_1X24: u32 = 0x100e;
+pub const MEDIA_BUS_FMT_RGB666_1X24_CPADHI: u32 = 0x1015;
+pub const MEDIA_BUS_FMT_RGB666_1X7X3_SPWG: u32 = 0x1010;
+pub const MEDIA_BUS_FMT_BGR888_1X24: u32 = 0x1013;
+pub const MEDIA_BUS_FMT_BGR888_3X8: u32 = 0x101b;
+pub const MEDIA_BUS_FMT_GBR888_1X24: u32 = 0x1014;
+pub const MEDIA_BUS_FMT_RGB888_1X24: u32 = 0x100a;
+pub const MEDIA_BUS_FMT_RGB888_2X12_BE: u32 = 0x100b;
+pub const MEDIA_BUS_FMT_RGB888_2X12_LE: u32 = 0x100c;
+pub const MEDIA_BUS_FMT_RGB888_3X8: u32 = 0x101c;
+pub const MEDIA_BUS_FMT_RGB888_3X8_DELTA: u32 = 0x101d;
+pub const MEDIA_BUS_FMT_RGB888_1X7X4_SPWG: u32 = 0x1011;
+pub const MEDIA_BUS_FMT_RGB888_1X7X4_JEIDA: u32 = 0x1012;
+pub const MEDIA_BUS_FMT_RGB666_1X30_CPADLO: u32 = 0x101e;
+pub const MEDIA_BUS_FMT_RGB888_1X30_CPADLO: u32 = 0x101f;
+pub const MEDIA_BUS_FMT_ARGB8888_1X32: u32 = 0x100d;
+pub const MEDIA_BUS_FMT_RGB888_1X32_PADHI: u32 = 0x100f;
+pub const MEDIA_BUS_FMT_RGB101010_1X30: u32 = 0x1018;
+pub const MEDIA_BUS_FMT_RGB666_1X36_CPADLO: u32 = 0x1020;
+pub const MEDIA_BUS_FMT_RGB888_1X36_CPADLO: u32 = 0x1021;
+pub const MEDIA_BUS_FMT_RGB121212_1X36: u32 = 0x1019;
+pub const MEDIA_BUS_FMT_RGB161616_1X48: u32 = 0x101a;
+
+// YUV, including grey
+pub const MEDIA_BUS_FMT_Y8_1X8: u32 = 0x2001;
+pub const MEDIA_BUS_FMT_UV8_1X8: u32 = 0x2015;
+pub const MEDIA_BUS_FMT_UYVY8_1_5X8: u32 = 0x2002;
+pub const MEDIA_BUS_FMT_VYUY8_1_5X8: u32 = 0x2003;
+pub const MEDIA_BUS_FMT_YUYV8_1_5X8: u32 = 0x2004;
+pub const MEDIA_BUS_FMT_YVYU8_1_5X8: u32 = 0x2005;
+pub const MEDIA_BUS_FMT_UYVY8_2X8: u32 = 0x2006;
+pub const MEDIA_BUS_FMT_VYUY8_2X8: u32 = 0x2007;
+pub const MEDIA_BUS_FMT_YUYV8_2X8: u32 = 0x2008;
+pub const MEDIA_BUS_FMT_YVYU8_2X8: u32 = 0x2009;
+pub const MEDIA_BUS_FMT_Y10_1X10: u32 = 0x200a;
+pub const MEDIA_BUS_FMT_Y10_2X8_PADHI_LE: u32 = 0x202c;
+pub const MEDIA_BUS_FMT_UYVY10_2X10: u32 = 0x2018;
+pub const MEDIA_BUS_FMT_VYUY10_2X10: u32 = 0x2019;
+pub const MEDIA_BUS_FMT_YUYV10_2X10: u32 = 0x200b;
+pub const MEDIA_BUS_FMT_YVYU10_2X10: u32 = 0x200c;
+pub const MEDIA_BUS_FMT_Y12_1X12: u32 = 0x2013;
+pub const MEDIA_BUS_FMT_UYVY12_2X12: u32 = 0x201c;
+pub const MEDIA_BUS_FMT_VYUY12_2X12: u32 = 0x201d;
+pub const MEDIA_BUS_FMT_YUYV12_2X12: u32 = 0x201e;
+pub const MEDIA_BUS_FMT_YVYU12_2X12: u32 = 0x201f;
+pub const MEDIA_BUS_FMT_Y14_1X14: u32 = 0x202d;
+pub const MEDIA_BUS_FMT_UYVY8_1X16: u32 = 0x200f;
+pub const MEDIA_BUS_FMT_VYUY8_1X16: u32 = 0x2010;
+pub const MEDIA_BUS_FMT_YUYV8_1X16: u32 = 0x2011;
+pub const MEDIA_BUS_FMT_YVYU8_1X16: u32 = 0x2012;
+pub const MEDIA_BUS_FMT_YDYUYDYV8_1X16: u32 = 0x2014;
+pub const MEDIA_BUS_FMT_UYVY10_1X20: u32 = 0x201a;
+pub const MEDIA_BUS_FMT_VYUY10_1X20: u32 = 0x201b;
+pub const MEDIA_BUS_FMT_YUYV10_1X20: u32 = 0x200d;
+pub const MEDIA_BUS_FMT_YVYU10_1X20: u32 = 0x200e;
+pub const MEDIA_BUS_FMT_VUY8_1X24: u32 = 0x2024;
+pub const MEDIA_BUS_FMT_YUV8_1X24: u32 = 0x2025;
+pub const MEDIA_BUS_FMT_UYYVYY8_0_5X24: u32 = 0x2026;
+pub const MEDIA_BUS_FMT_UYVY12_1X24: u32 = 0x2020;
+pub const MEDIA_BUS_FMT_VYUY12_1X24: u32 = 0x2021;
+pub const MEDIA_BUS_FMT_YUYV12_1X24: u32 = 0x2022;
+pub const MEDIA_BUS_FMT_YVYU12_1X24: u32 = 0x2023;
+pub const MEDIA_BUS_FMT_YUV10_1X30: u32 = 0x2016;
+pub const MEDIA_BUS_FMT_UYYVYY10_0_5X30: u32 = 0x2027;
+pub const MEDIA_BUS_FMT_AYUV8_1X32: u32 = 0x2017;
+pub const MEDIA_BUS_FMT_UYYVYY12_0_5X36: u32 = 0x2028;
+pub const MEDIA_BUS_FMT_YUV12_1X36: u32 = 0x2029;
+pub const MEDIA_BUS_FMT_YUV16_1X48: u32 = 0x202a;
+pub const MEDIA_BUS_FMT_UYYVYY16_0_5X48: u32 = 0x202b;
+
+// Bayer
+pub const MEDIA_BUS_FMT_SBGGR8_1X8: u32 = 0x3001;
+pub const MEDIA_BUS_FMT_SGBRG8_1X8: u32 = 0x3013;
+pub const MEDIA_BUS_FMT_SGRBG8_1X8: u32 = 0x3002;
+pub const MEDIA_BUS_FMT_SRGGB8_1X8: u32 = 0x3014;
+pub const MEDIA_BUS_FMT_SBGGR10_ALAW8_1X8: u32 = 0x3015;
+pub const MEDIA_BUS_FMT_SGBRG10_ALAW8_1X8: u32 = 0x3016;
+pub const MEDIA_BUS_FMT_SGRBG10_ALAW8_1X8: u32 = 0x3017;
+pub const MEDIA_BUS_FMT_SRGGB10_ALAW8_1X8: u32 = 0x3018;
+pub const MEDIA_BUS_FMT_SBGGR10_DPCM8_1X8: u32 = 0x300b;
+pub const MEDIA_BUS_FMT_SGBRG10_DPCM8_1X8: u32 = 0x300c;
+pub const MEDIA_BUS_FMT_SGRBG10_DPCM8_1X8: u32 = 0x3009;
+pub const MEDIA_BUS_FMT_SRGGB10_DPCM8_1X8: u32 = 0x300d;
+pub const MEDIA_BUS_FMT_SBGGR10_2X8_PADHI_BE: u32 = 0x3003;
+pub const MEDIA_BUS_FMT_SBGGR10_2X8_PADHI_LE: u32 = 0x3004;
+pub const MEDIA_BUS_FMT_SBGGR10_2X8_PADLO_BE: u32 = 0x3005;
+pub const MEDIA_BUS_FMT_SBGGR10_2X8_PADLO_LE: u32 = 0x3006;
+pub const MEDIA_BUS_FMT_SBGGR10_1X10: u32 = 0x3007;
+pub const MEDIA_BUS_FMT_SGBRG10_1X10: u32 = 0x300e;
+pub const MEDIA_BUS_FMT_SGRBG10_1X10: u32 = 0x300a;
+pub const MEDIA_BUS_FMT_SRGGB10_1X10: u32 = 0x300f;
+pub const MEDIA_BUS_FMT_SBGGR12_1X12: u32 = 0x3008;
+pub const MEDIA_BUS_FMT_SGBRG12_1X12: u32 = 0x3010;
+pub const MEDIA_BUS_FMT_SGRBG12_1X12: u32 = 0x3011;
+pub const MEDIA_BUS_FMT_SRGGB12_1X12: u32 = 0x3012;
+pub const MEDIA_BUS_FMT_SBGGR14_1X14: u32 = 0x3019;
+pub const MEDIA_BUS_FMT_SGBRG14_1X14: u32 = 0x301a;
+pub const MEDIA_BUS_FMT_SGRBG14_1X14: u32 = 0x301b;
+pub const MEDIA_BUS_FMT_SRGGB14_1X14: u32 = 0x301c;
+pub const MEDIA_BUS_FMT_SBGGR16_1X16: u32 = 0x301d;
+pub const MEDIA_BUS_FMT_SGBRG16_1X16: u32 = 0x301e;
+pub const MEDIA_BUS_FMT_SGRBG16_1X16: u32 = 0x301f;
+pub const MEDIA_BUS_FMT_SRGGB16_1X16: u32 = 0x3020;
+
+// JPEG
+pub const MEDIA_BUS_FMT_JPEG_1X8: u32 = 0x4001;
+
+// Vendor specific
+pub const MEDIA_BUS_FMT_S5C_UYVY_JPEG_1X8: u32 = 0x5001;
+
+// HSV
+pub const MEDIA_BUS_FMT_AHSV8888_1X32: u32 = 0x6001;
+
+// Metadata
+pub const MEDIA_BUS_FMT_METADATA_FIXED: u32 = 0x7001;
@@ -0,0 +1,99 @@
+use std::{
+    fs,
+    os::fd::{AsRawFd, BorrowedFd},
+    path::{Path, PathBuf},
+};
+
+use nix::sys::stat::{fstat, major, minor, stat};
+
+use crate::{
+    discovery::dev_media_paths, MediaDevice, MediaError, MediaGraph, MediaV2Entity,
+    MediaV2Interface, MediaV2IntfDevnode,
+};
+
+/// The media device, interface and entity behind a device node such as
+/// `/dev/videoN` or `/dev/v4l-subdevN`.
+#[derive(Debug)]
+pub struct DevnodeOwner {
+    pub media_path: PathBuf,
+    pub device: MediaDevice,
+    pub interface: MediaV2Interface,
+    /// Entity controlled by the interface, if the driver links one.
+    pub entity: Option<MediaV2Entity>,
+}
+
+impl MediaV2IntfDevnode {
+    /// Path of the character device in `/dev`, looked up in sysfs. `None` if
+    /// the device isn't registered (anymore).
+    pub fn path(&self) -> Option<PathBuf> {
+        let uevent = fs::read_to_string(format!(
+            "/sys/dev/char/{}:{}/uevent",
+            self.major, self.minor
+        ))
+        .ok()?;
+        let name = uevent
+            .lines()
+            .find_map(|line| line.strip_prefix("DEVNAME="))?;
+        Some(Path::new("/dev").join(name))
+    }
+}
+
+impl MediaGraph {
+    pub fn interface_by_devnode(&self, devnode: &MediaV2IntfDevnode) -> Option<&MediaV2Interface> {
+        self.topology().interfaces.iter().find(|intf| {
+            intf.devnode
+                .as_ref()
+                .is_some_and(|d| d.major == devnode.major && d.minor == devnode.minor)
+        })
+    }
+
+    /// Entity controlled by an interface.
+    pub fn interface_entity(&self, intf: &MediaV2Interface) -> Option<&MediaV2Entity> {
+        self.topology()
+            .interface_links()
+            .find(|link| link.source_interface() == Some(intf.id))
+            .and_then(|link| self.entity(link.sink_entity()?))
+    }
+}
+
+/// Finds which media device exposes the device node at `path`, by matching
+/// its major/minor numbers against the interface devnodes of every
+/// `/dev/mediaN` node. Media devices that can't be opened are skipped.
+pub fn find_devnode_owner(path: impl AsRef<Path>) -> Result<Option<DevnodeOwner>, MediaError> {
+    let path = path.as_ref();
+    let st = stat(path).map_err(|errno| MediaError::Open {
+        path: path.to_path_buf(),
+        source: errno.into(),
+    })?;
+    find_devnode_owner_by_rdev(st.st_rdev)
+}
+
+pub fn find_devnode_owner_from_fd(fd: BorrowedFd) -> Result<Option<DevnodeOwner>, MediaError> {
+    let st = fstat(fd.as_raw_fd()).map_err(|errno| MediaError::ioctl("fstat", errno))?;
+    find_devnode_owner_by_rdev(st.st_rdev)
+}
+
+fn find_devnode_owner_by_rdev(rdev: u64) -> Result<Option<DevnodeOwner>, MediaError> {
+    let devnode = MediaV2IntfDevnode {
+        major: major(rdev) as u32,
+        minor: minor(rdev) as u32,
+    };
+    for media_path in dev_media_paths()? {
+        let Ok(device) = MediaDevice::open(&media_path) else {
+            continue;
+        };
+        let graph = MediaGraph::new(device.topology()?);
+        let Some(interface) = graph.interface_by_devnode(&devnode) else {
+            continue;
+        };
+        let entity = graph.interface_entity(interface).cloned();
+        let interface = interface.clone();
+        return Ok(Some(DevnodeOwner {
+            media_path,
+            device,
+            interface,
+            entity,
+        }));
+    }
+    Ok(None)
+}
@@ -0,0 +1,183 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    hash::Hash,
+};
+
+use crate::{
+    LinkFlags, LinkId, MediaV2Entity, MediaV2Interface, MediaV2Link, MediaV2Pad, MediaV2Topology,
+};
+
+/// A link present in both snapshots whose flags differ.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LinkChange {
+    pub id: LinkId,
+    pub old_flags: u32,
+    pub new_flags: u32,
+}
+
+impl LinkChange {
+    /// Flags that were set or cleared.
+    pub fn toggled(&self) -> LinkFlags {
+        LinkFlags::from_bits_retain(self.old_flags ^ self.new_flags)
+    }
+}
+
+/// Objects added, removed or changed between two topology snapshots, matched
+/// by ID.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TopologyDiff {
+    pub added_entities: Vec<MediaV2Entity>,
+    pub removed_entities: Vec<MediaV2Entity>,
+    pub added_interfaces: Vec<MediaV2Interface>,
+    pub removed_interfaces: Vec<MediaV2Interface>,
+    pub added_pads: Vec<MediaV2Pad>,
+    pub removed_pads: Vec<MediaV2Pad>,
+    pub added_links: Vec<MediaV2Link>,
+    pub removed_links: Vec<MediaV2Link>,
+    pub changed_links: Vec<LinkChange>,
+}
+
+impl TopologyDiff {
+    pub fn between(old: &MediaV2Topology, new: &MediaV2Topology) -> TopologyDiff {
+        let (added_entities, removed_entities) =
+            added_removed(&old.entities, &new.entities, |e| e.id);
+        let (added_interfaces, removed_interfaces) =
+            added_removed(&old.interfaces, &new.interfaces, |i| i.id);
+        let (added_pads, removed_pads) = added_removed(&old.pads, &new.pads, |p| p.id);
+        let (added_links, removed_links) = added_removed(&old.links, &new.links, |l| l.id);
+
+        let old_links: HashMap<LinkId, &MediaV2Link> =
+            old.links.iter().map(|link| (link.id, link)).collect();
+        let changed_links = new
+            .links
+            .iter()
+            .filter_map(|link| {
+                let old_link = old_links.get(&link.id)?;
+                (old_link.flags != link.flags).then_some(LinkChange {
+                    id: link.id,
+                    old_flags: old_link.flags,
+                    new_flags: link.flags,
+                })
+            })
+            .collect();
+
+        TopologyDiff {
+            added_entities,
+            removed_entities,
+            added_interfaces,
+            removed_interfaces,
+            added_pads,
+            removed_pads,
+            added_links,
+            removed_links,
+            changed_links,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added_entities.is_empty()
+            && self.removed_entities.is_empty()
+            && self.added_interfaces.is_empty()
+            && self.removed_interfaces.is_empty()
+            && self.added_pads.is_empty()
+            && self.removed_pads.is_empty()
+            && self.added_links.is_empty()
+            && self.removed_links.is_empty()
+            && self.changed_links.is_empty()
+    }
+}
+
+/// Objects of `new` missing from `old`, and of `old` missing from `new`, in
+/// their original order.
+fn added_removed<T: Clone, K: Eq + Hash>(
+    old: &[T],
+    new: &[T],
+    key: impl Fn(&T) -> K,
+) -> (Vec<T>, Vec<T>) {
+    let old_keys: HashSet<K> = old.iter().map(&key).collect();
+    let new_keys: HashSet<K> = new.iter().map(&key).collect();
+    let added = new
+        .iter()
+        .filter(|o| !old_keys.contains(&key(o)))
+        .cloned()
+        .collect();
+    let removed = old
+        .iter()
+        .filter(|o| !new_keys.contains(&key(o)))
+        .cloned()
+        .collect();
+    (added, removed)
+}
+
+/// One line per change, prefixed with `+`, `-` or `~`.
+impl fmt::Display for TopologyDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entity in &self.added_entities {
+            writeln!(
+                f,
+                "+ entity {} {:?} ({})",
+                entity.id, entity.name, entity.function
+            )?;
+        }
+        for entity in &self.removed_entities {
+            writeln!(
+                f,
+                "- entity {} {:?} ({})",
+                entity.id, entity.name, entity.function
+            )?;
+        }
+        for intf in &self.added_interfaces {
+            writeln!(f, "+ interface {} ({})", intf.id, intf.intf_type)?;
+        }
+        for intf in &self.removed_interfaces {
+            writeln!(f, "- interface {} ({})", intf.id, intf.intf_type)?;
+        }
+        for pad in &self.added_pads {
+            writeln!(
+                f,
+                "+ pad {} (entity {} index {})",
+                pad.id, pad.entity_id, pad.index
+            )?;
+        }
+        for pad in &self.removed_pads {
+            writeln!(
+                f,
+                "- pad {} (entity {} index {})",
+                pad.id, pad.entity_id, pad.index
+            )?;
+        }
+        for link in &self.added_links {
+            writeln!(
+                f,
+                "+ link {} {} -> {} {:?}",
+                link.id,
+                link.source_id,
+                link.sink_id,
+                link.link_flags()
+            )?;
+        }
+        for link in &self.removed_links {
+            writeln!(
+                f,
+                "- link {} {} -> {} {:?}",
+                link.id,
+                link.source_id,
+                link.sink_id,
+                link.link_flags()
+            )?;
+        }
+        for change in &self.changed_links {
+            writeln!(
+                f,
+                "~ link {} {:?} -> {:?}",
+                change.id,
+                LinkFlags::from_bits_retain(change.old_flags),
+                LinkFlags::from_bits_retain(change.new_flags)
+            )?;
+        }
+        Ok(())
+    }
+}
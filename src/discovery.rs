@@ -0,0 +1,147 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{get_device_info, MediaDeviceInfo, MediaError};
+
+/// Paths of the `/dev/mediaN` nodes present in `dev_dir`, ordered by `N`.
+pub(crate) fn media_device_paths(dev_dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut nodes: Vec<(u32, PathBuf)> = Vec::new();
+    for entry in fs::read_dir(dev_dir)? {
+        let path = entry?.path();
+        if let Some(index) = media_index(&path) {
+            nodes.push((index, path));
+        }
+    }
+    nodes.sort();
+    Ok(nodes.into_iter().map(|(_, path)| path).collect())
+}
+
+/// `N` of a `mediaN` path.
+pub(crate) fn media_index(path: &Path) -> Option<u32> {
+    path.file_name()?
+        .to_str()?
+        .strip_prefix("media")?
+        .parse()
+        .ok()
+}
+
+/// A media controller node found by [`enumerate_devices`].
+#[derive(Debug)]
+pub struct DiscoveredDevice {
+    pub path: PathBuf,
+    /// Device information, or why the node couldn't be opened or queried.
+    pub info: Result<MediaDeviceInfo, MediaError>,
+}
+
+/// Lists the `/dev/mediaN` nodes along with their device information.
+///
+/// A node that fails to open or to answer `MEDIA_IOC_DEVICE_INFO` is still
+/// returned, with the error in [`DiscoveredDevice::info`].
+pub fn enumerate_devices() -> Result<Vec<DiscoveredDevice>, MediaError> {
+    Ok(scan(Path::new("/dev"))?.into_iter().map(discover).collect())
+}
+
+/// How [`enumerate_devices_with`] probes the nodes it finds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct ScanOptions {
+    /// Probe all nodes at once, each on its own thread, rather than one
+    /// after the other.
+    pub parallel: bool,
+    /// How long a node may take to open and answer `MEDIA_IOC_DEVICE_INFO`
+    /// before it's reported with [`MediaError::Timeout`].
+    pub timeout: Option<Duration>,
+}
+
+/// Like [`enumerate_devices`], probing the nodes as set by `options`, so that
+/// a driver hanging in `open` or an ioctl doesn't stall the whole scan.
+///
+/// A probe that times out is left running on a detached thread until the
+/// driver returns, the node is then closed.
+pub fn enumerate_devices_with(options: ScanOptions) -> Result<Vec<DiscoveredDevice>, MediaError> {
+    let paths = scan(Path::new("/dev"))?;
+    if !options.parallel && options.timeout.is_none() {
+        return Ok(paths.into_iter().map(discover).collect());
+    }
+    let deadline = || options.timeout.map(|timeout| Instant::now() + timeout);
+    if options.parallel {
+        let probes: Vec<_> = paths.into_iter().map(spawn_probe).collect();
+        let deadline = deadline();
+        Ok(probes
+            .into_iter()
+            .map(|probe| probe.finish(deadline, options.timeout))
+            .collect())
+    } else {
+        Ok(paths
+            .into_iter()
+            .map(|path| spawn_probe(path).finish(deadline(), options.timeout))
+            .collect())
+    }
+}
+
+/// Like [`enumerate_devices`], but lists the devices registered in
+/// `/sys/class/media`, which also covers nodes udev hasn't created (yet) or
+/// has renamed. Paths still point into `/dev`.
+pub fn enumerate_devices_from_sysfs() -> Result<Vec<DiscoveredDevice>, MediaError> {
+    Ok(scan(Path::new("/sys/class/media"))?
+        .into_iter()
+        .filter_map(|path| Some(Path::new("/dev").join(path.file_name()?)))
+        .map(discover)
+        .collect())
+}
+
+/// [`media_device_paths`] of `/dev`.
+pub(crate) fn dev_media_paths() -> Result<Vec<PathBuf>, MediaError> {
+    scan(Path::new("/dev"))
+}
+
+fn scan(dir: &Path) -> Result<Vec<PathBuf>, MediaError> {
+    media_device_paths(dir).map_err(|source| MediaError::Open {
+        path: dir.to_path_buf(),
+        source,
+    })
+}
+
+fn discover(path: PathBuf) -> DiscoveredDevice {
+    let info = get_device_info(&path);
+    DiscoveredDevice { path, info }
+}
+
+/// [`discover`] running on its own thread.
+struct Probe {
+    path: PathBuf,
+    result: mpsc::Receiver<DiscoveredDevice>,
+}
+
+fn spawn_probe(path: PathBuf) -> Probe {
+    let (sender, result) = mpsc::channel();
+    let probe_path = path.clone();
+    thread::spawn(move || {
+        // The receiver is gone if the probe timed out.
+        let _ = sender.send(discover(probe_path));
+    });
+    Probe { path, result }
+}
+
+impl Probe {
+    fn finish(self, deadline: Option<Instant>, timeout: Option<Duration>) -> DiscoveredDevice {
+        let received = match deadline {
+            None => self.result.recv().ok(),
+            Some(deadline) => self
+                .result
+                .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                .ok(),
+        };
+        received.unwrap_or_else(|| DiscoveredDevice {
+            info: Err(MediaError::Timeout {
+                path: self.path.clone(),
+                timeout: timeout.unwrap_or_default(),
+            }),
+            path: self.path,
+        })
+    }
+}
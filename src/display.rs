@@ -0,0 +1,192 @@
+//! Human-readable `Display` output for debugging. Flags are printed as
+//! symbolic lists such as `ENABLED|IMMUTABLE`, with unknown bits in hex.
+
+use std::{collections::HashMap, fmt};
+
+use bitflags::Flags;
+
+use crate::{
+    EntityFlags, EntityId, LinkFlags, LinkKind, MediaDeviceInfo, MediaV2Entity, MediaV2Interface,
+    MediaV2IntfDevnode, MediaV2Link, MediaV2Pad, MediaV2Topology, PadFlags, PadId,
+};
+
+/// Names of the flags set in `flags` joined by `|`, `0` if none is set.
+fn write_flags<F: Flags<Bits = u32>>(f: &mut fmt::Formatter<'_>, flags: F) -> fmt::Result {
+    let mut first = true;
+    let mut separator = |f: &mut fmt::Formatter<'_>| {
+        if !std::mem::take(&mut first) {
+            f.write_str("|")?;
+        }
+        Ok(())
+    };
+    for (name, _) in flags.iter_names() {
+        separator(f)?;
+        f.write_str(name)?;
+    }
+    let unknown = flags.bits() & !F::all().bits();
+    if unknown != 0 {
+        separator(f)?;
+        write!(f, "{unknown:#x}")?;
+    }
+    if first {
+        f.write_str("0")?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for EntityFlags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_flags(f, *self)
+    }
+}
+
+impl fmt::Display for PadFlags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_flags(f, *self)
+    }
+}
+
+impl fmt::Display for LinkFlags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_flags(f, *self)
+    }
+}
+
+impl fmt::Display for LinkKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LinkKind::Data => f.write_str("data"),
+            LinkKind::Interface => f.write_str("interface"),
+            LinkKind::Ancillary => f.write_str("ancillary"),
+            LinkKind::Unknown(bits) => write!(f, "unknown type {bits:#x}"),
+        }
+    }
+}
+
+impl fmt::Display for MediaDeviceInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({}) on {}, driver {}, media API {}, hw revision {:#x}",
+            self.model,
+            self.driver,
+            self.bus_info,
+            self.driver_version(),
+            self.media_version(),
+            self.hw_version,
+        )?;
+        if !self.serial.is_empty() {
+            write!(f, ", serial {}", self.serial)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for MediaV2Entity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "entity {}: {} ({}", self.id, self.name, self.function)?;
+        if self.flags != 0 {
+            write!(f, ", {}", self.entity_flags())?;
+        }
+        f.write_str(")")
+    }
+}
+
+impl fmt::Display for MediaV2IntfDevnode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.major, self.minor)
+    }
+}
+
+impl fmt::Display for MediaV2Interface {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "interface {}: {}", self.id, self.intf_type)?;
+        if let Some(devnode) = &self.devnode {
+            write!(f, " {devnode}")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for MediaV2Pad {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "pad {}: entity {} index {} [{}]",
+            self.id,
+            self.entity_id,
+            self.index,
+            self.pad_flags()
+        )
+    }
+}
+
+impl fmt::Display for MediaV2Link {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} link {}: {} -> {} [{}]",
+            self.kind(),
+            self.id,
+            self.source_id,
+            self.sink_id,
+            self.link_flags() - LinkFlags::LINK_TYPE
+        )
+    }
+}
+
+/// One block per entity listing its pads and their data links by name,
+/// followed by the interfaces and the entities they control.
+impl fmt::Display for MediaV2Topology {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let entities: HashMap<EntityId, &MediaV2Entity> =
+            self.entities.iter().map(|e| (e.id, e)).collect();
+        let pads: HashMap<PadId, &MediaV2Pad> = self.pads.iter().map(|p| (p.id, p)).collect();
+        let pad_name = |id: PadId| match pads.get(&id) {
+            Some(pad) => match entities.get(&pad.entity_id) {
+                Some(entity) => format!("\"{}\":{}", entity.name, pad.index),
+                None => format!("{}:{}", pad.entity_id, pad.index),
+            },
+            None => format!("pad {id}"),
+        };
+
+        writeln!(f, "topology version {}", self.topology_version)?;
+        for entity in &self.entities {
+            writeln!(f, "{entity}")?;
+            let mut entity_pads: Vec<_> = self
+                .pads
+                .iter()
+                .filter(|pad| pad.entity_id == entity.id)
+                .collect();
+            entity_pads.sort_by_key(|pad| pad.index);
+            for pad in entity_pads {
+                writeln!(f, "  pad {} [{}]", pad.index, pad.pad_flags())?;
+                for link in self.data_links() {
+                    let (Some(source), Some(sink)) = (link.source_pad(), link.sink_pad()) else {
+                        continue;
+                    };
+                    if source == pad.id {
+                        write!(f, "    -> {}", pad_name(sink))?;
+                    } else if sink == pad.id {
+                        write!(f, "    <- {}", pad_name(source))?;
+                    } else {
+                        continue;
+                    }
+                    writeln!(f, " [{}]", link.link_flags() - LinkFlags::LINK_TYPE)?;
+                }
+            }
+        }
+        for intf in &self.interfaces {
+            write!(f, "{intf}")?;
+            for link in self.interface_links() {
+                if link.source_interface() == Some(intf.id) {
+                    if let Some(entity) = link.sink_entity().and_then(|id| entities.get(&id)) {
+                        write!(f, " -> \"{}\"", entity.name)?;
+                    }
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
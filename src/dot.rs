@@ -0,0 +1,136 @@
+use std::{collections::HashMap, fmt::Write};
+
+use crate::{
+    EntityFunction, EntityId, LinkFlags, MediaGraph, MediaV2Entity, MediaV2Pad, MediaV2Topology,
+    PadFlags, PadId,
+};
+
+impl MediaV2Topology {
+    /// Renders the topology as a Graphviz graph in the style of
+    /// `media-ctl --print-dot`.
+    ///
+    /// Entities are nodes labelled with their name and function. Entities
+    /// with pads are records with sink pads as ports on top and source pads
+    /// at the bottom, V4L2 video nodes are plain boxes. Data links are solid
+    /// when enabled, dashed when disabled and bold when immutable. Ancillary
+    /// links are dotted and undirected.
+    pub fn to_dot(&self) -> String {
+        let mut pads_by_entity: HashMap<EntityId, Vec<&MediaV2Pad>> = HashMap::new();
+        for pad in &self.pads {
+            pads_by_entity.entry(pad.entity_id).or_default().push(pad);
+        }
+        let pads: HashMap<PadId, &MediaV2Pad> = self.pads.iter().map(|pad| (pad.id, pad)).collect();
+        let is_record = |entity: &MediaV2Entity| {
+            !entity.is_video_io() && pads_by_entity.contains_key(&entity.id)
+        };
+        let records: HashMap<EntityId, bool> = self
+            .entities
+            .iter()
+            .map(|entity| (entity.id, is_record(entity)))
+            .collect();
+
+        let mut dot = String::from("digraph board {\n\trankdir=TB\n");
+        for entity in &self.entities {
+            let label = format!(
+                "{}\\n{}",
+                escape(&entity.name),
+                escape(&function_label(entity.function))
+            );
+            if records[&entity.id] {
+                let mut entity_pads = pads_by_entity[&entity.id].clone();
+                entity_pads.sort_by_key(|pad| pad.index);
+                let ports = |flag: PadFlags| {
+                    entity_pads
+                        .iter()
+                        .filter(|pad| pad.pad_flags().contains(flag))
+                        .map(|pad| format!("<port{0}> {0}", pad.index))
+                        .collect::<Vec<_>>()
+                        .join(" | ")
+                };
+                let _ = writeln!(
+                    dot,
+                    "\tn{:08x} [label=\"{{{{{}}} | {} | {{{}}}}}\", shape=Mrecord, style=filled, fillcolor=green]",
+                    entity.id.0,
+                    ports(PadFlags::SINK),
+                    label,
+                    ports(PadFlags::SOURCE),
+                );
+            } else {
+                let _ = writeln!(
+                    dot,
+                    "\tn{:08x} [label=\"{}\", shape=box, style=filled, fillcolor=yellow]",
+                    entity.id.0, label
+                );
+            }
+        }
+
+        let endpoint = |pad: &MediaV2Pad| {
+            if records.get(&pad.entity_id).copied().unwrap_or(false) {
+                format!("n{:08x}:port{}", pad.entity_id.0, pad.index)
+            } else {
+                format!("n{:08x}", pad.entity_id.0)
+            }
+        };
+        for link in self.data_links() {
+            let (Some(source), Some(sink)) = (
+                link.source_pad().and_then(|id| pads.get(&id)),
+                link.sink_pad().and_then(|id| pads.get(&id)),
+            ) else {
+                continue;
+            };
+            let flags = link.link_flags();
+            let style = if flags.contains(LinkFlags::IMMUTABLE) {
+                "bold"
+            } else if flags.contains(LinkFlags::ENABLED) {
+                "solid"
+            } else {
+                "dashed"
+            };
+            let _ = writeln!(
+                dot,
+                "\t{} -> {} [style={}]",
+                endpoint(source),
+                endpoint(sink),
+                style
+            );
+        }
+        for link in self.ancillary_links() {
+            if let (Some(source), Some(sink)) = (link.source_entity(), link.sink_entity()) {
+                let _ = writeln!(
+                    dot,
+                    "\tn{:08x} -> n{:08x} [style=dotted, dir=none]",
+                    source.0, sink.0
+                );
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+impl MediaGraph {
+    /// See [`MediaV2Topology::to_dot`].
+    pub fn to_dot(&self) -> String {
+        self.topology().to_dot()
+    }
+}
+
+/// `CAM_SENSOR` rather than `MEDIA_ENT_F_CAM_SENSOR`, to keep nodes narrow.
+fn function_label(function: EntityFunction) -> String {
+    match function.name() {
+        Some(name) => name.trim_start_matches("MEDIA_ENT_F_").to_string(),
+        None => function.to_string(),
+    }
+}
+
+/// Escapes the characters with a meaning in quoted strings and record labels.
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if matches!(c, '"' | '\\' | '{' | '}' | '|' | '<' | '>') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
@@ -0,0 +1,305 @@
+use std::{io, path::PathBuf, time::Duration};
+
+use nix::errno::Errno;
+use thiserror::Error;
+
+use crate::{EntityId, Feature, KernelVersion, LinkRequest, PadId, PadRef, ValidationReport};
+#[cfg(feature = "subdev")]
+use crate::{SubdevFormat, Which};
+
+/// Error type shared by all fallible operations of the crate.
+#[derive(Debug, Error)]
+pub enum MediaError {
+    #[error("failed to open {}: {source}", path.display())]
+    Open {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error("{ioctl} failed: {errno}")]
+    Ioctl {
+        ioctl: &'static str,
+        #[source]
+        errno: Errno,
+    },
+    /// The device didn't answer within the timeout of
+    /// [`crate::enumerate_devices_with`].
+    #[error("{} did not respond within {timeout:?}", path.display())]
+    Timeout { path: PathBuf, timeout: Duration },
+    #[error("poll failed: {0}")]
+    Poll(#[source] Errno),
+    #[error("inotify failed: {0}")]
+    Inotify(#[source] Errno),
+    #[error("duplicating the device descriptor failed: {0}")]
+    Dup(#[source] Errno),
+    /// The topology changed between the sizing and the fetching
+    /// `MEDIA_IOC_G_TOPOLOGY` calls.
+    #[error("topology changed while being read (version {old_version} -> {new_version})")]
+    VersionChange { old_version: u64, new_version: u64 },
+    #[error("no pad with id {0} in the topology")]
+    UnknownPad(PadId),
+    /// A link setup failed and restoring the links changed before it failed
+    /// too, leaving the pipeline partially configured.
+    #[error("{error}, and rolling back the applied links failed: {rollback}")]
+    RollbackFailed {
+        #[source]
+        error: Box<MediaError>,
+        rollback: Box<MediaError>,
+    },
+    #[error("no data link path from entity {from} to entity {to}")]
+    NoPath { from: EntityId, to: EntityId },
+    #[error("no entity {0} in the topology")]
+    UnknownEntity(String),
+    #[error("no pad {0} in the topology")]
+    UnknownPadRef(String),
+    /// `MEDIA_IOC_SETUP_LINK` failed for the link between `source` and
+    /// `sink`.
+    #[error("setting up link {source} -> {sink} failed: {cause}")]
+    LinkSetup {
+        source: PadRef,
+        sink: PadRef,
+        #[source]
+        cause: LinkSetupError,
+    },
+    /// The operation needs a device opened read-write, see
+    /// [`crate::OpenMode`].
+    #[error("{ioctl} needs a device opened read-write")]
+    ReadOnlyHandle { ioctl: &'static str },
+    /// Link changes were refused before being applied.
+    #[error("invalid link changes: {0}")]
+    Validation(ValidationReport),
+    /// The device was removed, e.g. unplugged, and the descriptor is dead.
+    /// Every further ioctl fails the same way, see
+    /// [`crate::MediaDevice::reopen`].
+    #[error("{ioctl} failed: the device is gone")]
+    DeviceGone { ioctl: &'static str },
+    /// The running kernel doesn't implement the ioctl.
+    #[error("{ioctl} is not supported by the kernel")]
+    UnsupportedKernel { ioctl: &'static str },
+    /// The kernel is older than the first release providing `feature`.
+    #[error("{feature} needs kernel {needed} or newer, found {found}")]
+    Unsupported {
+        feature: Feature,
+        needed: KernelVersion,
+        found: KernelVersion,
+    },
+    /// The topology doesn't hold what it should, e.g. raw topology buffers
+    /// not matching their counts, see [`crate::parse_topology_fuzz`], or pad
+    /// indexes `MEDIA_IOC_SETUP_LINK` can't address.
+    #[error("malformed topology: {0}")]
+    MalformedTopology(String),
+    #[error("invalid ioctl recording: {0}")]
+    InvalidRecording(String),
+    /// A [`crate::ReplayBackend`] was asked for `found` while the next
+    /// recorded ioctl is `expected`, `None` once all are replayed. For a
+    /// `MEDIA_IOC_SETUP_LINK` of another link than recorded, `link` holds the
+    /// recorded and the replayed link, with entities addressed by ID.
+    #[error(
+        "replayed {found}{}, but the recording continues with {}{}",
+        link.as_deref().map_or(String::new(), |(_, replayed)| format!(" {replayed}")),
+        expected.as_deref().unwrap_or("nothing"),
+        link.as_deref().map_or(String::new(), |(recorded, _)| format!(" {recorded}"))
+    )]
+    ReplayMismatch {
+        expected: Option<String>,
+        found: &'static str,
+        link: Option<Box<(LinkRequest, LinkRequest)>>,
+    },
+    #[cfg(feature = "json")]
+    #[error("invalid topology dump: {0}")]
+    Json(#[source] serde_json::Error),
+    /// The topology dump uses a schema this version of the crate can't read.
+    #[cfg(feature = "json")]
+    #[error("unsupported topology dump schema {schema}")]
+    UnsupportedSchema { schema: u32 },
+    #[cfg(feature = "toml")]
+    #[error("invalid TOML: {0}")]
+    Toml(#[source] toml::de::Error),
+    #[cfg(feature = "yaml")]
+    #[error("invalid YAML: {0}")]
+    Yaml(#[source] serde_yaml::Error),
+    /// The entity has no V4L2 subdevice node, or it isn't registered.
+    #[cfg(feature = "subdev")]
+    #[error("entity {0} has no subdevice node")]
+    NoSubdevNode(EntityId),
+    /// A pad refused the format set by [`crate::MediaGraph::propagate_format`].
+    #[cfg(feature = "subdev")]
+    #[error("setting format {format} on {pad} ({which:?}) failed: {cause}")]
+    FormatRejected {
+        pad: PadRef,
+        which: Which,
+        format: SubdevFormat,
+        #[source]
+        cause: FormatRejection,
+    },
+    /// An enumeration ioctl kept succeeding past the last index tried, e.g.
+    /// a driver ignoring the index.
+    #[cfg(feature = "subdev")]
+    #[error("{ioctl} did not end the enumeration after {limit} items")]
+    EnumerationLimit { ioctl: &'static str, limit: u32 },
+    /// The entity has no `MEDIA_INTF_T_V4L_VIDEO` interface.
+    #[cfg(feature = "v4l-interop")]
+    #[error("entity {0} has no video device node")]
+    NoVideoNode(EntityId),
+    /// No device node is registered under the numbers of an interface,
+    /// e.g. because its driver was unbound.
+    #[cfg(feature = "v4l-interop")]
+    #[error("no device node {major}:{minor} is registered")]
+    UnregisteredDevnode { major: u32, minor: u32 },
+    #[cfg(feature = "test-harness")]
+    #[error("loading module {module} failed: {reason}")]
+    LoadModule {
+        module: &'static str,
+        reason: String,
+    },
+    #[cfg(feature = "udev")]
+    #[error("udev query failed: {0}")]
+    Udev(#[source] io::Error),
+}
+
+/// Reason the kernel gave for refusing a link setup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum LinkSetupError {
+    /// `EBUSY`: the pipeline the link belongs to is streaming.
+    #[error("pipeline is streaming")]
+    Streaming,
+    /// `EINVAL`: the link is immutable, the pads aren't linked or the flags
+    /// are invalid.
+    #[error("link is immutable or doesn't exist")]
+    Invalid,
+    /// `EPERM`, `EACCES` or `EBADF`: the device isn't open for writing.
+    #[error("device is not open read-write")]
+    ReadOnly,
+    #[error("{0}")]
+    Other(Errno),
+}
+
+/// Why a pad refused a format, see [`MediaError::FormatRejected`].
+#[cfg(feature = "subdev")]
+#[derive(Debug, Error)]
+pub enum FormatRejection {
+    /// The driver replaced the media bus code or the frame size with the
+    /// closest it supports.
+    #[error("driver adjusted it to {0}")]
+    Adjusted(SubdevFormat),
+    #[error(transparent)]
+    Failed(Box<MediaError>),
+}
+
+impl LinkSetupError {
+    pub fn from_errno(errno: Errno) -> LinkSetupError {
+        match errno {
+            Errno::EBUSY => LinkSetupError::Streaming,
+            Errno::EINVAL => LinkSetupError::Invalid,
+            Errno::EPERM | Errno::EACCES | Errno::EBADF => LinkSetupError::ReadOnly,
+            errno => LinkSetupError::Other(errno),
+        }
+    }
+
+    pub fn errno(&self) -> Errno {
+        match self {
+            LinkSetupError::Streaming => Errno::EBUSY,
+            LinkSetupError::Invalid => Errno::EINVAL,
+            LinkSetupError::ReadOnly => Errno::EPERM,
+            LinkSetupError::Other(errno) => *errno,
+        }
+    }
+}
+
+impl MediaError {
+    /// Wraps a failed ioctl, reporting `ENOTTY` as an unsupported kernel and
+    /// `ENODEV` as a removed device.
+    pub(crate) fn ioctl(ioctl: &'static str, errno: Errno) -> MediaError {
+        match errno {
+            Errno::ENOTTY => MediaError::UnsupportedKernel { ioctl },
+            Errno::ENODEV => MediaError::DeviceGone { ioctl },
+            errno => MediaError::Ioctl { ioctl, errno },
+        }
+    }
+
+    /// The errno reported by the kernel, if the error originates from a
+    /// syscall.
+    pub fn errno(&self) -> Option<Errno> {
+        match self {
+            MediaError::Open { source, .. } => source.raw_os_error().map(Errno::from_i32),
+            MediaError::Ioctl { errno, .. }
+            | MediaError::Poll(errno)
+            | MediaError::Inotify(errno)
+            | MediaError::Dup(errno) => Some(*errno),
+            MediaError::UnsupportedKernel { .. } | MediaError::Unsupported { .. } => {
+                Some(Errno::ENOTTY)
+            }
+            MediaError::ReadOnlyHandle { .. } => Some(Errno::EBADF),
+            MediaError::DeviceGone { .. } => Some(Errno::ENODEV),
+            MediaError::Timeout { .. } => Some(Errno::ETIMEDOUT),
+            MediaError::LinkSetup { cause, .. } => Some(cause.errno()),
+            MediaError::RollbackFailed { error, .. } => error.errno(),
+            #[cfg(feature = "subdev")]
+            MediaError::FormatRejected {
+                cause: FormatRejection::Failed(error),
+                ..
+            } => error.errno(),
+            #[cfg(feature = "udev")]
+            MediaError::Udev(source) => source.raw_os_error().map(Errno::from_i32),
+            _ => None,
+        }
+    }
+
+    fn io_kind(&self) -> io::ErrorKind {
+        match self {
+            MediaError::Open { source, .. } => source.kind(),
+            MediaError::Ioctl { errno, .. }
+            | MediaError::Poll(errno)
+            | MediaError::Inotify(errno)
+            | MediaError::Dup(errno) => io::Error::from(*errno).kind(),
+            MediaError::Timeout { .. } => io::ErrorKind::TimedOut,
+            MediaError::VersionChange { .. } => io::ErrorKind::Interrupted,
+            MediaError::UnknownPad(_)
+            | MediaError::NoPath { .. }
+            | MediaError::UnknownEntity(_)
+            | MediaError::UnknownPadRef(_) => io::ErrorKind::NotFound,
+            MediaError::LinkSetup { cause, .. } => io::Error::from(cause.errno()).kind(),
+            MediaError::ReadOnlyHandle { .. } => io::ErrorKind::PermissionDenied,
+            MediaError::DeviceGone { .. } => io::ErrorKind::NotConnected,
+            MediaError::Validation(_) => io::ErrorKind::InvalidInput,
+            MediaError::UnsupportedKernel { .. } | MediaError::Unsupported { .. } => {
+                io::ErrorKind::Unsupported
+            }
+            MediaError::RollbackFailed { error, .. } => error.io_kind(),
+            MediaError::MalformedTopology(_)
+            | MediaError::InvalidRecording(_)
+            | MediaError::ReplayMismatch { .. } => io::ErrorKind::InvalidData,
+            #[cfg(feature = "json")]
+            MediaError::Json(_) | MediaError::UnsupportedSchema { .. } => {
+                io::ErrorKind::InvalidData
+            }
+            #[cfg(feature = "toml")]
+            MediaError::Toml(_) => io::ErrorKind::InvalidData,
+            #[cfg(feature = "yaml")]
+            MediaError::Yaml(_) => io::ErrorKind::InvalidData,
+            #[cfg(feature = "subdev")]
+            MediaError::NoSubdevNode(_) => io::ErrorKind::NotFound,
+            #[cfg(feature = "subdev")]
+            MediaError::EnumerationLimit { .. } => io::ErrorKind::InvalidData,
+            #[cfg(feature = "subdev")]
+            MediaError::FormatRejected { cause, .. } => match cause {
+                FormatRejection::Adjusted(_) => io::ErrorKind::InvalidInput,
+                FormatRejection::Failed(error) => error.io_kind(),
+            },
+            #[cfg(feature = "v4l-interop")]
+            MediaError::NoVideoNode(_) | MediaError::UnregisteredDevnode { .. } => {
+                io::ErrorKind::NotFound
+            }
+            #[cfg(feature = "test-harness")]
+            MediaError::LoadModule { .. } => io::ErrorKind::Other,
+            #[cfg(feature = "udev")]
+            MediaError::Udev(source) => source.kind(),
+        }
+    }
+}
+
+impl From<MediaError> for io::Error {
+    fn from(err: MediaError) -> io::Error {
+        io::Error::new(err.io_kind(), err)
+    }
+}
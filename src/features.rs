@@ -0,0 +1,84 @@
+use std::fmt;
+
+use nix::errno::Errno;
+
+use crate::{KernelVersion, MediaDevice, MediaError};
+
+/// Optional parts of the media controller API, see [`MediaDevice::supports`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Feature {
+    /// `MEDIA_IOC_G_TOPOLOGY`. Without it the topology is rebuilt from the
+    /// legacy enumeration ioctls.
+    GTopology,
+    /// Entity flags in `media_v2_entity` (`MEDIA_V2_ENTITY_HAS_FLAGS`).
+    EntityFlags,
+    /// Pad indexes in `media_v2_pad` (`MEDIA_V2_PAD_HAS_INDEX`).
+    PadIndex,
+    /// Media requests, `MEDIA_IOC_REQUEST_ALLOC`.
+    RequestApi,
+    /// Ancillary links between entities, e.g. a sensor and its lens.
+    AncillaryLinks,
+}
+
+impl Feature {
+    /// First kernel release providing the feature.
+    pub fn min_version(&self) -> KernelVersion {
+        match self {
+            Feature::GTopology => KernelVersion::new(4, 5, 0),
+            Feature::EntityFlags | Feature::PadIndex => KernelVersion::new(4, 19, 0),
+            Feature::RequestApi => KernelVersion::new(4, 20, 0),
+            Feature::AncillaryLinks => KernelVersion::new(5, 19, 0),
+        }
+    }
+}
+
+impl fmt::Display for Feature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Feature::GTopology => "MEDIA_IOC_G_TOPOLOGY",
+            Feature::EntityFlags => "entity flags",
+            Feature::PadIndex => "pad indexes",
+            Feature::RequestApi => "the request API",
+            Feature::AncillaryLinks => "ancillary links",
+        })
+    }
+}
+
+impl MediaDevice {
+    /// Whether the device supports `feature`.
+    ///
+    /// The topology and request ioctls are probed, since drivers may lack
+    /// them on new kernels and backports may add them to old ones; probing
+    /// the request API allocates a request and closes it again. The media
+    /// API version, which is the kernel version on all kernels providing
+    /// the features, decides the other features, and the probed ones when
+    /// the probe fails with another error than `ENOTTY`.
+    pub fn supports(&self, feature: Feature) -> Result<bool, MediaError> {
+        let probe = match feature {
+            Feature::GTopology => Some(self.topology_version().map(drop)),
+            Feature::RequestApi => Some(self.alloc_request().map(drop)),
+            _ => None,
+        };
+        match probe {
+            Some(Ok(())) => Ok(true),
+            Some(Err(err)) if err.errno() == Some(Errno::ENOTTY) => Ok(false),
+            _ => Ok(self.device_info()?.media_version() >= feature.min_version()),
+        }
+    }
+
+    /// Fails with [`MediaError::Unsupported`] if the media API version is
+    /// older than the first kernel providing `feature`.
+    pub fn require(&self, feature: Feature) -> Result<(), MediaError> {
+        let found = self.device_info()?.media_version();
+        let needed = feature.min_version();
+        if found < needed {
+            return Err(MediaError::Unsupported {
+                feature,
+                needed,
+                found,
+            });
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+
+use crate::{EntityId, MediaV2Pad, MediaV2Topology, PadId};
+
+impl MediaV2Topology {
+    /// Stable 64-bit hash of the structure of the graph: entities with their
+    /// function and flags, pads, interface types and links with their flags.
+    ///
+    /// Objects are identified by entity name and pad index rather than by ID,
+    /// and the topology version, object order and device node numbers are
+    /// left out, so the fingerprint is the same across boots and kernel
+    /// enumeration orders as long as the graph is.
+    pub fn fingerprint(&self) -> u64 {
+        let names: HashMap<EntityId, &str> = self
+            .entities
+            .iter()
+            .map(|entity| (entity.id, &*entity.name))
+            .collect();
+        let pads: HashMap<PadId, &MediaV2Pad> = self.pads.iter().map(|pad| (pad.id, pad)).collect();
+        let entity_name = |id: EntityId| names.get(&id).copied().unwrap_or("");
+        let pad_name = |id: Option<PadId>| match id.and_then(|id| pads.get(&id)) {
+            Some(pad) => format!("{:?}:{}", entity_name(pad.entity_id), pad.index),
+            None => String::new(),
+        };
+
+        let mut records: Vec<String> = Vec::new();
+        for entity in &self.entities {
+            records.push(format!(
+                "entity {:?} {} {:#x}",
+                entity.name,
+                u32::from(entity.function),
+                entity.flags
+            ));
+        }
+        for pad in &self.pads {
+            records.push(format!(
+                "pad {:?}:{} {:#x}",
+                entity_name(pad.entity_id),
+                pad.index,
+                pad.flags
+            ));
+        }
+        for intf in &self.interfaces {
+            let targets: Vec<_> = self
+                .interface_links()
+                .filter(|link| link.source_interface() == Some(intf.id))
+                .filter_map(|link| link.sink_entity())
+                .map(|id| format!("{:?}", entity_name(id)))
+                .collect();
+            records.push(format!(
+                "interface {} {:#x} {}",
+                u32::from(intf.intf_type),
+                intf.flags,
+                targets.join(",")
+            ));
+        }
+        for link in self.data_links() {
+            records.push(format!(
+                "link {} -> {} {:#x}",
+                pad_name(link.source_pad()),
+                pad_name(link.sink_pad()),
+                link.flags
+            ));
+        }
+        for link in self.ancillary_links() {
+            let (source, sink) = (link.source_entity(), link.sink_entity());
+            records.push(format!(
+                "ancillary {:?} -> {:?} {:#x}",
+                source.map_or("", entity_name),
+                sink.map_or("", entity_name),
+                link.flags
+            ));
+        }
+        records.sort();
+
+        let mut hash = Fnv1a::new();
+        for record in &records {
+            hash.write(record.as_bytes());
+            hash.write(b"\n");
+        }
+        hash.0
+    }
+}
+
+/// 64-bit FNV-1a, used instead of `std::hash` whose output may change between
+/// Rust releases.
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Fnv1a {
+        Fnv1a(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+}
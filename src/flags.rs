@@ -0,0 +1,68 @@
+use bitflags::bitflags;
+
+use crate::media_ffi;
+
+bitflags! {
+    /// Entity flags (`MEDIA_ENT_FL_*`).
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct EntityFlags: u32 {
+        /// Default entity for its function type.
+        const DEFAULT = media_ffi::MEDIA_ENT_FL_DEFAULT;
+        /// The entity represents a physical connector.
+        const CONNECTOR = media_ffi::MEDIA_ENT_FL_CONNECTOR;
+    }
+}
+
+bitflags! {
+    /// Pad flags (`MEDIA_PAD_FL_*`).
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct PadFlags: u32 {
+        const SINK = media_ffi::MEDIA_PAD_FL_SINK;
+        const SOURCE = media_ffi::MEDIA_PAD_FL_SOURCE;
+        /// The pad needs an enabled link for the entity to be streamed.
+        const MUST_CONNECT = media_ffi::MEDIA_PAD_FL_MUST_CONNECT;
+    }
+}
+
+bitflags! {
+    /// Link flags (`MEDIA_LNK_FL_*`).
+    ///
+    /// The link type is encoded in the `LINK_TYPE` bits. Data links have no
+    /// type bit set.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct LinkFlags: u32 {
+        const ENABLED = media_ffi::MEDIA_LNK_FL_ENABLED;
+        const IMMUTABLE = media_ffi::MEDIA_LNK_FL_IMMUTABLE;
+        const DYNAMIC = media_ffi::MEDIA_LNK_FL_DYNAMIC;
+        const INTERFACE_LINK = media_ffi::MEDIA_LNK_FL_INTERFACE_LINK;
+        const ANCILLARY_LINK = media_ffi::MEDIA_LNK_FL_ANCILLARY_LINK;
+        const LINK_TYPE = media_ffi::MEDIA_LNK_FL_LINK_TYPE;
+    }
+}
+
+/// Type of a link, decoded from the `MEDIA_LNK_FL_LINK_TYPE` bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LinkKind {
+    /// Pad-to-pad link carrying data.
+    Data,
+    /// Link from an interface to the entity it controls.
+    Interface,
+    /// Entity-to-entity link, e.g. between a sensor and its lens controller.
+    Ancillary,
+    Unknown(u32),
+}
+
+impl LinkFlags {
+    pub fn kind(&self) -> LinkKind {
+        match self.bits() & media_ffi::MEDIA_LNK_FL_LINK_TYPE {
+            media_ffi::MEDIA_LNK_FL_DATA_LINK => LinkKind::Data,
+            media_ffi::MEDIA_LNK_FL_INTERFACE_LINK => LinkKind::Interface,
+            media_ffi::MEDIA_LNK_FL_ANCILLARY_LINK => LinkKind::Ancillary,
+            other => LinkKind::Unknown(other),
+        }
+    }
+}
@@ -0,0 +1,87 @@
+//! Entry points for fuzzing the parsers of kernel- and user-controlled
+//! input, see the targets in `fuzz/`.
+//!
+//! They take arbitrary bytes, never panic, and allocate in proportion to the
+//! input only.
+
+use std::mem::size_of;
+
+use crate::{
+    media_ffi::{
+        media_v2_entity, media_v2_interface, media_v2_link, media_v2_pad, media_v2_topology,
+    },
+    raw::{self, Pod},
+    LinkRequest, MediaError, MediaV2Topology, PadFormatRequest, SyntaxError,
+};
+
+/// Parses a topology from the buffers `MEDIA_IOC_G_TOPOLOGY` fills in, laid
+/// out back to back: the `media_v2_topology` struct, then as many entities,
+/// interfaces, pads and links as its counts say, in native byte order.
+///
+/// The counts are checked against the input length before anything is
+/// allocated, and fail with [`MediaError::MalformedTopology`] when they
+/// don't add up to exactly the input.
+pub fn parse_topology_fuzz(bytes: &[u8]) -> Result<MediaV2Topology, MediaError> {
+    let mut input = Input(bytes);
+    let topology: media_v2_topology = raw::read(input.take(1, size_of::<media_v2_topology>())?)
+        .expect("took the size of the struct");
+    let entities = input.structs::<media_v2_entity>(topology.num_entities)?;
+    let interfaces = input.structs::<media_v2_interface>(topology.num_interfaces)?;
+    let pads = input.structs::<media_v2_pad>(topology.num_pads)?;
+    let links = input.structs::<media_v2_link>(topology.num_links)?;
+    if !input.0.is_empty() {
+        return Err(MediaError::MalformedTopology(format!(
+            "{} trailing bytes",
+            input.0.len()
+        )));
+    }
+    Ok(MediaV2Topology::from_ffi(
+        &topology,
+        &entities,
+        &interfaces,
+        &pads,
+        &links,
+    ))
+}
+
+/// [`LinkRequest::parse_list`] over bytes, rejecting invalid UTF-8 with a
+/// [`SyntaxError`] at the first invalid byte.
+pub fn parse_links_fuzz(bytes: &[u8]) -> Result<Vec<LinkRequest>, SyntaxError> {
+    LinkRequest::parse_list(utf8(bytes)?)
+}
+
+/// [`PadFormatRequest::parse_list`] over bytes, like [`parse_links_fuzz`].
+pub fn parse_pad_formats_fuzz(bytes: &[u8]) -> Result<Vec<PadFormatRequest>, SyntaxError> {
+    PadFormatRequest::parse_list(utf8(bytes)?)
+}
+
+fn utf8(bytes: &[u8]) -> Result<&str, SyntaxError> {
+    std::str::from_utf8(bytes).map_err(|err| SyntaxError {
+        position: err.valid_up_to(),
+        message: "invalid UTF-8".to_string(),
+    })
+}
+
+struct Input<'a>(&'a [u8]);
+
+impl<'a> Input<'a> {
+    fn take(&mut self, count: u32, size: usize) -> Result<&'a [u8], MediaError> {
+        let len = (count as usize)
+            .checked_mul(size)
+            .filter(|&len| len <= self.0.len())
+            .ok_or_else(|| {
+                MediaError::MalformedTopology(format!(
+                    "{count} structs of {size} bytes, {} bytes left",
+                    self.0.len()
+                ))
+            })?;
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn structs<T: Pod>(&mut self, count: u32) -> Result<Vec<T>, MediaError> {
+        let bytes = self.take(count, size_of::<T>())?;
+        Ok(raw::read_slice(bytes).expect("took whole structs"))
+    }
+}
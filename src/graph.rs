@@ -0,0 +1,625 @@
+use std::{
+    collections::{hash_map::Entry, BTreeMap, HashMap, HashSet, VecDeque},
+    hash::{Hash, Hasher},
+};
+
+use crate::{
+    EntityId, InterfaceId, LegacyEntity, LinkId, LinkKind, MediaV2Entity, MediaV2Interface,
+    MediaV2Link, MediaV2Pad, MediaV2Topology, PadFlags, PadId,
+};
+
+/// Routes [`MediaGraph::find_all_paths`] returns at most.
+pub const MAX_PATHS: usize = 1024;
+
+/// One data link traversed by a [`MediaPath`], in source-to-sink direction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PathHop {
+    pub source_entity: EntityId,
+    pub source_pad: PadId,
+    pub link: LinkId,
+    pub sink_entity: EntityId,
+    pub sink_pad: PadId,
+}
+
+/// A loop of entities connected by data links, see
+/// [`MediaGraph::detect_cycles`]. The last entity links back to the first.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Cycle {
+    pub entities: Vec<EntityId>,
+}
+
+/// Route along data links between two entities, see [`MediaGraph::find_path`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MediaPath {
+    pub hops: Vec<PathHop>,
+}
+
+impl MediaPath {
+    /// Entities along the path, from the first source to the last sink.
+    pub fn entities(&self) -> Vec<EntityId> {
+        let first = self.hops.first().map(|hop| hop.source_entity);
+        first
+            .into_iter()
+            .chain(self.hops.iter().map(|hop| hop.sink_entity))
+            .collect()
+    }
+
+    pub fn links(&self) -> Vec<LinkId> {
+        self.hops.iter().map(|hop| hop.link).collect()
+    }
+}
+
+/// A topology snapshot indexed for constant-time lookups.
+///
+/// Indexes refer to positions in the wrapped [`MediaV2Topology`], which is
+/// kept unchanged and available through [`MediaGraph::topology`].
+#[derive(Debug, Clone)]
+pub struct MediaGraph {
+    topology: MediaV2Topology,
+    entity_by_id: HashMap<EntityId, usize>,
+    entity_by_name: HashMap<String, usize>,
+    pad_by_id: HashMap<PadId, usize>,
+    pads_by_entity: HashMap<EntityId, Vec<usize>>,
+    links_by_pad: HashMap<PadId, Vec<usize>>,
+    interface_by_id: HashMap<InterfaceId, usize>,
+    interfaces_by_entity: HashMap<EntityId, Vec<usize>>,
+    pub(crate) legacy: BTreeMap<EntityId, LegacyEntity>,
+    #[cfg(feature = "subdev")]
+    pub(crate) routing: BTreeMap<EntityId, Vec<crate::SubdevRoute>>,
+}
+
+/// Graphs are equal when their topologies, legacy details and routing tables
+/// are, the indexes are derived.
+impl PartialEq for MediaGraph {
+    fn eq(&self, other: &MediaGraph) -> bool {
+        #[cfg(feature = "subdev")]
+        if self.routing != other.routing {
+            return false;
+        }
+        self.topology == other.topology && self.legacy == other.legacy
+    }
+}
+
+impl Eq for MediaGraph {}
+
+impl Hash for MediaGraph {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.topology.hash(state);
+        self.legacy.hash(state);
+        #[cfg(feature = "subdev")]
+        self.routing.hash(state);
+    }
+}
+
+impl MediaGraph {
+    pub fn new(topology: MediaV2Topology) -> MediaGraph {
+        let entity_by_id = topology
+            .entities
+            .iter()
+            .enumerate()
+            .map(|(i, entity)| (entity.id, i))
+            .collect();
+        let entity_by_name = topology
+            .entities
+            .iter()
+            .enumerate()
+            .map(|(i, entity)| (entity.name.to_string(), i))
+            .collect();
+        let pad_by_id = topology
+            .pads
+            .iter()
+            .enumerate()
+            .map(|(i, pad)| (pad.id, i))
+            .collect();
+        let interface_by_id: HashMap<InterfaceId, usize> = topology
+            .interfaces
+            .iter()
+            .enumerate()
+            .map(|(i, intf)| (intf.id, i))
+            .collect();
+
+        let mut pads_by_entity: HashMap<EntityId, Vec<usize>> = HashMap::new();
+        for (i, pad) in topology.pads.iter().enumerate() {
+            pads_by_entity.entry(pad.entity_id).or_default().push(i);
+        }
+        for pads in pads_by_entity.values_mut() {
+            pads.sort_by_key(|&i| topology.pads[i].index);
+        }
+
+        let mut links_by_pad: HashMap<PadId, Vec<usize>> = HashMap::new();
+        let mut interfaces_by_entity: HashMap<EntityId, Vec<usize>> = HashMap::new();
+        for (i, link) in topology.links.iter().enumerate() {
+            if let (Some(source), Some(sink)) = (link.source_pad(), link.sink_pad()) {
+                links_by_pad.entry(source).or_default().push(i);
+                links_by_pad.entry(sink).or_default().push(i);
+            }
+            if let (Some(intf), Some(entity)) = (link.source_interface(), link.sink_entity()) {
+                if let Some(&intf_index) = interface_by_id.get(&intf) {
+                    interfaces_by_entity
+                        .entry(entity)
+                        .or_default()
+                        .push(intf_index);
+                }
+            }
+        }
+
+        MediaGraph {
+            topology,
+            entity_by_id,
+            entity_by_name,
+            pad_by_id,
+            pads_by_entity,
+            links_by_pad,
+            interface_by_id,
+            interfaces_by_entity,
+            legacy: BTreeMap::new(),
+            #[cfg(feature = "subdev")]
+            routing: BTreeMap::new(),
+        }
+    }
+
+    pub fn topology(&self) -> &MediaV2Topology {
+        &self.topology
+    }
+
+    pub fn into_topology(self) -> MediaV2Topology {
+        self.topology
+    }
+
+    pub fn entity(&self, id: EntityId) -> Option<&MediaV2Entity> {
+        let &i = self.entity_by_id.get(&id)?;
+        Some(&self.topology.entities[i])
+    }
+
+    /// Entity names are unique within a media device.
+    pub fn entity_by_name(&self, name: &str) -> Option<&MediaV2Entity> {
+        let &i = self.entity_by_name.get(name)?;
+        Some(&self.topology.entities[i])
+    }
+
+    pub fn pad(&self, id: PadId) -> Option<&MediaV2Pad> {
+        let &i = self.pad_by_id.get(&id)?;
+        Some(&self.topology.pads[i])
+    }
+
+    /// Pads of an entity, ordered by pad index.
+    pub fn pads(&self, entity: EntityId) -> impl Iterator<Item = &MediaV2Pad> {
+        self.pads_by_entity
+            .get(&entity)
+            .map_or(&[][..], Vec::as_slice)
+            .iter()
+            .map(|&i| &self.topology.pads[i])
+    }
+
+    pub fn pad_by_index(&self, entity: EntityId, index: u32) -> Option<&MediaV2Pad> {
+        self.pads(entity).find(|pad| pad.index == index)
+    }
+
+    /// Data links with `pad` as their source or sink.
+    pub fn links(&self, pad: PadId) -> impl Iterator<Item = &MediaV2Link> {
+        self.links_by_pad
+            .get(&pad)
+            .map_or(&[][..], Vec::as_slice)
+            .iter()
+            .map(|&i| &self.topology.links[i])
+    }
+
+    pub fn interface_by_id(&self, id: InterfaceId) -> Option<&MediaV2Interface> {
+        let &i = self.interface_by_id.get(&id)?;
+        Some(&self.topology.interfaces[i])
+    }
+
+    /// Pads at the other end of the data links of `pad`, whether the links
+    /// are enabled or not.
+    pub fn remote_pads(&self, pad: PadId) -> Vec<&MediaV2Pad> {
+        self.links(pad)
+            .filter_map(|link| {
+                let remote = if link.source_id == pad.0 {
+                    link.sink_pad()?
+                } else {
+                    link.source_pad()?
+                };
+                self.pad(remote)
+            })
+            .collect()
+    }
+
+    /// Entities feeding data into the sink pads of `entity`.
+    pub fn upstream_entities(&self, entity: EntityId) -> Vec<&MediaV2Entity> {
+        self.neighbours(entity, PadFlags::SINK)
+    }
+
+    /// Entities receiving data from the source pads of `entity`.
+    pub fn downstream_entities(&self, entity: EntityId) -> Vec<&MediaV2Entity> {
+        self.neighbours(entity, PadFlags::SOURCE)
+    }
+
+    /// Entities connected through the pads of `entity` having `direction`,
+    /// in pad order and without duplicates.
+    fn neighbours(&self, entity: EntityId, direction: PadFlags) -> Vec<&MediaV2Entity> {
+        let mut neighbours: Vec<&MediaV2Entity> = Vec::new();
+        for pad in self.pads(entity) {
+            if !pad.pad_flags().contains(direction) {
+                continue;
+            }
+            for remote in self.remote_pads(pad.id) {
+                if let Some(remote_entity) = self.entity(remote.entity_id) {
+                    if !neighbours.iter().any(|e| e.id == remote_entity.id) {
+                        neighbours.push(remote_entity);
+                    }
+                }
+            }
+        }
+        neighbours
+    }
+
+    /// Shortest route from `from` to `to` following data links from source to
+    /// sink pads, regardless of whether the links are enabled. A path from an
+    /// entity to itself is empty.
+    pub fn find_path(&self, from: EntityId, to: EntityId) -> Option<MediaPath> {
+        let mut reached_by: HashMap<EntityId, Option<PathHop>> = HashMap::new();
+        reached_by.insert(from, None);
+        let mut queue = VecDeque::from([from]);
+
+        while let Some(entity) = queue.pop_front() {
+            if entity == to {
+                let mut hops = Vec::new();
+                let mut current = to;
+                while let Some(Some(hop)) = reached_by.get(&current) {
+                    hops.push(*hop);
+                    current = hop.source_entity;
+                }
+                hops.reverse();
+                return Some(MediaPath { hops });
+            }
+            for hop in self.outgoing_hops(entity) {
+                if let Entry::Vacant(entry) = reached_by.entry(hop.sink_entity) {
+                    entry.insert(Some(hop));
+                    queue.push_back(hop.sink_entity);
+                }
+            }
+        }
+        None
+    }
+
+    /// Every route from `from` to `to` that visits each entity at most once,
+    /// following data links from source to sink pads. Stops after
+    /// [`MAX_PATHS`] routes, as their number grows exponentially with
+    /// parallel links.
+    pub fn find_all_paths(&self, from: EntityId, to: EntityId) -> Vec<MediaPath> {
+        if from == to {
+            return vec![MediaPath::default()];
+        }
+        let mut paths = Vec::new();
+        let mut visited = HashSet::from([from]);
+        let mut hops: Vec<PathHop> = Vec::new();
+        // Depth-first with an explicit stack like `find_cycle_from`: the hops
+        // not followed yet from `from` and from each sink entity of `hops`.
+        let mut stack = vec![self.outgoing_hops(from).into_iter()];
+        while let Some(pending) = stack.last_mut() {
+            let Some(hop) = pending.next() else {
+                stack.pop();
+                if let Some(hop) = hops.pop() {
+                    visited.remove(&hop.sink_entity);
+                }
+                continue;
+            };
+            if !visited.insert(hop.sink_entity) {
+                continue;
+            }
+            hops.push(hop);
+            if hop.sink_entity != to {
+                stack.push(self.outgoing_hops(hop.sink_entity).into_iter());
+                continue;
+            }
+            paths.push(MediaPath { hops: hops.clone() });
+            if paths.len() == MAX_PATHS {
+                break;
+            }
+            hops.pop();
+            visited.remove(&to);
+        }
+        paths
+    }
+
+    /// Entities ordered so that every entity comes after all entities feeding
+    /// data into it, considering all data links. Fails with one of the loops
+    /// if the graph is cyclic.
+    pub fn topological_order(&self) -> Result<Vec<EntityId>, Cycle> {
+        let entities: Vec<EntityId> = self.topology.entities.iter().map(|e| e.id).collect();
+        let mut in_degree: HashMap<EntityId, usize> = entities.iter().map(|&e| (e, 0)).collect();
+        // Links to pads of entities missing from the topology are ignored.
+        for &entity in &entities {
+            for hop in self.outgoing_hops(entity) {
+                if let Some(degree) = in_degree.get_mut(&hop.sink_entity) {
+                    *degree += 1;
+                }
+            }
+        }
+
+        let mut queue: VecDeque<EntityId> = entities
+            .iter()
+            .copied()
+            .filter(|e| in_degree[e] == 0)
+            .collect();
+        let mut order = Vec::with_capacity(entities.len());
+        while let Some(entity) = queue.pop_front() {
+            order.push(entity);
+            for hop in self.outgoing_hops(entity) {
+                let Some(degree) = in_degree.get_mut(&hop.sink_entity) else {
+                    continue;
+                };
+                *degree -= 1;
+                if *degree == 0 {
+                    queue.push_back(hop.sink_entity);
+                }
+            }
+        }
+
+        if order.len() != entities.len() {
+            // Only a loop keeps entities out of the order; if the search
+            // doesn't find it, report the entities left out.
+            return Err(self.detect_cycles().unwrap_or_else(|| Cycle {
+                entities: entities
+                    .iter()
+                    .copied()
+                    .filter(|e| in_degree[e] > 0)
+                    .collect(),
+            }));
+        }
+        Ok(order)
+    }
+
+    /// Finds a loop of entities connected by data links, if there is one.
+    pub fn detect_cycles(&self) -> Option<Cycle> {
+        let mut finished = HashSet::new();
+        for entity in &self.topology.entities {
+            if let Some(cycle) = self.find_cycle_from(entity.id, &mut finished) {
+                return Some(cycle);
+            }
+        }
+        None
+    }
+
+    /// Depth-first search for a loop through `start`'s descendants, with an
+    /// explicit stack so that long pipelines can't overflow the thread's.
+    fn find_cycle_from(&self, start: EntityId, finished: &mut HashSet<EntityId>) -> Option<Cycle> {
+        if finished.contains(&start) {
+            return None;
+        }
+        // The current path, each entity with its hops not followed yet.
+        let mut stack = vec![(start, self.outgoing_hops(start).into_iter())];
+        while let Some((entity, hops)) = stack.last_mut() {
+            let Some(hop) = hops.next() else {
+                finished.insert(*entity);
+                stack.pop();
+                continue;
+            };
+            let next = hop.sink_entity;
+            if finished.contains(&next) {
+                continue;
+            }
+            if let Some(start) = stack.iter().position(|&(e, _)| e == next) {
+                return Some(Cycle {
+                    entities: stack[start..].iter().map(|&(e, _)| e).collect(),
+                });
+            }
+            stack.push((next, self.outgoing_hops(next).into_iter()));
+        }
+        None
+    }
+
+    /// Data links leaving the source pads of `entity`.
+    fn outgoing_hops(&self, entity: EntityId) -> Vec<PathHop> {
+        let mut hops = Vec::new();
+        for pad in self.pads(entity) {
+            for link in self.links(pad.id) {
+                if link.source_pad() != Some(pad.id) {
+                    continue;
+                }
+                let Some(sink) = link.sink_pad().and_then(|id| self.pad(id)) else {
+                    continue;
+                };
+                hops.push(PathHop {
+                    source_entity: entity,
+                    source_pad: pad.id,
+                    link: link.id,
+                    sink_entity: sink.entity_id,
+                    sink_pad: sink.id,
+                });
+            }
+        }
+        hops
+    }
+
+    /// The connected component containing `entity`: every entity reachable
+    /// through data or ancillary links in either direction, with their pads,
+    /// the links among them and the interfaces controlling them.
+    ///
+    /// Returns `None` if the entity doesn't exist.
+    pub fn subgraph(&self, entity: EntityId) -> Option<MediaGraph> {
+        self.entity(entity)?;
+
+        let mut component = HashSet::from([entity]);
+        let mut queue = VecDeque::from([entity]);
+        while let Some(current) = queue.pop_front() {
+            let mut neighbours: Vec<EntityId> = self
+                .pads(current)
+                .flat_map(|pad| self.remote_pads(pad.id))
+                .map(|pad| pad.entity_id)
+                .collect();
+            neighbours.extend(self.topology.ancillary_peers(current).iter().map(|e| e.id));
+            for neighbour in neighbours {
+                if component.insert(neighbour) {
+                    queue.push_back(neighbour);
+                }
+            }
+        }
+
+        let pads: Vec<MediaV2Pad> = self
+            .topology
+            .pads
+            .iter()
+            .filter(|pad| component.contains(&pad.entity_id))
+            .cloned()
+            .collect();
+        let pad_ids: HashSet<PadId> = pads.iter().map(|pad| pad.id).collect();
+        let interfaces: Vec<MediaV2Interface> = self
+            .topology
+            .interfaces
+            .iter()
+            .filter(|intf| {
+                component
+                    .iter()
+                    .any(|&e| self.interfaces(e).any(|i| i.id == intf.id))
+            })
+            .cloned()
+            .collect();
+        let interface_ids: HashSet<InterfaceId> = interfaces.iter().map(|intf| intf.id).collect();
+        let links = self
+            .topology
+            .links
+            .iter()
+            .filter(|link| match link.kind() {
+                LinkKind::Data => {
+                    link.source_pad()
+                        .zip(link.sink_pad())
+                        .is_some_and(|(source, sink)| {
+                            pad_ids.contains(&source) && pad_ids.contains(&sink)
+                        })
+                }
+                LinkKind::Interface => link
+                    .source_interface()
+                    .is_some_and(|intf| interface_ids.contains(&intf)),
+                LinkKind::Ancillary => link
+                    .sink_entity()
+                    .is_some_and(|sink| component.contains(&sink)),
+                LinkKind::Unknown(_) => false,
+            })
+            .cloned()
+            .collect();
+
+        Some(MediaGraph::new(MediaV2Topology {
+            topology_version: self.topology.topology_version,
+            entities: self
+                .topology
+                .entities
+                .iter()
+                .filter(|e| component.contains(&e.id))
+                .cloned()
+                .collect(),
+            interfaces,
+            pads,
+            links,
+        }))
+    }
+
+    /// Interfaces controlling an entity, e.g. the `/dev/videoN` node of a
+    /// video IO entity.
+    pub fn interfaces(&self, entity: EntityId) -> impl Iterator<Item = &MediaV2Interface> {
+        self.interfaces_by_entity
+            .get(&entity)
+            .map_or(&[][..], Vec::as_slice)
+            .iter()
+            .map(|&i| &self.topology.interfaces[i])
+    }
+
+    /// The interface of an entity. Entities have at most one interface in
+    /// practice.
+    pub fn interface(&self, entity: EntityId) -> Option<&MediaV2Interface> {
+        self.interfaces(entity).next()
+    }
+}
+
+impl From<MediaV2Topology> for MediaGraph {
+    fn from(topology: MediaV2Topology) -> MediaGraph {
+        MediaGraph::new(topology)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{media_ffi, raw};
+
+    /// `stages + 1` entities in a row, each linked to the next by `parallel`
+    /// links between the same pads.
+    fn chain(stages: u32, parallel: u32) -> MediaGraph {
+        let entities: Vec<_> = (0..=stages)
+            .map(|id| {
+                let mut entity: media_ffi::media_v2_entity = raw::zeroed();
+                entity.id = id;
+                entity
+            })
+            .collect();
+        // Pad `2 * id` is the sink of entity `id`, `2 * id + 1` its source.
+        let pads: Vec<_> = (0..=stages)
+            .flat_map(|id| {
+                [media_ffi::MEDIA_PAD_FL_SINK, media_ffi::MEDIA_PAD_FL_SOURCE]
+                    .into_iter()
+                    .zip(0..)
+                    .map(move |(flags, index)| {
+                        let mut pad: media_ffi::media_v2_pad = raw::zeroed();
+                        pad.id = 2 * id + index;
+                        pad.entity_id = id;
+                        pad.index = index;
+                        pad.flags = flags;
+                        pad
+                    })
+            })
+            .collect();
+        let links: Vec<_> = (0..stages * parallel)
+            .map(|id| {
+                let stage = id / parallel;
+                let mut link: media_ffi::media_v2_link = raw::zeroed();
+                link.id = id;
+                link.source_id = 2 * stage + 1;
+                link.sink_id = 2 * (stage + 1);
+                link.flags = media_ffi::MEDIA_LNK_FL_DATA_LINK;
+                link
+            })
+            .collect();
+        MediaGraph::new(MediaV2Topology::from_ffi(
+            &raw::zeroed(),
+            &entities,
+            &[],
+            &pads,
+            &links,
+        ))
+    }
+
+    #[test]
+    fn all_paths_through_parallel_links() {
+        let graph = chain(2, 2);
+        let paths = graph.find_all_paths(EntityId(0), EntityId(2));
+        let links: Vec<Vec<u32>> = paths
+            .iter()
+            .map(|path| path.hops.iter().map(|hop| hop.link.into()).collect())
+            .collect();
+        assert_eq!(links, [[0, 2], [0, 3], [1, 2], [1, 3]]);
+        assert_eq!(
+            graph.find_all_paths(EntityId(1), EntityId(1)),
+            [MediaPath::default()]
+        );
+        assert!(graph.find_all_paths(EntityId(2), EntityId(0)).is_empty());
+    }
+
+    #[test]
+    fn all_paths_of_a_long_pipeline() {
+        let graph = chain(100_000, 1);
+        let paths = graph.find_all_paths(EntityId(0), EntityId(100_000));
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths[0].hops.len(), 100_000);
+    }
+
+    #[test]
+    fn all_paths_are_limited() {
+        let graph = chain(12, 2);
+        let paths = graph.find_all_paths(EntityId(0), EntityId(12));
+        assert_eq!(paths.len(), MAX_PATHS);
+        assert!(paths.iter().all(|path| path.hops.len() == 12));
+    }
+}
@@ -0,0 +1,157 @@
+use std::{path::PathBuf, process::Command, time::Duration};
+
+use crate::{
+    find_device, wait_for_device, DeviceMatch, EntityFunction, EntitySpec, LinkSpec, MediaDevice,
+    MediaError, Pattern, TopologySpec,
+};
+
+/// A virtual media driver of the kernel, providing a real media device on
+/// machines without camera hardware, e.g. CI VMs.
+///
+/// Integration tests look the device up with [`VirtualDriver::find`] and skip
+/// when it's missing, or load the module first with
+/// [`VirtualDriver::find_or_load`]:
+///
+/// ```no_run
+/// use mc_api::{assert_topology_matches, VirtualDriver};
+///
+/// let Some((_, device)) = VirtualDriver::Vimc.find()? else {
+///     eprintln!("vimc not loaded, skipping");
+///     return Ok(());
+/// };
+/// assert_topology_matches(&VirtualDriver::Vimc.expected_topology(), &device.topology()?);
+/// # Ok::<(), mc_api::MediaError>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VirtualDriver {
+    /// Virtual Media Controller driver, a camera pipeline of sensors,
+    /// debayers and a scaler.
+    Vimc,
+    /// Virtual video test driver, standalone capture and output nodes.
+    Vivid,
+}
+
+impl VirtualDriver {
+    /// The kernel module, which is also the driver name it reports.
+    pub fn module(&self) -> &'static str {
+        match self {
+            VirtualDriver::Vimc => "vimc",
+            VirtualDriver::Vivid => "vivid",
+        }
+    }
+
+    pub fn matcher(&self) -> DeviceMatch {
+        DeviceMatch::new().driver(Pattern::Exact(self.module().to_string()))
+    }
+
+    /// Opens the first media device of the driver, `None` if the module
+    /// isn't loaded.
+    pub fn find(&self) -> Result<Option<(PathBuf, MediaDevice)>, MediaError> {
+        find_device(&self.matcher())
+    }
+
+    /// Like [`VirtualDriver::find`], but if no device exists, loads the
+    /// module with `modprobe` and waits up to `timeout` for its device to
+    /// appear. Loading needs root.
+    pub fn find_or_load(
+        &self,
+        timeout: Duration,
+    ) -> Result<Option<(PathBuf, MediaDevice)>, MediaError> {
+        if let Some(found) = self.find()? {
+            return Ok(Some(found));
+        }
+        self.load()?;
+        wait_for_device(&self.matcher(), Some(timeout))
+    }
+
+    /// Loads the module with `modprobe`.
+    pub fn load(&self) -> Result<(), MediaError> {
+        let module = self.module();
+        let output = Command::new("modprobe")
+            .arg(module)
+            .output()
+            .map_err(|err| MediaError::LoadModule {
+                module,
+                reason: err.to_string(),
+            })?;
+        if !output.status.success() {
+            return Err(MediaError::LoadModule {
+                module,
+                reason: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// The topology of the device the driver creates with its default module
+    /// parameters.
+    ///
+    /// Entities added by later kernels, such as the lenses of vimc, and the
+    /// optional nodes of vivid are accepted as extras.
+    pub fn expected_topology(&self) -> TopologySpec {
+        match self {
+            VirtualDriver::Vimc => vimc_topology(),
+            VirtualDriver::Vivid => vivid_topology(),
+        }
+    }
+}
+
+fn entity(name: &str, function: EntityFunction, pads: Option<usize>) -> EntitySpec {
+    EntitySpec {
+        name: name.to_string(),
+        function: Some(function),
+        pads,
+        ..EntitySpec::default()
+    }
+}
+
+fn link(source: &str, source_pad: u32, sink: &str, enabled: bool, immutable: bool) -> LinkSpec {
+    LinkSpec {
+        source: source.to_string(),
+        source_pad,
+        sink: sink.to_string(),
+        sink_pad: 0,
+        enabled: Some(enabled),
+        immutable: Some(immutable),
+    }
+}
+
+fn vimc_topology() -> TopologySpec {
+    TopologySpec {
+        topology_version: None,
+        entities: vec![
+            entity("Sensor A", EntityFunction::CamSensor, Some(1)),
+            entity("Sensor B", EntityFunction::CamSensor, Some(1)),
+            entity("Debayer A", EntityFunction::ProcVideoPixelEncConv, Some(2)),
+            entity("Debayer B", EntityFunction::ProcVideoPixelEncConv, Some(2)),
+            entity("Raw Capture 0", EntityFunction::IoV4l, Some(1)),
+            entity("Raw Capture 1", EntityFunction::IoV4l, Some(1)),
+            entity("RGB/YUV Input", EntityFunction::CamSensor, Some(1)),
+            entity("Scaler", EntityFunction::ProcVideoScaler, Some(2)),
+            entity("RGB/YUV Capture", EntityFunction::IoV4l, Some(1)),
+        ],
+        links: vec![
+            link("Sensor A", 0, "Debayer A", true, true),
+            link("Sensor A", 0, "Raw Capture 0", true, true),
+            link("Sensor B", 0, "Debayer B", true, true),
+            link("Sensor B", 0, "Raw Capture 1", true, true),
+            link("Debayer A", 1, "Scaler", true, false),
+            link("Debayer B", 1, "Scaler", false, false),
+            link("RGB/YUV Input", 0, "Scaler", false, false),
+            link("Scaler", 1, "RGB/YUV Capture", true, true),
+        ],
+        allow_extra: true,
+    }
+}
+
+fn vivid_topology() -> TopologySpec {
+    TopologySpec {
+        topology_version: None,
+        entities: vec![
+            entity("vivid-000-vid-cap", EntityFunction::IoV4l, None),
+            entity("vivid-000-vid-out", EntityFunction::IoV4l, None),
+        ],
+        links: Vec::new(),
+        allow_extra: true,
+    }
+}
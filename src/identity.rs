@@ -0,0 +1,62 @@
+use std::{collections::BTreeMap, fmt, path::PathBuf, str::FromStr};
+
+use crate::{enumerate_devices, MediaDeviceInfo, MediaError, ParseEnumError};
+
+/// Key identifying a media device independently of its `/dev/mediaN`
+/// number, which depends on probe order.
+///
+/// Formats as `driver/bus_info/model`, which [`FromStr`] parses back, for
+/// use in configuration files.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeviceIdentity {
+    pub driver: String,
+    pub bus_info: String,
+    pub model: String,
+}
+
+impl MediaDeviceInfo {
+    pub fn identity(&self) -> DeviceIdentity {
+        DeviceIdentity {
+            driver: self.driver.clone(),
+            bus_info: self.bus_info.clone(),
+            model: self.model.clone(),
+        }
+    }
+}
+
+impl fmt::Display for DeviceIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}/{}", self.driver, self.bus_info, self.model)
+    }
+}
+
+/// The model comes last as it is free-form text that may contain `/`.
+impl FromStr for DeviceIdentity {
+    type Err = ParseEnumError;
+
+    fn from_str(s: &str) -> Result<DeviceIdentity, ParseEnumError> {
+        let mut parts = s.splitn(3, '/');
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(driver), Some(bus_info), Some(model)) => Ok(DeviceIdentity {
+                driver: driver.to_string(),
+                bus_info: bus_info.to_string(),
+                model: model.to_string(),
+            }),
+            _ => Err(ParseEnumError(s.to_string())),
+        }
+    }
+}
+
+/// Maps the identity of each `/dev/mediaN` node to its path. Nodes that can't
+/// be queried are left out. Should several nodes share an identity, the
+/// lowest numbered one is kept.
+pub fn enumerate_devices_by_identity() -> Result<BTreeMap<DeviceIdentity, PathBuf>, MediaError> {
+    let mut devices = BTreeMap::new();
+    for device in enumerate_devices()? {
+        if let Ok(info) = device.info {
+            devices.entry(info.identity()).or_insert(device.path);
+        }
+    }
+    Ok(devices)
+}
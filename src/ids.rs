@@ -0,0 +1,58 @@
+use std::fmt;
+
+/// Declares a newtype over a raw media graph object ID.
+macro_rules! object_id {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+        #[cfg_attr(feature = "serde", serde(transparent))]
+        pub struct $name(pub u32);
+
+        impl From<u32> for $name {
+            fn from(id: u32) -> $name {
+                $name(id)
+            }
+        }
+
+        impl From<$name> for u32 {
+            fn from(id: $name) -> u32 {
+                id.0
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.0.fmt(f)
+            }
+        }
+
+        #[cfg(feature = "proptest")]
+        impl proptest::arbitrary::Arbitrary for $name {
+            type Parameters = ();
+            type Strategy = proptest::strategy::Map<proptest::num::u32::Any, fn(u32) -> $name>;
+
+            fn arbitrary_with(_: ()) -> Self::Strategy {
+                use proptest::strategy::Strategy;
+                proptest::num::u32::ANY.prop_map($name)
+            }
+        }
+    };
+}
+
+object_id!(
+    /// ID of an entity, shared by `MEDIA_IOC_G_TOPOLOGY` and the legacy API.
+    EntityId
+);
+object_id!(
+    /// ID of a pad as reported by `MEDIA_IOC_G_TOPOLOGY`.
+    PadId
+);
+object_id!(
+    /// ID of a link as reported by `MEDIA_IOC_G_TOPOLOGY`.
+    LinkId
+);
+object_id!(
+    /// ID of an interface as reported by `MEDIA_IOC_G_TOPOLOGY`.
+    InterfaceId
+);
@@ -0,0 +1,111 @@
+use std::{collections::HashSet, os::fd::AsFd, os::raw::c_char, sync::Arc};
+
+#[cfg(feature = "unstable-ffi")]
+use crate::ffi;
+use crate::{
+    c_str_bytes, c_str_to_arc, topology_ref, MediaDevice, MediaError, MediaV2Entity,
+    MediaV2Topology, TopologyBuffers,
+};
+
+/// Pool of entity names shared between topology snapshots.
+///
+/// Snapshots taken through [`MediaDevice::topology_interned`] point their
+/// entity names at the pooled strings, so repeatedly polled devices keep a
+/// single copy of each name, and names of different snapshots can be compared
+/// with [`Arc::ptr_eq`].
+#[derive(Debug, Clone, Default)]
+pub struct NameInterner {
+    names: HashSet<Arc<str>>,
+}
+
+impl NameInterner {
+    pub fn new() -> NameInterner {
+        NameInterner::default()
+    }
+
+    /// The pooled copy of `name`, added to the pool if it isn't there yet.
+    pub fn intern(&mut self, name: &str) -> Arc<str> {
+        if let Some(interned) = self.names.get(name) {
+            return interned.clone();
+        }
+        let interned: Arc<str> = Arc::from(name);
+        self.names.insert(interned.clone());
+        interned
+    }
+
+    /// [`NameInterner::intern`] for a kernel string, only decoded into a new
+    /// string if it isn't pooled yet.
+    pub(crate) fn intern_c_str(&mut self, c_str: &[c_char]) -> Arc<str> {
+        match std::str::from_utf8(c_str_bytes(c_str)) {
+            Ok(name) => self.intern(name),
+            Err(_) => self.intern(&c_str_to_arc(c_str)),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// Drops the names no snapshot refers to anymore, e.g. those of entities
+    /// that were removed.
+    pub fn purge_unused(&mut self) {
+        self.names.retain(|name| Arc::strong_count(name) > 1);
+    }
+}
+
+impl MediaV2Entity {
+    /// The name as a shared string, see [`NameInterner`].
+    pub fn name_arc(&self) -> &Arc<str> {
+        &self.name
+    }
+}
+
+impl MediaV2Topology {
+    /// [`MediaV2Topology::from_raw_parts`] with the entity names taken from
+    /// `interner`.
+    #[cfg(feature = "unstable-ffi")]
+    pub fn from_raw_parts_interned(
+        topology: &ffi::media_v2_topology,
+        entities: &[ffi::media_v2_entity],
+        interfaces: &[ffi::media_v2_interface],
+        pads: &[ffi::media_v2_pad],
+        links: &[ffi::media_v2_link],
+        interner: &mut NameInterner,
+    ) -> MediaV2Topology {
+        MediaV2Topology::from_ffi_with_names(
+            topology.topology_version,
+            entities,
+            interfaces,
+            pads,
+            links,
+            |entity| interner.intern_c_str(&entity.name),
+        )
+    }
+
+    /// Replaces the entity names with their pooled copies in `interner`.
+    pub fn intern_names(&mut self, interner: &mut NameInterner) {
+        for entity in &mut self.entities {
+            entity.name = interner.intern(&entity.name);
+        }
+    }
+}
+
+impl MediaDevice {
+    /// [`MediaDevice::topology`] with entity names taken from `interner`:
+    /// names already pooled are looked up in the kernel structs, without
+    /// decoding them into new strings.
+    pub fn topology_interned(
+        &self,
+        interner: &mut NameInterner,
+    ) -> Result<MediaV2Topology, MediaError> {
+        topology_ref::get_topology_interned_from_fd(
+            self.as_fd(),
+            &mut TopologyBuffers::new(),
+            interner,
+        )
+    }
+}
@@ -0,0 +1,216 @@
+//! Versioned JSON format for topology dumps.
+//!
+//! The format is defined by the private `*V1` types below rather than by the
+//! serde derives of the public types, so it stays stable as the crate evolves.
+//! Schema 1 is an object with the fields:
+//!
+//! - `schema`: `1`
+//! - `topology_version`: integer
+//! - `entities`: `{id, name, function, flags}` objects, plus `name_bytes` (array
+//!   of bytes) when the kernel name isn't valid UTF-8
+//! - `interfaces`: `{id, intf_type, flags, devnode}` objects, `devnode` being
+//!   `{major, minor}` or `null`
+//! - `pads`: `{id, entity_id, flags, index}` objects
+//! - `links`: `{id, source_id, sink_id, flags}` objects
+//!
+//! `function` and `intf_type` hold the UAPI constant name, e.g.
+//! `MEDIA_ENT_F_CAM_SENSOR`, or a `0x` hexadecimal value for constants unknown
+//! to the producer. Readers ignore fields they don't know, so new fields may
+//! be added without bumping the schema.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    KernelStruct, MediaError, MediaV2Entity, MediaV2Interface, MediaV2IntfDevnode, MediaV2Link,
+    MediaV2Pad, MediaV2Topology, RawBytes,
+};
+
+/// Schema written by [`MediaV2Topology::to_json`].
+pub const TOPOLOGY_JSON_SCHEMA: u32 = 1;
+
+#[derive(Deserialize)]
+struct SchemaHeader {
+    schema: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct TopologyV1 {
+    schema: u32,
+    topology_version: u64,
+    entities: Vec<EntityV1>,
+    interfaces: Vec<InterfaceV1>,
+    pads: Vec<PadV1>,
+    links: Vec<LinkV1>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct EntityV1 {
+    id: u32,
+    name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name_bytes: Option<Vec<u8>>,
+    function: String,
+    flags: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct InterfaceV1 {
+    id: u32,
+    intf_type: String,
+    flags: u32,
+    devnode: Option<DevnodeV1>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct DevnodeV1 {
+    major: u32,
+    minor: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PadV1 {
+    id: u32,
+    entity_id: u32,
+    flags: u32,
+    index: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct LinkV1 {
+    id: u32,
+    source_id: u32,
+    sink_id: u32,
+    flags: u32,
+}
+
+impl MediaV2Topology {
+    /// Serializes the topology in the versioned format described in the
+    /// [module documentation](self).
+    pub fn to_json(&self) -> String {
+        let dump = TopologyV1 {
+            schema: TOPOLOGY_JSON_SCHEMA,
+            topology_version: self.topology_version,
+            entities: self
+                .entities
+                .iter()
+                .map(|entity| EntityV1 {
+                    id: entity.id.into(),
+                    name: entity.name.to_string(),
+                    name_bytes: entity.name_raw.as_ref().map(|raw| raw.0.clone()),
+                    function: entity.function.to_string(),
+                    flags: entity.flags,
+                })
+                .collect(),
+            interfaces: self
+                .interfaces
+                .iter()
+                .map(|intf| InterfaceV1 {
+                    id: intf.id.into(),
+                    intf_type: intf.intf_type.to_string(),
+                    flags: intf.flags,
+                    devnode: intf.devnode.as_ref().map(|devnode| DevnodeV1 {
+                        major: devnode.major,
+                        minor: devnode.minor,
+                    }),
+                })
+                .collect(),
+            pads: self
+                .pads
+                .iter()
+                .map(|pad| PadV1 {
+                    id: pad.id.into(),
+                    entity_id: pad.entity_id.into(),
+                    flags: pad.flags,
+                    index: pad.index,
+                })
+                .collect(),
+            links: self
+                .links
+                .iter()
+                .map(|link| LinkV1 {
+                    id: link.id.into(),
+                    source_id: link.source_id,
+                    sink_id: link.sink_id,
+                    flags: link.flags,
+                })
+                .collect(),
+        };
+        serde_json::to_string(&dump).expect("topology dumps are always serializable")
+    }
+
+    /// Parses a dump produced by [`MediaV2Topology::to_json`], possibly by
+    /// another version of the crate.
+    pub fn from_json(json: &str) -> Result<MediaV2Topology, MediaError> {
+        let header: SchemaHeader = serde_json::from_str(json).map_err(MediaError::Json)?;
+        if header.schema != TOPOLOGY_JSON_SCHEMA {
+            return Err(MediaError::UnsupportedSchema {
+                schema: header.schema,
+            });
+        }
+        let dump: TopologyV1 = serde_json::from_str(json).map_err(MediaError::Json)?;
+        let parse_error = |what: &str, value: &str| {
+            MediaError::Json(serde::de::Error::custom(format!(
+                "invalid {what} {value:?}"
+            )))
+        };
+
+        let mut entities = Vec::with_capacity(dump.entities.len());
+        for entity in dump.entities {
+            entities.push(MediaV2Entity {
+                id: entity.id.into(),
+                function: entity
+                    .function
+                    .parse()
+                    .map_err(|_| parse_error("function", &entity.function))?,
+                name: entity.name.into(),
+                flags: entity.flags,
+                name_raw: entity.name_bytes.map(RawBytes),
+                kernel: KernelStruct::default(),
+            });
+        }
+        let mut interfaces = Vec::with_capacity(dump.interfaces.len());
+        for intf in dump.interfaces {
+            interfaces.push(MediaV2Interface {
+                id: intf.id.into(),
+                intf_type: intf
+                    .intf_type
+                    .parse()
+                    .map_err(|_| parse_error("intf_type", &intf.intf_type))?,
+                flags: intf.flags,
+                devnode: intf.devnode.map(|devnode| MediaV2IntfDevnode {
+                    major: devnode.major,
+                    minor: devnode.minor,
+                }),
+                kernel: KernelStruct::default(),
+            });
+        }
+
+        Ok(MediaV2Topology {
+            topology_version: dump.topology_version,
+            entities,
+            interfaces,
+            pads: dump
+                .pads
+                .into_iter()
+                .map(|pad| MediaV2Pad {
+                    id: pad.id.into(),
+                    entity_id: pad.entity_id.into(),
+                    flags: pad.flags,
+                    index: pad.index,
+                    kernel: KernelStruct::default(),
+                })
+                .collect(),
+            links: dump
+                .links
+                .into_iter()
+                .map(|link| MediaV2Link {
+                    id: link.id.into(),
+                    source_id: link.source_id,
+                    sink_id: link.sink_id,
+                    flags: link.flags,
+                    kernel: KernelStruct::default(),
+                })
+                .collect(),
+        })
+    }
+}
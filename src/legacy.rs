@@ -0,0 +1,394 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    os::fd::{AsFd, BorrowedFd},
+    path::Path,
+};
+
+use nix::errno::Errno;
+
+use crate::media_ffi::{self, media_entity_desc, media_link_desc, media_pad_desc};
+use crate::{
+    c_str_to_str, get_device_info_from_fd, get_topology_from_fd, raw, raw_or_str, EntityFlags,
+    EntityId, Feature, InterfaceId, KernelStruct, LinkFlags, LinkId, MediaDevice, MediaError,
+    MediaGraph, MediaV2Entity, MediaV2Interface, MediaV2IntfDevnode, MediaV2Link, MediaV2Pad,
+    MediaV2Topology, PadFlags, PadId, RawBytes,
+};
+
+/// Entity description returned by the legacy `MEDIA_IOC_ENUM_ENTITIES` ioctl.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MediaEntityDesc {
+    pub id: EntityId,
+    pub name: String,
+    pub entity_type: u32,
+    pub revision: u32,
+    pub flags: u32,
+    pub group_id: u32,
+    pub pads: u16,
+    pub links: u16,
+    /// Device node of the entity, set for V4L2 subdevices and
+    /// `MEDIA_ENT_T_DEVNODE_*` entities exposing a character device.
+    pub devnode: Option<MediaV2IntfDevnode>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    name_raw: Option<RawBytes>,
+}
+
+impl MediaEntityDesc {
+    fn from_ffi(entity: &media_entity_desc) -> MediaEntityDesc {
+        let has_devnode = match entity.type_ & media_ffi::MEDIA_ENT_TYPE_MASK {
+            media_ffi::MEDIA_ENT_T_DEVNODE => entity.type_ != media_ffi::MEDIA_ENT_T_DEVNODE_ALSA,
+            media_ffi::MEDIA_ENT_T_V4L2_SUBDEV => true,
+            _ => false,
+        };
+        let (major, minor) = raw::entity_dev(entity);
+        // Major 0 is never a character device, the driver just doesn't expose a node.
+        let devnode = if has_devnode && major != 0 {
+            Some(MediaV2IntfDevnode { major, minor })
+        } else {
+            None
+        };
+        MediaEntityDesc {
+            id: entity.id.into(),
+            name: c_str_to_str(&entity.name),
+            entity_type: entity.type_,
+            revision: entity.revision,
+            flags: entity.flags,
+            group_id: entity.group_id,
+            pads: entity.pads,
+            links: entity.links,
+            devnode,
+            name_raw: RawBytes::if_lossy(&entity.name),
+        }
+    }
+
+    /// `name` exactly as reported by the kernel, without the terminating NUL.
+    pub fn name_bytes(&self) -> &[u8] {
+        raw_or_str(&self.name_raw, &self.name)
+    }
+
+    pub fn entity_flags(&self) -> EntityFlags {
+        EntityFlags::from_bits_retain(self.flags)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MediaPadDesc {
+    pub entity: EntityId,
+    pub index: u16,
+    pub flags: u32,
+}
+
+impl MediaPadDesc {
+    fn from_ffi(pad: &media_pad_desc) -> MediaPadDesc {
+        MediaPadDesc {
+            entity: pad.entity.into(),
+            index: pad.index,
+            flags: pad.flags,
+        }
+    }
+
+    pub fn pad_flags(&self) -> PadFlags {
+        PadFlags::from_bits_retain(self.flags)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MediaLinkDesc {
+    pub source: MediaPadDesc,
+    pub sink: MediaPadDesc,
+    pub flags: u32,
+}
+
+impl MediaLinkDesc {
+    fn from_ffi(link: &media_link_desc) -> MediaLinkDesc {
+        MediaLinkDesc {
+            source: MediaPadDesc::from_ffi(&link.source),
+            sink: MediaPadDesc::from_ffi(&link.sink),
+            flags: link.flags,
+        }
+    }
+
+    pub fn link_flags(&self) -> LinkFlags {
+        LinkFlags::from_bits_retain(self.flags)
+    }
+}
+
+/// Pads and outbound links of one entity, as returned by `MEDIA_IOC_ENUM_LINKS`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MediaLinksEnum {
+    pub entity: EntityId,
+    pub pads: Vec<MediaPadDesc>,
+    pub links: Vec<MediaLinkDesc>,
+}
+
+/// What the legacy enumeration ioctls report about an entity, see
+/// [`MediaGraph::legacy_entity`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LegacyEntity {
+    pub desc: MediaEntityDesc,
+    /// Pads and outbound data links from `MEDIA_IOC_ENUM_LINKS`.
+    pub links: MediaLinksEnum,
+}
+
+impl MediaGraph {
+    /// Legacy details of `entity`, only present in graphs built by
+    /// [`MediaDevice::graph_with_legacy`].
+    pub fn legacy_entity(&self, entity: EntityId) -> Option<&LegacyEntity> {
+        self.legacy.get(&entity)
+    }
+}
+
+impl MediaDevice {
+    pub fn graph_with_legacy(&self) -> Result<MediaGraph, MediaError> {
+        graph_with_legacy_from_fd(self.fd.as_fd())
+    }
+
+    /// See [`link_graph_from_fd`].
+    pub(crate) fn link_graph(&self) -> Result<MediaGraph, MediaError> {
+        link_graph_from_fd(self.fd.as_fd())
+    }
+
+    pub fn enum_entities(&self) -> Result<Vec<MediaEntityDesc>, MediaError> {
+        enum_entities_from_fd(self.fd.as_fd())
+    }
+
+    pub fn enum_links(&self, entity_id: EntityId) -> Result<MediaLinksEnum, MediaError> {
+        enum_links_from_fd(self.fd.as_fd(), entity_id)
+    }
+}
+
+/// Enumerates all entities by repeatedly asking the kernel for the entity
+/// following the last seen ID (`MEDIA_ENT_ID_FLAG_NEXT`) until it reports
+/// `EINVAL`.
+pub fn enum_entities_from_fd(fd: BorrowedFd) -> Result<Vec<MediaEntityDesc>, MediaError> {
+    let mut entities = Vec::new();
+    let mut id = 0;
+    loop {
+        match enum_entity(fd, id | media_ffi::MEDIA_ENT_ID_FLAG_NEXT) {
+            Ok(entity) => {
+                id = entity.id;
+                entities.push(MediaEntityDesc::from_ffi(&entity));
+            }
+            Err(Errno::EINVAL) => return Ok(entities),
+            Err(errno) => return Err(MediaError::ioctl("MEDIA_IOC_ENUM_ENTITIES", errno)),
+        }
+    }
+}
+
+pub fn enum_links_from_fd(
+    fd: BorrowedFd,
+    entity_id: EntityId,
+) -> Result<MediaLinksEnum, MediaError> {
+    let (_, pads, links) = raw::enum_links(fd, entity_id.into())
+        .map_err(|errno| MediaError::ioctl("MEDIA_IOC_ENUM_LINKS", errno))?;
+
+    Ok(MediaLinksEnum {
+        entity: entity_id,
+        pads: pads.iter().map(MediaPadDesc::from_ffi).collect(),
+        links: links.iter().map(MediaLinkDesc::from_ffi).collect(),
+    })
+}
+
+/// Indexes the topology as a graph carrying the `MEDIA_IOC_ENUM_ENTITIES`
+/// and `MEDIA_IOC_ENUM_LINKS` data of every entity.
+///
+/// Kernels older than 4.19 don't report pad indexes in `MEDIA_IOC_G_TOPOLOGY`,
+/// leaving them all 0. The indexes of such entities are taken from the legacy
+/// pad table, which lists the pads in the same order.
+pub fn graph_with_legacy_from_fd(fd: BorrowedFd) -> Result<MediaGraph, MediaError> {
+    let mut topology = get_topology_from_fd(fd)?;
+    let mut legacy = BTreeMap::new();
+    for desc in enum_entities_from_fd(fd)? {
+        let links = enum_links_from_fd(fd, desc.id)?;
+        let mut pads: Vec<&mut MediaV2Pad> = topology
+            .pads
+            .iter_mut()
+            .filter(|pad| pad.entity_id == desc.id)
+            .collect();
+        if pads.len() > 1 && pads.len() == links.pads.len() && pads.iter().all(|pad| pad.index == 0)
+        {
+            for (pad, legacy_pad) in pads.iter_mut().zip(&links.pads) {
+                pad.index = legacy_pad.index.into();
+            }
+        }
+        legacy.insert(desc.id, LegacyEntity { desc, links });
+    }
+    let mut graph = MediaGraph::new(topology);
+    graph.legacy = legacy;
+    Ok(graph)
+}
+
+/// The graph to address links in by pad index, as `MEDIA_IOC_SETUP_LINK`
+/// does. The pad indexes of kernels older than 4.19 are repaired like
+/// [`graph_with_legacy_from_fd`] does, failing with
+/// [`MediaError::Unsupported`] if that isn't possible.
+pub(crate) fn link_graph_from_fd(fd: BorrowedFd) -> Result<MediaGraph, MediaError> {
+    let topology = get_topology_from_fd(fd)?;
+    if has_pad_indexes(&topology) {
+        return Ok(MediaGraph::new(topology));
+    }
+    let graph = graph_with_legacy_from_fd(fd)?;
+    require_pad_indexes(fd, graph.topology())?;
+    Ok(graph)
+}
+
+/// Fails with [`MediaError::Unsupported`] for [`Feature::PadIndex`] if
+/// `topology` lacks pad indexes.
+pub(crate) fn require_pad_indexes(
+    fd: BorrowedFd,
+    topology: &MediaV2Topology,
+) -> Result<(), MediaError> {
+    if has_pad_indexes(topology) {
+        return Ok(());
+    }
+    Err(MediaError::Unsupported {
+        feature: Feature::PadIndex,
+        needed: Feature::PadIndex.min_version(),
+        found: get_device_info_from_fd(fd)?.media_version(),
+    })
+}
+
+/// Whether the pads of each entity have distinct indexes, unlike those of
+/// `MEDIA_IOC_G_TOPOLOGY` on kernels older than 4.19, which are all 0.
+pub(crate) fn has_pad_indexes(topology: &MediaV2Topology) -> bool {
+    let mut seen = HashSet::new();
+    topology
+        .pads
+        .iter()
+        .all(|pad| seen.insert((pad.entity_id, pad.index)))
+}
+
+/// Reconstructs a `MediaV2Topology` from the legacy enumeration ioctls, for
+/// kernels that predate `MEDIA_IOC_G_TOPOLOGY`.
+///
+/// Entity IDs are the ones reported by the kernel. The legacy API has no IDs
+/// for pads, links and interfaces, so these are allocated after the highest
+/// entity ID and are only meaningful within the returned snapshot. Interfaces
+/// are synthesized for entities that expose a V4L2 device node.
+pub(crate) fn topology_from_legacy(fd: BorrowedFd) -> Result<MediaV2Topology, MediaError> {
+    let descs = enum_entities_from_fd(fd)?;
+
+    let mut next_id = descs.iter().map(|e| e.id.0).max().unwrap_or(0);
+    let mut alloc_id = || {
+        next_id += 1;
+        next_id
+    };
+
+    let mut entities = Vec::with_capacity(descs.len());
+    let mut interfaces = Vec::new();
+    let mut pads = Vec::new();
+    let mut links = Vec::new();
+    let mut entity_links = Vec::with_capacity(descs.len());
+    let mut pad_ids: HashMap<(EntityId, u16), PadId> = HashMap::new();
+
+    for desc in &descs {
+        entities.push(MediaV2Entity {
+            id: desc.id,
+            name: desc.name.as_str().into(),
+            function: desc.entity_type.into(),
+            flags: desc.flags,
+            name_raw: desc.name_raw.clone(),
+            kernel: KernelStruct::default(),
+        });
+
+        if let Some(intf_type) = legacy_interface_type(desc.entity_type) {
+            if let Some(devnode) = &desc.devnode {
+                let intf_id = alloc_id();
+                interfaces.push(MediaV2Interface {
+                    id: InterfaceId(intf_id),
+                    intf_type: intf_type.into(),
+                    flags: 0,
+                    devnode: Some(MediaV2IntfDevnode {
+                        major: devnode.major,
+                        minor: devnode.minor,
+                    }),
+                    kernel: KernelStruct::default(),
+                });
+                links.push(MediaV2Link {
+                    id: LinkId(alloc_id()),
+                    source_id: intf_id,
+                    sink_id: desc.id.into(),
+                    flags: (LinkFlags::INTERFACE_LINK | LinkFlags::ENABLED | LinkFlags::IMMUTABLE)
+                        .bits(),
+                    kernel: KernelStruct::default(),
+                });
+            }
+        }
+
+        let links_enum = enum_links_from_fd(fd, desc.id)?;
+        for pad in &links_enum.pads {
+            let id = PadId(alloc_id());
+            pad_ids.insert((pad.entity, pad.index), id);
+            pads.push(MediaV2Pad {
+                id,
+                entity_id: pad.entity,
+                flags: pad.flags,
+                index: pad.index.into(),
+                kernel: KernelStruct::default(),
+            });
+        }
+        entity_links.push(links_enum.links);
+    }
+
+    // ENUM_LINKS reports each data link once, from its source entity.
+    for link in entity_links.iter().flatten() {
+        let source = pad_ids.get(&(link.source.entity, link.source.index));
+        let sink = pad_ids.get(&(link.sink.entity, link.sink.index));
+        if let (Some(&source_id), Some(&sink_id)) = (source, sink) {
+            links.push(MediaV2Link {
+                id: LinkId(alloc_id()),
+                source_id: source_id.into(),
+                sink_id: sink_id.into(),
+                flags: link.flags,
+                kernel: KernelStruct::default(),
+            });
+        }
+    }
+
+    Ok(MediaV2Topology {
+        topology_version: 0,
+        entities,
+        interfaces,
+        pads,
+        links,
+    })
+}
+
+fn legacy_interface_type(entity_type: u32) -> Option<u32> {
+    match entity_type & media_ffi::MEDIA_ENT_TYPE_MASK {
+        media_ffi::MEDIA_ENT_T_DEVNODE if entity_type == media_ffi::MEDIA_ENT_T_DEVNODE_V4L => {
+            Some(media_ffi::MEDIA_INTF_T_V4L_VIDEO)
+        }
+        media_ffi::MEDIA_ENT_T_V4L2_SUBDEV => Some(media_ffi::MEDIA_INTF_T_V4L_SUBDEV),
+        _ => None,
+    }
+}
+
+fn enum_entity(fd: BorrowedFd, id: u32) -> Result<media_entity_desc, Errno> {
+    let mut entity: media_entity_desc = raw::zeroed();
+    entity.id = id;
+    raw::enum_entity(fd, &mut entity)?;
+    Ok(entity)
+}
+
+pub fn enum_entities(path: impl AsRef<Path>) -> Result<Vec<MediaEntityDesc>, MediaError> {
+    MediaDevice::open(path)?.enum_entities()
+}
+
+pub fn enum_links(
+    path: impl AsRef<Path>,
+    entity_id: EntityId,
+) -> Result<MediaLinksEnum, MediaError> {
+    MediaDevice::open(path)?.enum_links(entity_id)
+}
+
+pub fn graph_with_legacy(path: impl AsRef<Path>) -> Result<MediaGraph, MediaError> {
+    MediaDevice::open(path)?.graph_with_legacy()
+}
@@ -1,34 +1,194 @@
-use std::{ffi::CStr, os::fd::AsRawFd, os::raw::c_char, path::Path};
+use std::{
+    fmt,
+    fs::OpenOptions,
+    hash::{Hash, Hasher},
+    io,
+    os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, IntoRawFd, OwnedFd, RawFd},
+    os::raw::c_char,
+    os::unix::{ffi::OsStrExt, fs::OpenOptionsExt},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
+use legacy::link_graph_from_fd;
 use media_ffi::{
-    media_device_info, media_v2_entity, media_v2_interface, media_v2_link, media_v2_pad,
+    media_device_info, media_pad_desc, media_v2_entity, media_v2_interface, media_v2_link,
+    media_v2_pad,
+};
+use nix::{
+    errno::Errno,
+    fcntl::{fcntl, FcntlArg, FdFlag, OFlag},
 };
-use nix::errno::Errno;
+use raw::TopologySections;
 
+#[cfg(feature = "proptest")]
+mod arbitrary;
+#[cfg(feature = "async")]
+mod async_api;
+mod backend;
+mod builder;
+mod cache;
+#[cfg(feature = "capi")]
+mod capi;
+mod config;
+pub mod consts;
+mod devnode;
+mod diff;
+mod discovery;
+mod display;
+mod dot;
+mod error;
+mod features;
+mod fingerprint;
+mod flags;
+mod fuzz;
+mod graph;
+#[cfg(feature = "test-harness")]
+mod harness;
+mod identity;
+mod ids;
+mod intern;
+#[cfg(feature = "json")]
+mod json;
+mod legacy;
+mod matcher;
+#[allow(dead_code)]
 mod media_ffi;
 
-nix::ioctl_readwrite!(
-    media_ioc_device_info,
-    b'|',
-    0x00,
-    media_ffi::media_device_info
-);
-nix::ioctl_readwrite!(
-    media_ioc_g_topology,
-    b'|',
-    0x04,
-    media_ffi::media_v2_topology
-);
+/// The bindgen structs of the UAPI header, for fields the safe types don't
+/// expose. Not covered by semver: the layout follows the kernel headers the
+/// bindings were generated from.
+#[cfg(feature = "unstable-ffi")]
+pub mod ffi {
+    pub use crate::media_ffi::*;
+}
+mod mediactl;
+mod mediactl_syntax;
+mod monitor;
+mod pipeline;
+mod plan;
+mod query;
+mod raw;
+mod record;
+mod request;
+mod retry;
+mod spec;
+#[cfg(feature = "subdev")]
+mod subdev;
+#[cfg(feature = "subdev")]
+#[allow(dead_code)]
+mod subdev_ffi;
+mod system;
+mod topology_ref;
+mod types;
+#[cfg(feature = "udev")]
+mod udev_devices;
+#[cfg(feature = "v4l-interop")]
+mod v4l_interop;
+mod validate;
+mod verify;
+mod version;
 
-#[derive(Debug)]
+#[cfg(feature = "proptest")]
+pub use arbitrary::TopologyParams;
+#[cfg(feature = "async")]
+pub use async_api::AsyncDeviceMonitor;
+pub use backend::{MediaBackend, MockBackend, MockOp};
+pub use builder::TopologyBuilder;
+pub use cache::CachedTopology;
+pub use config::{ApplyOptions, ApplyReport, LinkConfig, PadFormatConfig, PipelineConfig};
+pub use devnode::{find_devnode_owner, find_devnode_owner_from_fd, DevnodeOwner};
+pub use diff::{LinkChange, TopologyDiff};
+pub use discovery::{
+    enumerate_devices, enumerate_devices_from_sysfs, enumerate_devices_with, DiscoveredDevice,
+    ScanOptions,
+};
+#[cfg(feature = "subdev")]
+pub use error::FormatRejection;
+pub use error::{LinkSetupError, MediaError};
+pub use features::Feature;
+pub use flags::{EntityFlags, LinkFlags, LinkKind, PadFlags};
+pub use fuzz::{parse_links_fuzz, parse_pad_formats_fuzz, parse_topology_fuzz};
+pub use graph::{Cycle, MediaGraph, MediaPath, PathHop, MAX_PATHS};
+#[cfg(feature = "test-harness")]
+pub use harness::VirtualDriver;
+pub use identity::{enumerate_devices_by_identity, DeviceIdentity};
+pub use ids::{EntityId, InterfaceId, LinkId, PadId};
+pub use intern::NameInterner;
+#[cfg(feature = "json")]
+pub use json::TOPOLOGY_JSON_SCHEMA;
+pub use legacy::{
+    enum_entities, enum_entities_from_fd, enum_links, enum_links_from_fd, graph_with_legacy,
+    graph_with_legacy_from_fd, LegacyEntity, MediaEntityDesc, MediaLinkDesc, MediaLinksEnum,
+    MediaPadDesc,
+};
+pub use matcher::{
+    find_device, wait_for_device, DeviceMatch, EntityMatch, Pattern, PipelineMatch, PipelineMatcher,
+};
+pub use mediactl_syntax::{
+    EntityRef, LinkRequest, MbusFormat, PadFormatProperties, PadFormatRequest, PadRef, Rect,
+    SyntaxError,
+};
+pub use monitor::{DeviceEvent, DeviceMonitor};
+pub use pipeline::CapturePipeline;
+pub use plan::DryRun;
+pub use query::{get_entities, get_interfaces, get_links, get_pads, TopologyQuery};
+pub use record::{IoctlRecord, RecordingBackend, ReplayBackend};
+pub use request::Request;
+pub use retry::{BusyRetry, RetryPolicy, Retrying};
+pub use spec::{
+    assert_topology_matches, check_topology, EntitySpec, LinkSpec, SpecMismatch, TopologySpec,
+};
+#[cfg(feature = "subdev")]
+pub use subdev::{
+    FrameSizeRange, PadStream, RouteFlags, SelectionFlags, SelectionTarget, Subdev, SubdevFormat,
+    SubdevRoute, Which,
+};
+pub use system::{SystemDevice, SystemGraph, SystemId};
+pub use topology_ref::{
+    get_topology_into_from_fd, get_topology_ref_from_fd, EntityView, InterfaceView, LinkView,
+    PadView, TopologyBuffers, TopologyRef,
+};
+pub use types::{EntityFunction, InterfaceType, MbusCode, ParseEnumError};
+#[cfg(feature = "udev")]
+pub use udev_devices::{enumerate_devices_udev, UdevMediaDevice, UdevParent};
+pub use validate::{TopologyFinding, ValidationIssue, ValidationReport};
+pub use verify::LinkMismatch;
+pub use version::KernelVersion;
+
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MediaDeviceInfo {
-    pub driver: String,
-    pub model: String,
-    pub serial: String,
-    pub bus_info: String,
-    pub media_version: u32,
-    pub hw_version: u32,
-    pub driver_version: u32,
+    driver: String,
+    model: String,
+    serial: String,
+    bus_info: String,
+    media_version: u32,
+    hw_version: u32,
+    driver_version: u32,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    driver_raw: Option<RawBytes>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    model_raw: Option<RawBytes>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    serial_raw: Option<RawBytes>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    bus_info_raw: Option<RawBytes>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    kernel: KernelStruct<media_device_info>,
 }
 
 impl MediaDeviceInfo {
@@ -41,189 +201,1430 @@ impl MediaDeviceInfo {
             media_version: info.media_version,
             hw_version: info.hw_revision,
             driver_version: info.driver_version,
+            driver_raw: RawBytes::if_lossy(&info.driver),
+            model_raw: RawBytes::if_lossy(&info.model),
+            serial_raw: RawBytes::if_lossy(&info.serial),
+            bus_info_raw: RawBytes::if_lossy(&info.bus_info),
+            kernel: KernelStruct::new(*info),
         }
     }
+
+    pub fn driver(&self) -> &str {
+        &self.driver
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    pub fn serial(&self) -> &str {
+        &self.serial
+    }
+
+    pub fn bus_info(&self) -> &str {
+        &self.bus_info
+    }
+
+    /// Version of the media controller API implemented by the kernel.
+    pub fn media_version(&self) -> KernelVersion {
+        self.media_version.into()
+    }
+
+    pub fn hw_version(&self) -> u32 {
+        self.hw_version
+    }
+
+    pub fn driver_version(&self) -> KernelVersion {
+        self.driver_version.into()
+    }
+
+    /// The `media_device_info` reported by the kernel, `None` if the information wasn't read
+    /// from a device.
+    #[cfg(feature = "unstable-ffi")]
+    pub fn raw(&self) -> Option<&ffi::media_device_info> {
+        self.kernel.get()
+    }
+
+    /// Converts a `media_device_info` as the kernel reports it, the inverse of
+    /// [`MediaDeviceInfo::raw`].
+    #[cfg(feature = "unstable-ffi")]
+    pub fn from_raw(raw: &ffi::media_device_info) -> MediaDeviceInfo {
+        MediaDeviceInfo::from_ffi(raw)
+    }
+
+    /// `driver` exactly as reported by the kernel, without the terminating NUL.
+    pub fn driver_bytes(&self) -> &[u8] {
+        raw_or_str(&self.driver_raw, &self.driver)
+    }
+
+    pub fn model_bytes(&self) -> &[u8] {
+        raw_or_str(&self.model_raw, &self.model)
+    }
+
+    pub fn serial_bytes(&self) -> &[u8] {
+        raw_or_str(&self.serial_raw, &self.serial)
+    }
+
+    pub fn bus_info_bytes(&self) -> &[u8] {
+        raw_or_str(&self.bus_info_raw, &self.bus_info)
+    }
 }
 
-#[derive(Debug)]
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MediaV2Entity {
-    pub id: u32,
-    pub name: String,
-    pub function: u32,
-    pub flags: u32,
+    id: EntityId,
+    name: Arc<str>,
+    function: EntityFunction,
+    flags: u32,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    name_raw: Option<RawBytes>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    kernel: KernelStruct<media_v2_entity>,
 }
 
 impl MediaV2Entity {
     fn from_ffi(entity: &media_v2_entity) -> MediaV2Entity {
+        MediaV2Entity::from_ffi_named(entity, c_str_to_arc(&entity.name))
+    }
+
+    /// [`MediaV2Entity::from_ffi`] with the name already decoded, e.g. by a
+    /// [`NameInterner`].
+    fn from_ffi_named(entity: &media_v2_entity, name: Arc<str>) -> MediaV2Entity {
         MediaV2Entity {
-            name: c_str_to_str(&entity.name),
-            id: entity.id,
+            name,
+            id: entity.id.into(),
             flags: entity.flags,
-            function: entity.function,
+            function: entity.function.into(),
+            name_raw: RawBytes::if_lossy(&entity.name),
+            kernel: KernelStruct::new(*entity),
         }
     }
+
+    /// Overwrites `self` with `entity`, keeping the name if it didn't change.
+    fn assign_ffi(&mut self, entity: &media_v2_entity) {
+        if self.name.as_bytes() != c_str_bytes(&entity.name) {
+            self.name = c_str_to_arc(&entity.name);
+        }
+        self.id = entity.id.into();
+        self.flags = entity.flags;
+        self.function = entity.function.into();
+        self.name_raw = RawBytes::if_lossy(&entity.name);
+        self.kernel = KernelStruct::new(*entity);
+    }
+
+    pub fn id(&self) -> EntityId {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn function(&self) -> EntityFunction {
+        self.function
+    }
+
+    pub fn flags(&self) -> u32 {
+        self.flags
+    }
+
+    /// The `media_v2_entity` reported by the kernel, `None` if the entity wasn't read
+    /// from a device.
+    #[cfg(feature = "unstable-ffi")]
+    pub fn raw(&self) -> Option<&ffi::media_v2_entity> {
+        self.kernel.get()
+    }
+
+    /// Converts a `media_v2_entity` as the kernel reports it, the inverse of
+    /// [`MediaV2Entity::raw`].
+    #[cfg(feature = "unstable-ffi")]
+    pub fn from_raw(raw: &ffi::media_v2_entity) -> MediaV2Entity {
+        MediaV2Entity::from_ffi(raw)
+    }
+
+    /// `name` exactly as reported by the kernel, without the terminating NUL.
+    pub fn name_bytes(&self) -> &[u8] {
+        raw_or_str(&self.name_raw, &self.name)
+    }
+
+    pub fn entity_flags(&self) -> EntityFlags {
+        EntityFlags::from_bits_retain(self.flags)
+    }
+
+    pub fn is_camera_sensor(&self) -> bool {
+        self.function == EntityFunction::CamSensor
+    }
+
+    /// A V4L2 video capture or output node (`/dev/videoN`).
+    pub fn is_video_io(&self) -> bool {
+        self.function == EntityFunction::IoV4l
+    }
+
+    /// A video interface bridge, the function reported by CSI-2 receivers.
+    pub fn is_csi_receiver(&self) -> bool {
+        self.function == EntityFunction::VidIfBridge
+    }
+
+    pub fn is_lens(&self) -> bool {
+        self.function == EntityFunction::Lens
+    }
+
+    pub fn is_flash(&self) -> bool {
+        self.function == EntityFunction::Flash
+    }
+
+    pub fn is_isp(&self) -> bool {
+        self.function == EntityFunction::ProcVideoIsp
+    }
+
+    /// A physical input or output such as an HDMI, S-Video or composite
+    /// connector, flagged `MEDIA_ENT_FL_CONNECTOR` or with a connector
+    /// function.
+    pub fn is_connector(&self) -> bool {
+        self.entity_flags().contains(EntityFlags::CONNECTOR)
+            || matches!(
+                self.function,
+                EntityFunction::ConnRf | EntityFunction::ConnSvideo | EntityFunction::ConnComposite
+            )
+    }
 }
 
-#[derive(Debug)]
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MediaV2IntfDevnode {
-    pub major: u32,
-    pub minor: u32,
+    major: u32,
+    minor: u32,
 }
 
-#[derive(Debug)]
+impl MediaV2IntfDevnode {
+    /// The device node of `intf`, if its type has one.
+    fn from_ffi(intf: &media_v2_interface) -> Option<MediaV2IntfDevnode> {
+        if !InterfaceType::from(intf.intf_type).is_devnode() {
+            return None;
+        }
+        let (major, minor) = raw::intf_devnode(intf);
+        Some(MediaV2IntfDevnode { major, minor })
+    }
+
+    pub fn major(&self) -> u32 {
+        self.major
+    }
+
+    pub fn minor(&self) -> u32 {
+        self.minor
+    }
+}
+
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MediaV2Interface {
-    pub id: u32,
-    pub intf_type: u32,
-    pub flags: u32,
-    // todo devnode
+    id: InterfaceId,
+    intf_type: InterfaceType,
+    flags: u32,
+    devnode: Option<MediaV2IntfDevnode>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    kernel: KernelStruct<media_v2_interface>,
 }
 
 impl MediaV2Interface {
     fn from_ffi(intf: &media_v2_interface) -> MediaV2Interface {
         MediaV2Interface {
-            id: intf.id,
+            id: intf.id.into(),
             flags: intf.flags,
-            intf_type: intf.intf_type,
+            intf_type: intf.intf_type.into(),
+            devnode: MediaV2IntfDevnode::from_ffi(intf),
+            kernel: KernelStruct::new(*intf),
         }
     }
+
+    pub fn id(&self) -> InterfaceId {
+        self.id
+    }
+
+    pub fn intf_type(&self) -> InterfaceType {
+        self.intf_type
+    }
+
+    pub fn flags(&self) -> u32 {
+        self.flags
+    }
+
+    /// Character device of the interface, e.g. the `/dev/videoN` node of a
+    /// `MEDIA_INTF_T_V4L_VIDEO` interface.
+    pub fn devnode(&self) -> Option<&MediaV2IntfDevnode> {
+        self.devnode.as_ref()
+    }
+
+    /// The `media_v2_interface` reported by the kernel, `None` if the interface wasn't read
+    /// from a device.
+    #[cfg(feature = "unstable-ffi")]
+    pub fn raw(&self) -> Option<&ffi::media_v2_interface> {
+        self.kernel.get()
+    }
+
+    /// Converts a `media_v2_interface` as the kernel reports it, the inverse of
+    /// [`MediaV2Interface::raw`].
+    #[cfg(feature = "unstable-ffi")]
+    pub fn from_raw(raw: &ffi::media_v2_interface) -> MediaV2Interface {
+        MediaV2Interface::from_ffi(raw)
+    }
 }
 
-#[derive(Debug)]
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MediaV2Pad {
-    pub id: u32,
-    pub entity_id: u32,
-    pub flags: u32,
-    pub index: u32,
+    id: PadId,
+    entity_id: EntityId,
+    flags: u32,
+    index: u32,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    kernel: KernelStruct<media_v2_pad>,
 }
 
 impl MediaV2Pad {
     fn from_ffi(pad: &media_v2_pad) -> MediaV2Pad {
         MediaV2Pad {
-            id: pad.id,
-            entity_id: pad.entity_id,
+            id: pad.id.into(),
+            entity_id: pad.entity_id.into(),
             flags: pad.flags,
             index: pad.index,
+            kernel: KernelStruct::new(*pad),
         }
     }
+
+    pub fn id(&self) -> PadId {
+        self.id
+    }
+
+    pub fn entity_id(&self) -> EntityId {
+        self.entity_id
+    }
+
+    pub fn flags(&self) -> u32 {
+        self.flags
+    }
+
+    /// Index of the pad within its entity.
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// The `media_v2_pad` reported by the kernel, `None` if the pad wasn't read
+    /// from a device.
+    #[cfg(feature = "unstable-ffi")]
+    pub fn raw(&self) -> Option<&ffi::media_v2_pad> {
+        self.kernel.get()
+    }
+
+    /// Converts a `media_v2_pad` as the kernel reports it, the inverse of
+    /// [`MediaV2Pad::raw`].
+    #[cfg(feature = "unstable-ffi")]
+    pub fn from_raw(raw: &ffi::media_v2_pad) -> MediaV2Pad {
+        MediaV2Pad::from_ffi(raw)
+    }
+
+    pub fn pad_flags(&self) -> PadFlags {
+        PadFlags::from_bits_retain(self.flags)
+    }
+
+    /// The legacy (entity, index) representation of the pad used by
+    /// `MEDIA_IOC_SETUP_LINK`, which only has 16 bits for the index.
+    fn to_ffi_desc(&self) -> Result<media_pad_desc, MediaError> {
+        let index = u16::try_from(self.index).map_err(|_| {
+            MediaError::MalformedTopology(format!(
+                "pad {} has index {}, beyond the 16 bits of MEDIA_IOC_SETUP_LINK",
+                self.id, self.index
+            ))
+        })?;
+        Ok(raw::pad_desc(self.entity_id.into(), index, self.flags))
+    }
 }
 
-#[derive(Debug)]
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MediaV2Link {
-    pub id: u32,
-    pub source_id: u32,
-    pub sink_id: u32,
-    pub flags: u32,
+    id: LinkId,
+    source_id: u32,
+    sink_id: u32,
+    flags: u32,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    kernel: KernelStruct<media_v2_link>,
 }
 
 impl MediaV2Link {
     fn from_ffi(pad: &media_v2_link) -> MediaV2Link {
         MediaV2Link {
-            id: pad.id,
+            id: pad.id.into(),
             source_id: pad.source_id,
             sink_id: pad.sink_id,
             flags: pad.flags,
+            kernel: KernelStruct::new(*pad),
         }
     }
+
+    pub fn id(&self) -> LinkId {
+        self.id
+    }
+
+    /// Source pad of data links, interface of interface links, or source
+    /// entity of ancillary links.
+    pub fn source_id(&self) -> u32 {
+        self.source_id
+    }
+
+    /// Sink pad of data links, or sink entity of interface and ancillary links.
+    pub fn sink_id(&self) -> u32 {
+        self.sink_id
+    }
+
+    pub fn flags(&self) -> u32 {
+        self.flags
+    }
+
+    /// The `media_v2_link` reported by the kernel, `None` if the link wasn't read
+    /// from a device.
+    #[cfg(feature = "unstable-ffi")]
+    pub fn raw(&self) -> Option<&ffi::media_v2_link> {
+        self.kernel.get()
+    }
+
+    /// Converts a `media_v2_link` as the kernel reports it, the inverse of
+    /// [`MediaV2Link::raw`].
+    #[cfg(feature = "unstable-ffi")]
+    pub fn from_raw(raw: &ffi::media_v2_link) -> MediaV2Link {
+        MediaV2Link::from_ffi(raw)
+    }
+
+    pub fn link_flags(&self) -> LinkFlags {
+        LinkFlags::from_bits_retain(self.flags)
+    }
+
+    pub fn kind(&self) -> LinkKind {
+        self.link_flags().kind()
+    }
+
+    /// Source pad of a data link.
+    pub fn source_pad(&self) -> Option<PadId> {
+        (self.kind() == LinkKind::Data).then_some(PadId(self.source_id))
+    }
+
+    /// Sink pad of a data link.
+    pub fn sink_pad(&self) -> Option<PadId> {
+        (self.kind() == LinkKind::Data).then_some(PadId(self.sink_id))
+    }
+
+    /// Interface of an interface link.
+    pub fn source_interface(&self) -> Option<InterfaceId> {
+        (self.kind() == LinkKind::Interface).then_some(InterfaceId(self.source_id))
+    }
+
+    /// Source entity of an ancillary link.
+    pub fn source_entity(&self) -> Option<EntityId> {
+        (self.kind() == LinkKind::Ancillary).then_some(EntityId(self.source_id))
+    }
+
+    /// Entity targeted by an interface or ancillary link.
+    pub fn sink_entity(&self) -> Option<EntityId> {
+        matches!(self.kind(), LinkKind::Interface | LinkKind::Ancillary)
+            .then_some(EntityId(self.sink_id))
+    }
 }
 
-#[derive(Debug)]
+#[non_exhaustive]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MediaV2Topology {
+    topology_version: u64,
+    entities: Vec<MediaV2Entity>,
+    interfaces: Vec<MediaV2Interface>,
+    pads: Vec<MediaV2Pad>,
+    links: Vec<MediaV2Link>,
+}
+
+impl MediaV2Topology {
+    fn from_ffi(
+        topology: &media_ffi::media_v2_topology,
+        entities: &[media_v2_entity],
+        interfaces: &[media_v2_interface],
+        pads: &[media_v2_pad],
+        links: &[media_v2_link],
+    ) -> MediaV2Topology {
+        MediaV2Topology::from_ffi_with_names(
+            topology.topology_version,
+            entities,
+            interfaces,
+            pads,
+            links,
+            |entity| c_str_to_arc(&entity.name),
+        )
+    }
+
+    /// [`MediaV2Topology::from_ffi`] with the entity names decoded by `name`.
+    fn from_ffi_with_names(
+        topology_version: u64,
+        entities: &[media_v2_entity],
+        interfaces: &[media_v2_interface],
+        pads: &[media_v2_pad],
+        links: &[media_v2_link],
+        mut name: impl FnMut(&media_v2_entity) -> Arc<str>,
+    ) -> MediaV2Topology {
+        MediaV2Topology {
+            topology_version,
+            entities: entities
+                .iter()
+                .map(|entity| MediaV2Entity::from_ffi_named(entity, name(entity)))
+                .collect(),
+            interfaces: interfaces.iter().map(MediaV2Interface::from_ffi).collect(),
+            pads: pads.iter().map(MediaV2Pad::from_ffi).collect(),
+            links: links.iter().map(MediaV2Link::from_ffi).collect(),
+        }
+    }
+
+    /// Converts the sections filled in by `MEDIA_IOC_G_TOPOLOGY`, e.g. kernel
+    /// structs recorded from a device or crafted by a test. Only
+    /// `topology_version` is read from `topology`: the slices are taken whole,
+    /// whatever the counts say.
+    ///
+    /// No ID is checked for consistency, so any input converts without
+    /// panicking; [`MediaV2Topology::validate`] reports dangling references.
+    #[cfg(feature = "unstable-ffi")]
+    pub fn from_raw_parts(
+        topology: &ffi::media_v2_topology,
+        entities: &[ffi::media_v2_entity],
+        interfaces: &[ffi::media_v2_interface],
+        pads: &[ffi::media_v2_pad],
+        links: &[ffi::media_v2_link],
+    ) -> MediaV2Topology {
+        MediaV2Topology::from_ffi(topology, entities, interfaces, pads, links)
+    }
+
+    /// Overwrites `self` like [`MediaV2Topology::from_raw_parts`], reusing
+    /// the allocations of the sections and of the entity names that didn't
+    /// change, as [`MediaDevice::topology_into`] does.
+    #[cfg(feature = "unstable-ffi")]
+    pub fn assign_raw_parts(
+        &mut self,
+        topology: &ffi::media_v2_topology,
+        entities: &[ffi::media_v2_entity],
+        interfaces: &[ffi::media_v2_interface],
+        pads: &[ffi::media_v2_pad],
+        links: &[ffi::media_v2_link],
+    ) {
+        self.assign_ffi(topology.topology_version, entities, interfaces, pads, links);
+    }
+
+    /// Overwrites `self` like [`MediaV2Topology::from_ffi`], reusing the
+    /// allocations of the sections and entity names.
+    fn assign_ffi(
+        &mut self,
+        topology_version: u64,
+        entities: &[media_v2_entity],
+        interfaces: &[media_v2_interface],
+        pads: &[media_v2_pad],
+        links: &[media_v2_link],
+    ) {
+        self.topology_version = topology_version;
+        self.entities.truncate(entities.len());
+        for (dst, src) in self.entities.iter_mut().zip(entities) {
+            dst.assign_ffi(src);
+        }
+        let assigned = self.entities.len();
+        self.entities
+            .extend(entities[assigned..].iter().map(MediaV2Entity::from_ffi));
+        self.interfaces.clear();
+        self.interfaces
+            .extend(interfaces.iter().map(MediaV2Interface::from_ffi));
+        self.pads.clear();
+        self.pads.extend(pads.iter().map(MediaV2Pad::from_ffi));
+        self.links.clear();
+        self.links.extend(links.iter().map(MediaV2Link::from_ffi));
+    }
+
+    pub fn topology_version(&self) -> u64 {
+        self.topology_version
+    }
+
+    pub fn entities(&self) -> &[MediaV2Entity] {
+        &self.entities
+    }
+
+    pub fn interfaces(&self) -> &[MediaV2Interface] {
+        &self.interfaces
+    }
+
+    pub fn pads(&self) -> &[MediaV2Pad] {
+        &self.pads
+    }
+
+    pub fn links(&self) -> &[MediaV2Link] {
+        &self.links
+    }
+
+    /// Number of objects in each section, usable as a size hint for
+    /// [`MediaDevice::topology_with_hint`].
+    pub fn counts(&self) -> TopologyCounts {
+        TopologyCounts {
+            entities: self.entities.len(),
+            interfaces: self.interfaces.len(),
+            pads: self.pads.len(),
+            links: self.links.len(),
+        }
+    }
+    pub fn entities_by_function(
+        &self,
+        function: EntityFunction,
+    ) -> impl Iterator<Item = &MediaV2Entity> {
+        self.entities
+            .iter()
+            .filter(move |entity| entity.function == function)
+    }
+
+    /// The entity the driver marked `MEDIA_ENT_FL_DEFAULT` among those with
+    /// `function`, e.g. the primary video capture node. Falls back to the only
+    /// entity with `function` when the driver sets no default.
+    pub fn default_entity_for(&self, function: EntityFunction) -> Option<&MediaV2Entity> {
+        let mut candidates = self.entities_by_function(function);
+        let first = candidates.next()?;
+        if first.entity_flags().contains(EntityFlags::DEFAULT) {
+            return Some(first);
+        }
+        let mut sole = true;
+        for entity in candidates {
+            if entity.entity_flags().contains(EntityFlags::DEFAULT) {
+                return Some(entity);
+            }
+            sole = false;
+        }
+        sole.then_some(first)
+    }
+
+    pub fn camera_sensors(&self) -> impl Iterator<Item = &MediaV2Entity> {
+        self.entities_by_function(EntityFunction::CamSensor)
+    }
+
+    /// Physical connectors, see [`MediaV2Entity::is_connector`].
+    pub fn connectors(&self) -> impl Iterator<Item = &MediaV2Entity> {
+        self.entities.iter().filter(|entity| entity.is_connector())
+    }
+
+    /// Pad-to-pad links.
+    pub fn data_links(&self) -> impl Iterator<Item = &MediaV2Link> {
+        self.links_of_kind(LinkKind::Data)
+    }
+
+    /// Interface-to-entity links.
+    pub fn interface_links(&self) -> impl Iterator<Item = &MediaV2Link> {
+        self.links_of_kind(LinkKind::Interface)
+    }
+
+    /// Entity-to-entity links binding e.g. lens and flash controllers to a
+    /// camera sensor. Reported by kernels 5.19 and newer.
+    pub fn ancillary_links(&self) -> impl Iterator<Item = &MediaV2Link> {
+        self.links_of_kind(LinkKind::Ancillary)
+    }
+
+    /// Entities bound to `entity_id` by ancillary links, such as the lens
+    /// (VCM) and flash drivers of a sensor, or the sensor of a lens.
+    pub fn ancillary_peers(&self, entity_id: EntityId) -> Vec<&MediaV2Entity> {
+        self.ancillary_links()
+            .filter_map(|link| {
+                let (source, sink) = (link.source_entity()?, link.sink_entity()?);
+                if source == entity_id {
+                    Some(sink)
+                } else if sink == entity_id {
+                    Some(source)
+                } else {
+                    None
+                }
+            })
+            .filter_map(|peer_id| self.entities.iter().find(|e| e.id == peer_id))
+            .collect()
+    }
+
+    fn links_of_kind(&self, kind: LinkKind) -> impl Iterator<Item = &MediaV2Link> {
+        self.links.iter().filter(move |link| link.kind() == kind)
+    }
+}
+
+/// Object counts of a topology.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TopologyCounts {
+    pub entities: usize,
+    pub interfaces: usize,
+    pub pads: usize,
+    pub links: usize,
+}
+
+impl TopologyCounts {
+    /// The counts plus `headroom`, `None` if one exceeds
+    /// [`MAX_HINT_OBJECTS`].
+    fn with_headroom(&self, headroom: usize) -> Option<TopologyCounts> {
+        let add = |count: usize| {
+            count
+                .checked_add(headroom)
+                .filter(|&count| count <= MAX_HINT_OBJECTS)
+        };
+        Some(TopologyCounts {
+            entities: add(self.entities)?,
+            interfaces: add(self.interfaces)?,
+            pads: add(self.pads)?,
+            links: add(self.links)?,
+        })
+    }
+}
+
+/// Result of the cheap topology probe, see [`MediaDevice::topology_version`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TopologyVersion {
     pub topology_version: u64,
-    pub entities: Vec<MediaV2Entity>,
-    pub interfaces: Vec<MediaV2Interface>,
-    pub pads: Vec<MediaV2Pad>,
-    pub links: Vec<MediaV2Link>,
+    pub counts: TopologyCounts,
 }
 
-pub fn get_device_info(path: &Path) -> Result<MediaDeviceInfo, Errno> {
-    let video_device = std::fs::File::open(path).unwrap();
-    let video_device = video_device.as_raw_fd();
-    let mut dev_info: media_ffi::media_device_info = unsafe { std::mem::zeroed() };
+/// How [`MediaDevice::open_with_mode`] and [`MediaOpenOptions`] open the
+/// device node.
+///
+/// Queries work on read-only handles, link setup needs a read-write one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum OpenMode {
+    ReadOnly,
+    ReadWrite,
+    /// Read-write if permitted, read-only otherwise.
+    #[default]
+    Auto,
+}
+
+/// Options for opening a device node, like [`std::fs::OpenOptions`].
+///
+/// ```no_run
+/// use mc_api::{MediaOpenOptions, OpenMode};
+///
+/// let device = MediaOpenOptions::new()
+///     .mode(OpenMode::ReadWrite)
+///     .nonblocking(true)
+///     .open("/dev/media0")?;
+/// # Ok::<(), mc_api::MediaError>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MediaOpenOptions {
+    mode: OpenMode,
+    nonblocking: bool,
+    cloexec: bool,
+}
+
+impl MediaOpenOptions {
+    /// [`OpenMode::Auto`], blocking, and closed on `exec`.
+    pub fn new() -> MediaOpenOptions {
+        MediaOpenOptions {
+            mode: OpenMode::Auto,
+            nonblocking: false,
+            cloexec: true,
+        }
+    }
+
+    pub fn mode(mut self, mode: OpenMode) -> MediaOpenOptions {
+        self.mode = mode;
+        self
+    }
+
+    /// Opens with `O_NONBLOCK`, e.g. for descriptors registered with an
+    /// event loop.
+    pub fn nonblocking(mut self, nonblocking: bool) -> MediaOpenOptions {
+        self.nonblocking = nonblocking;
+        self
+    }
+
+    /// Whether the descriptor is closed on `exec`, `true` by default. Pass
+    /// `false` for descriptors inherited by helper processes.
+    pub fn cloexec(mut self, cloexec: bool) -> MediaOpenOptions {
+        self.cloexec = cloexec;
+        self
+    }
+
+    pub fn open(&self, path: impl AsRef<Path>) -> Result<MediaDevice, MediaError> {
+        let path = path.as_ref();
+        let mode = self.mode;
+        let flags = if self.nonblocking {
+            nix::libc::O_NONBLOCK
+        } else {
+            0
+        };
+        let open = |write| {
+            OpenOptions::new()
+                .read(true)
+                .write(write)
+                .custom_flags(flags)
+                .open(path)
+        };
+        let file = match mode {
+            OpenMode::ReadOnly => open(false),
+            OpenMode::ReadWrite => open(true),
+            OpenMode::Auto => match open(true) {
+                Err(err)
+                    if err.kind() == io::ErrorKind::PermissionDenied
+                        || err.raw_os_error() == Some(Errno::EROFS as i32) =>
+                {
+                    open(false)
+                }
+                res => res,
+            },
+        }
+        .and_then(|file| {
+            // std always opens with O_CLOEXEC.
+            if !self.cloexec {
+                fcntl(file.as_raw_fd(), FcntlArg::F_SETFD(FdFlag::empty()))?;
+            }
+            Ok(file)
+        })
+        .map_err(|source| MediaError::Open {
+            path: path.to_path_buf(),
+            source,
+        });
+        #[cfg(feature = "tracing")]
+        match &file {
+            Ok(file) => tracing::debug!(
+                path = %path.display(),
+                fd = file.as_raw_fd(),
+                options = ?self,
+                "opened media device"
+            ),
+            Err(err) => {
+                tracing::debug!(path = %path.display(), options = ?self, %err, "open failed")
+            }
+        }
+        Ok(MediaDevice {
+            fd: file?.into(),
+            origin: Some((path.to_path_buf(), *self)),
+        })
+    }
+}
 
-    let result = unsafe { media_ioc_device_info(video_device, &mut dev_info) };
-    match result {
-        Ok(_) => return Result::Ok(MediaDeviceInfo::from_ffi(&dev_info)),
-        Err(err) => return Result::Err(err),
+impl Default for MediaOpenOptions {
+    fn default() -> MediaOpenOptions {
+        MediaOpenOptions::new()
     }
 }
 
+/// An open media controller device node.
+///
+/// The file descriptor stays open for the lifetime of the value, so repeated
+/// queries don't pay for re-opening the node.
+///
+/// A device can be shared between threads by reference, or duplicated with
+/// [`MediaDevice::try_clone`]. The kernel serializes the media ioctls of a
+/// device, so every query returns a consistent snapshot and every link setup
+/// is applied whole. Operations made of several ioctls, such as
+/// [`MediaDevice::apply_links`], are not atomic though: a link changed by
+/// another thread between reading the topology and setting up the links is
+/// not taken into account.
+///
+/// Once the device is unplugged, every operation fails with
+/// [`MediaError::DeviceGone`]; [`MediaDevice::reopen`] picks the device up
+/// again when it's plugged back in.
 #[derive(Debug)]
-pub enum GetTopologyError {
-    IoctlError(Errno),
-    VersionChange { old_version: u64, new_version: u64 },
-}
-
-pub fn get_topology(path: &Path) -> Result<MediaV2Topology, GetTopologyError> {
-    let video_device = std::fs::File::open(path).unwrap();
-    let video_device = video_device.as_raw_fd();
-    let mut topology: media_ffi::media_v2_topology = unsafe { std::mem::zeroed() };
-
-    let res = unsafe { media_ioc_g_topology(video_device, &mut topology) };
-
-    match res {
-        Err(err) => return Result::Err(GetTopologyError::IoctlError(err)),
-        Ok(_) => (),
-    }
-
-    let version = topology.topology_version;
-
-    let mut entities: Vec<media_v2_entity> =
-        Vec::with_capacity(topology.num_entities.try_into().unwrap());
-    let mut interfaces: Vec<media_v2_interface> =
-        Vec::with_capacity(topology.num_interfaces.try_into().unwrap());
-    let mut pads: Vec<media_v2_pad> = Vec::with_capacity(topology.num_pads.try_into().unwrap());
-    let mut links: Vec<media_v2_link> = Vec::with_capacity(topology.num_links.try_into().unwrap());
-
-    unsafe {
-        topology.ptr_entities = entities.as_mut_ptr() as u64;
-        topology.ptr_interfaces = interfaces.as_mut_ptr() as u64;
-        topology.ptr_pads = pads.as_mut_ptr() as u64;
-        topology.ptr_links = links.as_mut_ptr() as u64;
-        let res = media_ioc_g_topology(video_device, &mut topology);
-        if let Err(errno) = res {
-            return Result::Err(GetTopologyError::IoctlError(errno));
-        }
-        if topology.topology_version != version {
-            return Result::Err(GetTopologyError::VersionChange {
-                old_version: version,
-                new_version: topology.topology_version,
-            });
-        }
-        entities.set_len(topology.num_entities.try_into().unwrap());
-        interfaces.set_len(topology.num_interfaces.try_into().unwrap());
-        pads.set_len(topology.num_pads.try_into().unwrap());
-        links.set_len(topology.num_links.try_into().unwrap());
-    };
+pub struct MediaDevice {
+    fd: OwnedFd,
+    /// Path and mode the device was opened with, `None` for descriptors
+    /// wrapped with [`MediaDevice::from_fd`].
+    origin: Option<(PathBuf, MediaOpenOptions)>,
+}
+
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<MediaDevice>();
+    assert_send_sync::<Request>();
+};
+
+impl MediaDevice {
+    /// Opens the device with [`OpenMode::Auto`].
+    pub fn open(path: impl AsRef<Path>) -> Result<MediaDevice, MediaError> {
+        MediaDevice::open_with_mode(path, OpenMode::Auto)
+    }
+
+    pub fn open_with_mode(
+        path: impl AsRef<Path>,
+        mode: OpenMode,
+    ) -> Result<MediaDevice, MediaError> {
+        MediaOpenOptions::new().mode(mode).open(path)
+    }
+
+    /// Opens `/dev/media{index}` with [`OpenMode::Auto`].
+    pub fn by_index(index: u32) -> Result<MediaDevice, MediaError> {
+        MediaDevice::open(format!("/dev/media{index}"))
+    }
+
+    /// Wraps an already-open media device descriptor, e.g. one received from
+    /// a privileged broker process.
+    pub fn from_fd(fd: OwnedFd) -> MediaDevice {
+        MediaDevice { fd, origin: None }
+    }
+
+    /// Replaces the descriptor with a newly opened one, e.g. after the device
+    /// was unplugged and operations failed with [`MediaError::DeviceGone`].
+    ///
+    /// The node is opened from the path and with the [`MediaOpenOptions`] the
+    /// device was opened with. Devices wrapping a descriptor reopen the path
+    /// it was opened from, as `/proc/self/fd` reports it, with its access
+    /// mode and `O_NONBLOCK` and `FD_CLOEXEC` flags.
+    ///
+    /// Fails with [`MediaError::Open`] while the node doesn't exist, so
+    /// callers can retry later, leaving `self` unchanged. A different device
+    /// may have taken the node over since, which callers can rule out by
+    /// comparing the [`MediaDevice::device_info`].
+    pub fn reopen(&mut self) -> Result<(), MediaError> {
+        let (path, options) = match &self.origin {
+            Some(origin) => origin.clone(),
+            None => (fd_path(self.fd.as_fd())?, fd_options(self.fd.as_fd())),
+        };
+        *self = options.open(&path)?;
+        Ok(())
+    }
+
+    /// Another handle to the same open device, sharing its access mode. The
+    /// descriptor is duplicated with `F_DUPFD_CLOEXEC`.
+    pub fn try_clone(&self) -> Result<MediaDevice, MediaError> {
+        let fd = self
+            .fd
+            .try_clone()
+            .map_err(|err| MediaError::Dup(Errno::from_i32(err.raw_os_error().unwrap_or(0))))?;
+        Ok(MediaDevice {
+            fd,
+            origin: self.origin.clone(),
+        })
+    }
+
+    /// Access mode of the descriptor, [`OpenMode::ReadOnly`] or
+    /// [`OpenMode::ReadWrite`].
+    pub fn mode(&self) -> OpenMode {
+        if is_writable(self.fd.as_fd()) {
+            OpenMode::ReadWrite
+        } else {
+            OpenMode::ReadOnly
+        }
+    }
+
+    pub fn device_info(&self) -> Result<MediaDeviceInfo, MediaError> {
+        get_device_info_from_fd(self.fd.as_fd())
+    }
+
+    pub fn topology(&self) -> Result<MediaV2Topology, MediaError> {
+        get_topology_from_fd(self.fd.as_fd())
+    }
+
+    /// Reads the current topology version and object counts without copying
+    /// or converting any of the graph, so pollers can detect that nothing
+    /// changed since the last snapshot.
+    pub fn topology_version(&self) -> Result<TopologyVersion, MediaError> {
+        get_topology_version_from_fd(self.fd.as_fd())
+    }
+
+    /// Fetches the topology with a single `MEDIA_IOC_G_TOPOLOGY` call when it
+    /// fits into buffers sized `hint` plus `headroom` extra slots per section.
+    /// Only if the kernel reports more objects than fit, the counts are queried
+    /// and the fetch is repeated with larger buffers.
+    ///
+    /// Pollers typically pass the [`MediaV2Topology::counts`] of the previous
+    /// snapshot as the hint, halving the number of syscalls.
+    ///
+    /// Sections that would take more than 65536 objects aren't preallocated:
+    /// the topology is then fetched like [`MediaDevice::topology`] does.
+    pub fn topology_with_hint(
+        &self,
+        hint: TopologyCounts,
+        headroom: usize,
+    ) -> Result<MediaV2Topology, MediaError> {
+        get_topology_with_hint_from_fd(self.fd.as_fd(), hint, headroom)
+    }
+
+    /// Like [`MediaDevice::topology`], but re-reads the topology when it
+    /// changes while being fetched, up to `max_attempts` times in total.
+    pub fn topology_with_retries(
+        &self,
+        max_attempts: usize,
+    ) -> Result<MediaV2Topology, MediaError> {
+        get_topology_with_retries_from_fd(self.fd.as_fd(), max_attempts)
+    }
+
+    /// Changes the flags (usually `MEDIA_LNK_FL_ENABLED`) of the data link
+    /// between two pads, addressed by the pad IDs reported in the topology.
+    pub fn setup_link(
+        &self,
+        source_pad_id: PadId,
+        sink_pad_id: PadId,
+        flags: u32,
+    ) -> Result<(), MediaError> {
+        setup_link_from_fd(self.fd.as_fd(), source_pad_id, sink_pad_id, flags)
+    }
+
+    /// [`MediaDevice::setup_link`] with pads addressed by entity name and pad
+    /// index, e.g. `("imx219 1-0010", 0)`, which unlike IDs are stable across
+    /// boots.
+    pub fn setup_link_by_name(
+        &self,
+        source: (&str, u32),
+        sink: (&str, u32),
+        flags: u32,
+    ) -> Result<(), MediaError> {
+        setup_link_by_name_from_fd(self.fd.as_fd(), source, sink, flags)
+    }
+
+    pub fn reset_links(&self) -> Result<Vec<LinkChange>, MediaError> {
+        reset_links_from_fd(self.fd.as_fd())
+    }
+}
+
+impl MediaDevice {
+    /// The raw descriptor of the device, still owned by `self`.
+    pub fn raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+
+    /// Issues an ioctl the crate doesn't wrap, e.g. a private vendor
+    /// extension, on the device's descriptor. Returns the non-negative value
+    /// returned by the kernel.
+    ///
+    /// # Safety
+    ///
+    /// `arg` must be valid for the access `request` encodes: pointing to a
+    /// value of the size and layout the kernel expects, writable if the
+    /// kernel writes to it, or the plain integer argument for ioctls taking
+    /// one. The kernel must not keep the pointer after the call returns.
+    pub unsafe fn ioctl_raw(
+        &self,
+        request: nix::sys::ioctl::ioctl_num_type,
+        arg: *mut std::ffi::c_void,
+    ) -> Result<i32, MediaError> {
+        let ret = nix::libc::ioctl(self.fd.as_raw_fd(), request, arg);
+        raw::traced("custom ioctl", self.fd.as_fd(), Errno::result(ret))
+            .map_err(|errno| MediaError::ioctl("custom ioctl", errno))
+    }
+}
+
+impl AsFd for MediaDevice {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
+
+impl AsRawFd for MediaDevice {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+impl IntoRawFd for MediaDevice {
+    fn into_raw_fd(self) -> RawFd {
+        self.fd.into_raw_fd()
+    }
+}
+
+impl From<MediaDevice> for OwnedFd {
+    fn from(device: MediaDevice) -> OwnedFd {
+        device.fd
+    }
+}
+
+impl FromRawFd for MediaDevice {
+    unsafe fn from_raw_fd(fd: RawFd) -> MediaDevice {
+        MediaDevice::from_fd(OwnedFd::from_raw_fd(fd))
+    }
+}
+
+impl From<OwnedFd> for MediaDevice {
+    fn from(fd: OwnedFd) -> MediaDevice {
+        MediaDevice::from_fd(fd)
+    }
+}
+
+/// The path `fd` was opened from. Once the node is removed, the kernel
+/// reports it with a ` (deleted)` suffix.
+fn fd_path(fd: BorrowedFd) -> Result<PathBuf, MediaError> {
+    let link = PathBuf::from(format!("/proc/self/fd/{}", fd.as_raw_fd()));
+    let path =
+        std::fs::read_link(&link).map_err(|source| MediaError::Open { path: link, source })?;
+    let bytes = path.as_os_str().as_bytes();
+    match bytes.strip_suffix(b" (deleted)") {
+        Some(bytes) => Ok(PathBuf::from(std::ffi::OsStr::from_bytes(bytes))),
+        None => Ok(path),
+    }
+}
+
+pub fn get_device_info_from_fd(fd: BorrowedFd) -> Result<MediaDeviceInfo, MediaError> {
+    let mut dev_info: media_ffi::media_device_info = raw::zeroed();
+
+    raw::device_info(fd, &mut dev_info)
+        .map_err(|errno| MediaError::ioctl("MEDIA_IOC_DEVICE_INFO", errno))?;
+    Ok(MediaDeviceInfo::from_ffi(&dev_info))
+}
+
+pub fn get_topology_from_fd(fd: BorrowedFd) -> Result<MediaV2Topology, MediaError> {
+    TopologyQuery::all().fetch_from_fd(fd)
+}
 
-    let entities: Vec<MediaV2Entity> = entities
-        .iter()
-        .map(|e| MediaV2Entity::from_ffi(e))
-        .collect();
+pub fn get_topology_with_hint_from_fd(
+    fd: BorrowedFd,
+    hint: TopologyCounts,
+    headroom: usize,
+) -> Result<MediaV2Topology, MediaError> {
+    // Buffers too large to preallocate are left to the two-phase fetch, sized
+    // by the kernel.
+    let Some(mut capacity) = hint.with_headroom(headroom) else {
+        return get_topology_from_fd(fd);
+    };
+    for _ in 0..MAX_HINT_ATTEMPTS {
+        let mut entities: Vec<media_v2_entity> = raw::zeroed_vec(capacity.entities);
+        let mut interfaces: Vec<media_v2_interface> = raw::zeroed_vec(capacity.interfaces);
+        let mut pads: Vec<media_v2_pad> = raw::zeroed_vec(capacity.pads);
+        let mut links: Vec<media_v2_link> = raw::zeroed_vec(capacity.links);
 
-    let interfaces: Vec<MediaV2Interface> = interfaces
-        .iter()
-        .map(|i| MediaV2Interface::from_ffi(i))
-        .collect();
+        let mut topology: media_ffi::media_v2_topology = raw::zeroed();
+        let sections = TopologySections {
+            entities: Some(&mut entities),
+            interfaces: Some(&mut interfaces),
+            pads: Some(&mut pads),
+            links: Some(&mut links),
+        };
+        match raw::g_topology(fd, &mut topology, sections) {
+            Ok(()) => {
+                entities.truncate(topology.num_entities as usize);
+                interfaces.truncate(topology.num_interfaces as usize);
+                pads.truncate(topology.num_pads as usize);
+                links.truncate(topology.num_links as usize);
+                return Ok(MediaV2Topology::from_ffi(
+                    &topology,
+                    &entities,
+                    &interfaces,
+                    &pads,
+                    &links,
+                ));
+            }
+            // One of the sections didn't fit, ask for the current counts.
+            Err(Errno::ENOSPC) => {
+                match get_topology_version_from_fd(fd)?
+                    .counts
+                    .with_headroom(headroom)
+                {
+                    Some(counts) => capacity = counts,
+                    None => return get_topology_from_fd(fd),
+                }
+            }
+            Err(Errno::ENOTTY) => return legacy::topology_from_legacy(fd),
+            Err(errno) => return Err(MediaError::ioctl("MEDIA_IOC_G_TOPOLOGY", errno)),
+        }
+    }
+    Err(MediaError::ioctl("MEDIA_IOC_G_TOPOLOGY", Errno::ENOSPC))
+}
 
-    let pads: Vec<MediaV2Pad> = pads.iter().map(|i| MediaV2Pad::from_ffi(i)).collect();
+/// Attempts of [`get_topology_with_hint_from_fd`] before giving up on a
+/// topology that keeps outgrowing the buffers.
+const MAX_HINT_ATTEMPTS: usize = 4;
 
-    let links: Vec<MediaV2Link> = links.iter().map(|i| MediaV2Link::from_ffi(i)).collect();
+/// Objects per section [`get_topology_with_hint_from_fd`] preallocates at
+/// most, far more than any media graph has.
+const MAX_HINT_OBJECTS: usize = 1 << 16;
 
-    let topology = MediaV2Topology {
+/// Reads the topology version and object counts by issuing
+/// `MEDIA_IOC_G_TOPOLOGY` with all buffer pointers null.
+pub fn get_topology_version_from_fd(fd: BorrowedFd) -> Result<TopologyVersion, MediaError> {
+    let mut topology: media_ffi::media_v2_topology = raw::zeroed();
+    raw::g_topology(fd, &mut topology, TopologySections::default())
+        .map_err(|errno| MediaError::ioctl("MEDIA_IOC_G_TOPOLOGY", errno))?;
+    Ok(TopologyVersion {
         topology_version: topology.topology_version,
-        entities,
-        interfaces,
-        pads,
-        links,
+        counts: TopologyCounts {
+            entities: topology.num_entities as usize,
+            interfaces: topology.num_interfaces as usize,
+            pads: topology.num_pads as usize,
+            links: topology.num_links as usize,
+        },
+    })
+}
+
+/// Fetches the topology, retrying when it changes between the sizing and the
+/// fetching ioctl. `MediaError::VersionChange` is only returned after
+/// `max_attempts` consecutive races.
+pub fn get_topology_with_retries_from_fd(
+    fd: BorrowedFd,
+    max_attempts: usize,
+) -> Result<MediaV2Topology, MediaError> {
+    let mut attempt = 1;
+    loop {
+        match get_topology_from_fd(fd) {
+            Err(MediaError::VersionChange { .. }) if attempt < max_attempts => attempt += 1,
+            res => return res,
+        }
+    }
+}
+
+pub fn setup_link_from_fd(
+    fd: BorrowedFd,
+    source_pad_id: PadId,
+    sink_pad_id: PadId,
+    flags: u32,
+) -> Result<(), MediaError> {
+    let graph = link_graph_from_fd(fd)?;
+    let find_pad = |id: PadId| graph.pad(id).ok_or(MediaError::UnknownPad(id));
+    let source = find_pad(source_pad_id)?;
+    let sink = find_pad(sink_pad_id)?;
+    setup_pad_link(fd, &graph, source, sink, flags)
+}
+
+/// Fails with [`MediaError::UnknownEntity`] or [`MediaError::UnknownPadRef`]
+/// naming the entity or pad that isn't in the topology.
+pub fn setup_link_by_name_from_fd(
+    fd: BorrowedFd,
+    source: (&str, u32),
+    sink: (&str, u32),
+    flags: u32,
+) -> Result<(), MediaError> {
+    let graph = link_graph_from_fd(fd)?;
+    let resolve = |(entity, index): (&str, u32)| {
+        PadRef {
+            entity: EntityRef::Name(entity.to_string()),
+            index,
+        }
+        .resolve(&graph)
     };
+    setup_pad_link(fd, &graph, resolve(source)?, resolve(sink)?, flags)
+}
+
+/// Disables every enabled data link that isn't immutable, like
+/// `media-ctl --reset`. Returns the links that were disabled.
+pub fn reset_links_from_fd(fd: BorrowedFd) -> Result<Vec<LinkChange>, MediaError> {
+    let graph = link_graph_from_fd(fd)?;
+    plan::execute(
+        fd,
+        &graph,
+        &plan::plan_reset(&graph)?,
+        false,
+        &RetryPolicy::default(),
+    )
+}
+
+/// Whether `fd` was opened for writing. Descriptors whose flags can't be read
+/// are assumed writable and left for the kernel to check.
+fn is_writable(fd: BorrowedFd) -> bool {
+    match fcntl(fd.as_raw_fd(), FcntlArg::F_GETFL) {
+        Ok(flags) => OFlag::from_bits_truncate(flags) & OFlag::O_ACCMODE != OFlag::O_RDONLY,
+        Err(_) => true,
+    }
+}
+
+/// The options reopening `fd` like it was opened, as far as its flags tell.
+fn fd_options(fd: BorrowedFd) -> MediaOpenOptions {
+    let status =
+        fcntl(fd.as_raw_fd(), FcntlArg::F_GETFL).map_or(OFlag::empty(), OFlag::from_bits_truncate);
+    let fd_flags = fcntl(fd.as_raw_fd(), FcntlArg::F_GETFD)
+        .map_or(FdFlag::FD_CLOEXEC, FdFlag::from_bits_truncate);
+    MediaOpenOptions::new()
+        .mode(if is_writable(fd) {
+            OpenMode::ReadWrite
+        } else {
+            OpenMode::ReadOnly
+        })
+        .nonblocking(status.contains(OFlag::O_NONBLOCK))
+        .cloexec(fd_flags.contains(FdFlag::FD_CLOEXEC))
+}
+
+/// Fails with [`MediaError::LinkSetup`] naming the pads from `graph`.
+fn setup_pad_link(
+    fd: BorrowedFd,
+    graph: &MediaGraph,
+    source: &MediaV2Pad,
+    sink: &MediaV2Pad,
+    flags: u32,
+) -> Result<(), MediaError> {
+    if !is_writable(fd) {
+        return Err(MediaError::ReadOnlyHandle {
+            ioctl: "MEDIA_IOC_SETUP_LINK",
+        });
+    }
+    let mut link = raw::link_desc(source.to_ffi_desc()?, sink.to_ffi_desc()?, flags);
+
+    raw::setup_link(fd, &mut link).map_err(|errno| match errno {
+        Errno::ENOTTY | Errno::ENODEV => MediaError::ioctl("MEDIA_IOC_SETUP_LINK", errno),
+        errno => MediaError::LinkSetup {
+            source: plan::pad_ref(graph, source),
+            sink: plan::pad_ref(graph, sink),
+            cause: LinkSetupError::from_errno(errno),
+        },
+    })?;
+    #[cfg(feature = "tracing")]
+    tracing::info!(
+        link = %LinkRequest {
+            source: plan::pad_ref(graph, source),
+            sink: plan::pad_ref(graph, sink),
+            flags,
+        },
+        "link set up"
+    );
+    Ok(())
+}
+
+pub fn get_device_info(path: impl AsRef<Path>) -> Result<MediaDeviceInfo, MediaError> {
+    MediaDevice::open(path)?.device_info()
+}
+
+pub fn get_topology(path: impl AsRef<Path>) -> Result<MediaV2Topology, MediaError> {
+    MediaDevice::open(path)?.topology()
+}
+
+pub fn get_topology_version(path: impl AsRef<Path>) -> Result<TopologyVersion, MediaError> {
+    MediaDevice::open(path)?.topology_version()
+}
+
+pub fn get_topology_with_retries(
+    path: impl AsRef<Path>,
+    max_attempts: usize,
+) -> Result<MediaV2Topology, MediaError> {
+    MediaDevice::open(path)?.topology_with_retries(max_attempts)
+}
+
+pub fn setup_link_by_name(
+    path: impl AsRef<Path>,
+    source: (&str, u32),
+    sink: (&str, u32),
+    flags: u32,
+) -> Result<(), MediaError> {
+    MediaDevice::open(path)?.setup_link_by_name(source, sink, flags)
+}
 
-    return Result::Ok(topology);
+pub fn reset_links(path: impl AsRef<Path>) -> Result<Vec<LinkChange>, MediaError> {
+    MediaDevice::open(path)?.reset_links()
 }
 
+pub fn setup_link(
+    path: impl AsRef<Path>,
+    source_pad_id: PadId,
+    sink_pad_id: PadId,
+    flags: u32,
+) -> Result<(), MediaError> {
+    MediaDevice::open(path)?.setup_link(source_pad_id, sink_pad_id, flags)
+}
+
+/// Bytes of a fixed-size kernel string up to the first NUL, or the whole
+/// buffer if the driver didn't terminate it.
+fn c_str_bytes(c_str: &[c_char]) -> &[u8] {
+    let bytes = raw::c_chars_as_bytes(c_str);
+    let len = bytes.iter().position(|&c| c == 0).unwrap_or(bytes.len());
+    &bytes[..len]
+}
+
+/// Decodes a kernel string, replacing invalid UTF-8 sequences with U+FFFD.
 fn c_str_to_str(c_str: &[c_char]) -> String {
-    CStr::from_bytes_until_nul(c_str)
-        .unwrap()
-        .to_str()
-        .unwrap()
-        .to_string()
+    String::from_utf8_lossy(c_str_bytes(c_str)).into_owned()
+}
+
+/// [`c_str_to_str`] into a shareable `Arc<str>`.
+fn c_str_to_arc(c_str: &[c_char]) -> Arc<str> {
+    Arc::from(String::from_utf8_lossy(c_str_bytes(c_str)))
+}
+
+/// Exact bytes of a kernel string, kept next to its `String` decoding when
+/// that decoding had to replace invalid UTF-8.
+#[derive(Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct RawBytes(Vec<u8>);
+
+impl RawBytes {
+    fn if_lossy(c_str: &[c_char]) -> Option<RawBytes> {
+        let bytes = c_str_bytes(c_str);
+        std::str::from_utf8(bytes)
+            .is_err()
+            .then(|| RawBytes(bytes.to_vec()))
+    }
+}
+
+/// The kernel struct an object was decoded from, exposed by the `raw()`
+/// accessors of the `unstable-ffi` feature.
+/// It isn't part of the value: comparisons and hashing ignore it, and
+/// objects built by the crate rather than read from a device have none.
+#[derive(Clone, Copy)]
+struct KernelStruct<T: Copy>(Option<T>);
+
+impl<T: Copy> KernelStruct<T> {
+    fn new(raw: T) -> KernelStruct<T> {
+        KernelStruct(Some(raw))
+    }
+
+    #[cfg(feature = "unstable-ffi")]
+    fn get(&self) -> Option<&T> {
+        self.0.as_ref()
+    }
+}
+
+impl<T: Copy> Default for KernelStruct<T> {
+    fn default() -> KernelStruct<T> {
+        KernelStruct(None)
+    }
+}
+
+impl<T: Copy> fmt::Debug for KernelStruct<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(if self.0.is_some() { "Some(..)" } else { "None" })
+    }
+}
+
+impl<T: Copy> PartialEq for KernelStruct<T> {
+    fn eq(&self, _: &KernelStruct<T>) -> bool {
+        true
+    }
+}
+
+impl<T: Copy> Eq for KernelStruct<T> {}
+
+impl<T: Copy> Hash for KernelStruct<T> {
+    fn hash<H: Hasher>(&self, _: &mut H) {}
+}
+
+/// The kernel bytes of a string, which are the decoded `String` itself
+/// unless the decoding was lossy.
+fn raw_or_str<'a>(raw: &'a Option<RawBytes>, decoded: &'a str) -> &'a [u8] {
+    raw.as_ref().map_or(decoded.as_bytes(), |raw| &raw.0)
+}
+
+impl fmt::Debug for RawBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "b\"{}\"", self.0.escape_ascii())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_open_cloexec(device: &MediaDevice) -> bool {
+        nix::fcntl::fcntl(device.as_raw_fd(), nix::fcntl::FcntlArg::F_GETFD)
+            .is_ok_and(|flags| flags & nix::libc::FD_CLOEXEC != 0)
+    }
+
+    #[test]
+    fn device_shared_between_threads() {
+        let device = Arc::new(MediaDevice::from(raw::closed_pipe().unwrap()));
+        let clones: Vec<MediaDevice> = (0..4)
+            .map(|_| {
+                let device = Arc::clone(&device);
+                std::thread::spawn(move || device.try_clone().unwrap())
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|thread| thread.join().unwrap())
+            .collect();
+        drop(device);
+
+        for clone in &clones {
+            assert!(is_open_cloexec(clone));
+            assert!(is_open_cloexec(&clone.try_clone().unwrap()));
+        }
+    }
 }
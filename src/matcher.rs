@@ -0,0 +1,324 @@
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    discovery::dev_media_paths, DeviceEvent, DeviceMonitor, EntityFunction, EntityId, MediaDevice,
+    MediaDeviceInfo, MediaError, MediaGraph, MediaV2Entity,
+};
+
+/// How often [`wait_for_device`] retries nodes that appeared but couldn't be
+/// opened yet, e.g. while udev is still setting their permissions.
+const RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How a [`DeviceMatch`] field is compared against a device information string.
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    Exact(String),
+    /// Shell-style pattern where `*` matches any run of characters and `?`
+    /// matches exactly one.
+    Glob(String),
+    #[cfg(feature = "regex")]
+    Regex(regex::Regex),
+}
+
+impl Pattern {
+    pub fn matches(&self, value: &str) -> bool {
+        match self {
+            Pattern::Exact(pattern) => pattern == value,
+            Pattern::Glob(pattern) => glob_match(pattern, value),
+            #[cfg(feature = "regex")]
+            Pattern::Regex(regex) => regex.is_match(value),
+        }
+    }
+}
+
+/// Criteria selecting a media device by its [`MediaDeviceInfo`]. Fields left
+/// unset match any device.
+#[derive(Debug, Clone, Default)]
+pub struct DeviceMatch {
+    pub driver: Option<Pattern>,
+    pub model: Option<Pattern>,
+    pub bus_info: Option<Pattern>,
+}
+
+impl DeviceMatch {
+    pub fn new() -> DeviceMatch {
+        DeviceMatch::default()
+    }
+
+    pub fn driver(mut self, pattern: Pattern) -> DeviceMatch {
+        self.driver = Some(pattern);
+        self
+    }
+
+    pub fn model(mut self, pattern: Pattern) -> DeviceMatch {
+        self.model = Some(pattern);
+        self
+    }
+
+    pub fn bus_info(mut self, pattern: Pattern) -> DeviceMatch {
+        self.bus_info = Some(pattern);
+        self
+    }
+
+    pub fn matches(&self, info: &MediaDeviceInfo) -> bool {
+        [
+            (&self.driver, &info.driver),
+            (&self.model, &info.model),
+            (&self.bus_info, &info.bus_info),
+        ]
+        .into_iter()
+        .all(|(pattern, value)| pattern.as_ref().is_none_or(|p| p.matches(value)))
+    }
+
+    /// Opens the matching device, or returns `None` if `path` doesn't match.
+    pub(crate) fn open(&self, path: PathBuf) -> Option<(PathBuf, MediaDevice)> {
+        let device = MediaDevice::open(&path).ok()?;
+        let info = device.device_info().ok()?;
+        self.matches(&info).then_some((path, device))
+    }
+}
+
+/// Criteria selecting an entity of a media device by name and function.
+/// Fields left unset match any entity.
+#[derive(Debug, Clone, Default)]
+pub struct EntityMatch {
+    pub name: Option<Pattern>,
+    pub function: Option<EntityFunction>,
+}
+
+impl EntityMatch {
+    pub fn new() -> EntityMatch {
+        EntityMatch::default()
+    }
+
+    pub fn name(mut self, pattern: Pattern) -> EntityMatch {
+        self.name = Some(pattern);
+        self
+    }
+
+    pub fn function(mut self, function: EntityFunction) -> EntityMatch {
+        self.function = Some(function);
+        self
+    }
+
+    pub fn matches(&self, entity: &MediaV2Entity) -> bool {
+        self.name.as_ref().is_none_or(|p| p.matches(entity.name()))
+            && self.function.is_none_or(|f| f == entity.function())
+    }
+}
+
+/// Locates the hardware of a pipeline the way libcamera pipeline handlers
+/// do: a media device matching a [`DeviceMatch`] whose graph has an entity
+/// for each [`EntityMatch`], e.g. a `rkisp1` device with a camera sensor.
+///
+/// Each entity criterion needs an entity of its own, so two
+/// [`EntityFunction::CamSensor`] criteria only match devices with two
+/// sensors.
+///
+/// ```no_run
+/// use mc_api::{EntityFunction, EntityMatch, Pattern, PipelineMatcher};
+///
+/// let matcher = PipelineMatcher::new()
+///     .driver(Pattern::Exact("rkisp1".into()))
+///     .entity(EntityMatch::new().name(Pattern::Exact("rkisp1_isp".into())))
+///     .entity(EntityMatch::new().function(EntityFunction::CamSensor));
+/// for found in matcher.find_all()? {
+///     println!("{}: sensor {}", found.path.display(), found.entities[1]);
+/// }
+/// # Ok::<(), mc_api::MediaError>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PipelineMatcher {
+    pub device: DeviceMatch,
+    pub entities: Vec<EntityMatch>,
+}
+
+/// A media device found by [`PipelineMatcher::find_all`].
+#[derive(Debug)]
+pub struct PipelineMatch {
+    pub path: PathBuf,
+    pub device: MediaDevice,
+    pub info: MediaDeviceInfo,
+    pub graph: MediaGraph,
+    /// The entity matched by each [`EntityMatch`], in the order they were
+    /// added.
+    pub entities: Vec<EntityId>,
+}
+
+impl PipelineMatcher {
+    pub fn new() -> PipelineMatcher {
+        PipelineMatcher::default()
+    }
+
+    pub fn device(mut self, device: DeviceMatch) -> PipelineMatcher {
+        self.device = device;
+        self
+    }
+
+    /// Shorthand for a [`DeviceMatch::driver`] criterion.
+    pub fn driver(mut self, pattern: Pattern) -> PipelineMatcher {
+        self.device.driver = Some(pattern);
+        self
+    }
+
+    pub fn entity(mut self, entity: EntityMatch) -> PipelineMatcher {
+        self.entities.push(entity);
+        self
+    }
+
+    /// Assigns a distinct entity of `graph` to each entity criterion, `None`
+    /// if some criterion is left without one.
+    pub fn match_entities(&self, graph: &MediaGraph) -> Option<Vec<EntityId>> {
+        let candidates: Vec<Vec<EntityId>> = self
+            .entities
+            .iter()
+            .map(|criterion| {
+                graph
+                    .topology()
+                    .entities
+                    .iter()
+                    .filter(|entity| criterion.matches(entity))
+                    .map(|entity| entity.id())
+                    .collect()
+            })
+            .collect();
+        let mut assigned = Vec::with_capacity(candidates.len());
+        assign(&candidates, &mut assigned).then_some(assigned)
+    }
+
+    /// Opens every `/dev/mediaN` node, in numeric order, matching the
+    /// criteria. Nodes that can't be opened or queried are skipped.
+    pub fn find_all(&self) -> Result<Vec<PipelineMatch>, MediaError> {
+        Ok(dev_media_paths()?
+            .into_iter()
+            .filter_map(|path| self.open(path))
+            .collect())
+    }
+
+    /// Like [`PipelineMatcher::find_all`], stopping at the first match.
+    pub fn find_first(&self) -> Result<Option<PipelineMatch>, MediaError> {
+        Ok(dev_media_paths()?
+            .into_iter()
+            .find_map(|path| self.open(path)))
+    }
+
+    fn open(&self, path: PathBuf) -> Option<PipelineMatch> {
+        let device = MediaDevice::open(&path).ok()?;
+        let info = device.device_info().ok()?;
+        if !self.device.matches(&info) {
+            return None;
+        }
+        let graph = MediaGraph::new(device.topology().ok()?);
+        let entities = self.match_entities(&graph)?;
+        Some(PipelineMatch {
+            path,
+            device,
+            info,
+            graph,
+            entities,
+        })
+    }
+}
+
+/// Extends `assigned` with an entity for each remaining criterion of
+/// `candidates`, not reusing any, backtracking on dead ends.
+fn assign(candidates: &[Vec<EntityId>], assigned: &mut Vec<EntityId>) -> bool {
+    let Some(options) = candidates.get(assigned.len()) else {
+        return true;
+    };
+    for &entity in options {
+        if assigned.contains(&entity) {
+            continue;
+        }
+        assigned.push(entity);
+        if assign(candidates, assigned) {
+            return true;
+        }
+        assigned.pop();
+    }
+    false
+}
+
+/// Opens the first `/dev/mediaN` node, in numeric order, matching `matcher`.
+/// Nodes that can't be opened or queried are skipped.
+pub fn find_device(matcher: &DeviceMatch) -> Result<Option<(PathBuf, MediaDevice)>, MediaError> {
+    Ok(dev_media_paths()?
+        .into_iter()
+        .find_map(|path| matcher.open(path)))
+}
+
+/// Like [`find_device`], but if no device matches yet, waits for one to
+/// appear, for services starting before the driver has probed. Returns `None`
+/// once `timeout` elapses, `None` waits indefinitely.
+pub fn wait_for_device(
+    matcher: &DeviceMatch,
+    timeout: Option<Duration>,
+) -> Result<Option<(PathBuf, MediaDevice)>, MediaError> {
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    // Watch before scanning so a device appearing in between isn't missed.
+    let mut monitor = DeviceMonitor::new()?;
+    if let Some(found) = find_device(matcher)? {
+        return Ok(Some(found));
+    }
+
+    let mut unopened: Vec<PathBuf> = Vec::new();
+    loop {
+        let now = Instant::now();
+        let mut wait = deadline.map(|deadline| deadline.saturating_duration_since(now));
+        if !unopened.is_empty() {
+            wait = Some(wait.map_or(RETRY_INTERVAL, |wait| wait.min(RETRY_INTERVAL)));
+        }
+        match monitor.next_event(wait)? {
+            Some(DeviceEvent::Added(path)) => unopened.push(path),
+            Some(DeviceEvent::Removed(path)) => unopened.retain(|p| *p != path),
+            None if deadline.is_some_and(|deadline| Instant::now() >= deadline) => return Ok(None),
+            None => {}
+        }
+
+        for path in std::mem::take(&mut unopened) {
+            match MediaDevice::open(&path) {
+                Ok(device) => {
+                    if device
+                        .device_info()
+                        .is_ok_and(|info| matcher.matches(&info))
+                    {
+                        return Ok(Some((path, device)));
+                    }
+                }
+                Err(_) => unopened.push(path),
+            }
+        }
+    }
+}
+
+pub(crate) fn glob_match(pattern: &str, value: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let value: Vec<char> = value.chars().collect();
+    let (mut p, mut v) = (0, 0);
+    // Position of the last `*` and the value position it was tried at.
+    let mut backtrack = None;
+    while v < value.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, v));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == value[v] => {
+                p += 1;
+                v += 1;
+            }
+            _ => match backtrack {
+                Some((star, start)) => {
+                    backtrack = Some((star, start + 1));
+                    p = star + 1;
+                    v = start + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
@@ -0,0 +1,189 @@
+use std::{collections::HashMap, fmt::Write};
+
+use crate::{
+    EntityFunction, EntityId, LinkFlags, MediaDevice, MediaDeviceInfo, MediaError, MediaV2Entity,
+    MediaV2IntfDevnode, MediaV2Pad, MediaV2Topology, PadFlags, PadId,
+};
+
+impl MediaV2Topology {
+    /// Formats the topology like `media-ctl -p`, preceded by the device
+    /// information header if `info` is given.
+    ///
+    /// Device node names are looked up in sysfs. Formats are not printed as
+    /// they are V4L2 state rather than part of the media graph.
+    pub fn format_mediactl(&self, info: Option<&MediaDeviceInfo>) -> String {
+        let mut out = String::new();
+        if let Some(info) = info {
+            let _ = write!(
+                out,
+                "Media controller API version {}\n\n\
+                 Media device information\n\
+                 ------------------------\n\
+                 driver          {}\n\
+                 model           {}\n\
+                 serial          {}\n\
+                 bus info        {}\n\
+                 hw revision     {:#x}\n\
+                 driver version  {}\n\n",
+                info.media_version(),
+                info.driver,
+                info.model,
+                info.serial,
+                info.bus_info,
+                info.hw_version,
+                info.driver_version(),
+            );
+        }
+
+        let entities: HashMap<EntityId, &MediaV2Entity> = self
+            .entities
+            .iter()
+            .map(|entity| (entity.id, entity))
+            .collect();
+        let pads: HashMap<PadId, &MediaV2Pad> = self.pads.iter().map(|pad| (pad.id, pad)).collect();
+        let mut devnodes: HashMap<EntityId, &MediaV2IntfDevnode> = HashMap::new();
+        for link in self.interface_links() {
+            let intf = self
+                .interfaces
+                .iter()
+                .find(|intf| Some(intf.id) == link.source_interface());
+            if let (Some(devnode), Some(entity)) =
+                (intf.and_then(|i| i.devnode.as_ref()), link.sink_entity())
+            {
+                devnodes.entry(entity).or_insert(devnode);
+            }
+        }
+
+        out.push_str("Device topology\n");
+        for entity in &self.entities {
+            let mut entity_pads: Vec<_> = self
+                .pads
+                .iter()
+                .filter(|pad| pad.entity_id == entity.id)
+                .collect();
+            entity_pads.sort_by_key(|pad| pad.index);
+            let num_links = self
+                .data_links()
+                .filter(|link| {
+                    link.source_pad()
+                        .and_then(|id| pads.get(&id))
+                        .is_some_and(|pad| pad.entity_id == entity.id)
+                })
+                .count();
+            let (kind, subtype) = mediactl_type(entity.function);
+            let _ = writeln!(
+                out,
+                "- entity {}: {} ({} pad{}, {} link{})",
+                entity.id,
+                entity.name,
+                entity_pads.len(),
+                if entity_pads.len() == 1 { "" } else { "s" },
+                num_links,
+                if num_links == 1 { "" } else { "s" },
+            );
+            let _ = writeln!(
+                out,
+                "            type {} subtype {} flags {:x}",
+                kind, subtype, entity.flags
+            );
+            if let Some(devnode) = devnodes.get(&entity.id) {
+                match devnode.path() {
+                    Some(path) => {
+                        let _ = writeln!(out, "            device node name {}", path.display());
+                    }
+                    None => {
+                        let _ = writeln!(
+                            out,
+                            "            device node {}:{}",
+                            devnode.major, devnode.minor
+                        );
+                    }
+                }
+            }
+
+            for pad in entity_pads {
+                let _ = writeln!(out, "\tpad{}: {}", pad.index, pad_flags(pad.pad_flags()));
+                for link in self.data_links() {
+                    let (Some(source), Some(sink)) = (
+                        link.source_pad().and_then(|id| pads.get(&id)),
+                        link.sink_pad().and_then(|id| pads.get(&id)),
+                    ) else {
+                        continue;
+                    };
+                    let (arrow, remote) = if source.id == pad.id {
+                        ("->", sink)
+                    } else if sink.id == pad.id {
+                        ("<-", source)
+                    } else {
+                        continue;
+                    };
+                    let remote_name = entities
+                        .get(&remote.entity_id)
+                        .map_or("", |entity| &*entity.name);
+                    let _ = writeln!(
+                        out,
+                        "\t\t{} \"{}\":{} [{}]",
+                        arrow,
+                        remote_name,
+                        remote.index,
+                        link_flags(link.link_flags())
+                    );
+                }
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+impl MediaDevice {
+    /// Device information and topology formatted like `media-ctl -p`.
+    pub fn format_mediactl(&self) -> Result<String, MediaError> {
+        let info = self.device_info()?;
+        Ok(self.topology()?.format_mediactl(Some(&info)))
+    }
+}
+
+/// The legacy type and subtype media-ctl derives from the entity function.
+fn mediactl_type(function: EntityFunction) -> (&'static str, &'static str) {
+    match function {
+        EntityFunction::IoV4l => ("Node", "V4L"),
+        EntityFunction::IoDtv => ("Node", "DVB"),
+        EntityFunction::IoVbi | EntityFunction::IoSwradio => ("Node", "Unknown"),
+        EntityFunction::CamSensor => ("V4L2 subdev", "Sensor"),
+        EntityFunction::Flash => ("V4L2 subdev", "Flash"),
+        EntityFunction::Lens => ("V4L2 subdev", "Lens"),
+        EntityFunction::AtvDecoder => ("V4L2 subdev", "Decoder"),
+        EntityFunction::Tuner => ("V4L2 subdev", "Tuner"),
+        EntityFunction::Unknown(_) => ("Unknown", "Unknown"),
+        _ => ("V4L2 subdev", "Unknown"),
+    }
+}
+
+fn pad_flags(flags: PadFlags) -> String {
+    let mut names = Vec::new();
+    if flags.contains(PadFlags::SINK) {
+        names.push("Sink");
+    }
+    if flags.contains(PadFlags::SOURCE) {
+        names.push("Source");
+    }
+    if flags.contains(PadFlags::MUST_CONNECT) {
+        names.push("Must Connect");
+    }
+    names.join(", ")
+}
+
+fn link_flags(flags: LinkFlags) -> String {
+    let mut names = Vec::new();
+    if flags.contains(LinkFlags::ENABLED) {
+        names.push("ENABLED");
+    }
+    if flags.contains(LinkFlags::IMMUTABLE) {
+        names.push("IMMUTABLE");
+    }
+    if flags.contains(LinkFlags::DYNAMIC) {
+        names.push("DYNAMIC");
+    }
+    names.join(",")
+}
@@ -0,0 +1,623 @@
+use std::{collections::HashSet, error::Error, fmt, str::FromStr};
+
+use crate::{EntityId, MbusCode, MediaError, MediaGraph, MediaV2Pad, ParseEnumError};
+
+/// Entity reference in media-ctl syntax: a quoted name or a numeric ID.
+///
+/// Names are quoted with `'` or `"`. A name containing both is written in
+/// `'` quotes with its `'` doubled, e.g. `'it''s "quoted"'`, which media-ctl
+/// itself can't express.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum EntityRef {
+    Name(String),
+    Id(EntityId),
+}
+
+impl fmt::Display for EntityRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EntityRef::Name(name) if !name.contains('\'') => write!(f, "'{name}'"),
+            EntityRef::Name(name) if !name.contains('"') => write!(f, "\"{name}\""),
+            EntityRef::Name(name) => write!(f, "'{}'", name.replace('\'', "''")),
+            EntityRef::Id(id) => write!(f, "{id}"),
+        }
+    }
+}
+
+/// Pad reference in media-ctl syntax, e.g. `'imx219 1-0010':0`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PadRef {
+    pub entity: EntityRef,
+    pub index: u32,
+}
+
+impl PadRef {
+    /// Looks the pad up in `graph`.
+    ///
+    /// Fails with [`MediaError::MalformedTopology`] if the pads of the entity
+    /// share an index, as they do in the `MEDIA_IOC_G_TOPOLOGY` topology of
+    /// kernels older than 4.19: resolve against
+    /// [`MediaDevice::graph_with_legacy`](crate::MediaDevice::graph_with_legacy)
+    /// on those.
+    pub fn resolve<'a>(&self, graph: &'a MediaGraph) -> Result<&'a MediaV2Pad, MediaError> {
+        let entity = match &self.entity {
+            EntityRef::Name(name) => graph.entity_by_name(name),
+            EntityRef::Id(id) => graph.entity(*id),
+        }
+        .ok_or_else(|| MediaError::UnknownEntity(self.entity.to_string()))?;
+        let mut indexes = HashSet::new();
+        if !graph.pads(entity.id).all(|pad| indexes.insert(pad.index)) {
+            return Err(MediaError::MalformedTopology(format!(
+                "pads of entity {} share an index",
+                self.entity
+            )));
+        }
+        graph
+            .pad_by_index(entity.id, self.index)
+            .ok_or_else(|| MediaError::UnknownPadRef(self.to_string()))
+    }
+}
+
+impl fmt::Display for PadRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.entity, self.index)
+    }
+}
+
+/// A link setup in media-ctl `-l` syntax, e.g.
+/// `'imx219 1-0010':0 -> 'csi':0 [1]`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LinkRequest {
+    pub source: PadRef,
+    pub sink: PadRef,
+    /// Link flags, in practice `1` (`ENABLED`) or `0`.
+    pub flags: u32,
+}
+
+impl LinkRequest {
+    /// Parses a comma-separated list of links, as accepted by `media-ctl -l`.
+    pub fn parse_list(s: &str) -> Result<Vec<LinkRequest>, SyntaxError> {
+        let mut parser = Parser::new(s);
+        let mut links = vec![parser.link()?];
+        while parser.eat(",") {
+            links.push(parser.link()?);
+        }
+        parser.end()?;
+        Ok(links)
+    }
+}
+
+impl FromStr for LinkRequest {
+    type Err = SyntaxError;
+
+    fn from_str(s: &str) -> Result<LinkRequest, SyntaxError> {
+        let mut parser = Parser::new(s);
+        let link = parser.link()?;
+        parser.end()?;
+        Ok(link)
+    }
+}
+
+impl fmt::Display for LinkRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} -> {} [{}]", self.source, self.sink, self.flags)
+    }
+}
+
+/// Media bus format and frame size, the `fmt:` property.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MbusFormat {
+    /// Media bus code name without its `MEDIA_BUS_FMT_` prefix, e.g.
+    /// `SRGGB10_1X10`.
+    pub code: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl MbusFormat {
+    /// The media bus code `code` names.
+    pub fn mbus_code(&self) -> Result<MbusCode, ParseEnumError> {
+        self.code.parse()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rect {
+    pub left: i32,
+    pub top: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Properties of a [`PadFormatRequest`], those not given are left unset.
+///
+/// Enumerated values (`field`, `colorspace`, ...) are kept as the names
+/// media-ctl uses.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct PadFormatProperties {
+    pub format: Option<MbusFormat>,
+    pub field: Option<String>,
+    pub colorspace: Option<String>,
+    pub xfer: Option<String>,
+    pub ycbcr: Option<String>,
+    pub quantization: Option<String>,
+    pub crop: Option<Rect>,
+    pub compose: Option<Rect>,
+    /// Frame interval as `(numerator, denominator)`.
+    pub interval: Option<(u32, u32)>,
+}
+
+/// Parses the properties alone, without the surrounding brackets, e.g.
+/// `fmt:SRGGB10_1X10/3280x2464 field:none`.
+impl FromStr for PadFormatProperties {
+    type Err = SyntaxError;
+
+    fn from_str(s: &str) -> Result<PadFormatProperties, SyntaxError> {
+        Parser::new(s).properties(None)
+    }
+}
+
+/// A pad format setup in media-ctl `-V` syntax, e.g.
+/// `'imx219 1-0010':0 [fmt:SRGGB10_1X10/3280x2464 field:none]`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PadFormatRequest {
+    pub pad: PadRef,
+    pub properties: PadFormatProperties,
+}
+
+impl PadFormatRequest {
+    /// Parses a comma-separated list of pad formats, as accepted by
+    /// `media-ctl -V`.
+    pub fn parse_list(s: &str) -> Result<Vec<PadFormatRequest>, SyntaxError> {
+        let mut parser = Parser::new(s);
+        let mut formats = vec![parser.pad_format()?];
+        while parser.eat(",") {
+            formats.push(parser.pad_format()?);
+        }
+        parser.end()?;
+        Ok(formats)
+    }
+}
+
+impl FromStr for PadFormatRequest {
+    type Err = SyntaxError;
+
+    fn from_str(s: &str) -> Result<PadFormatRequest, SyntaxError> {
+        let mut parser = Parser::new(s);
+        let format = parser.pad_format()?;
+        parser.end()?;
+        Ok(format)
+    }
+}
+
+/// Error returned for malformed media-ctl syntax.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SyntaxError {
+    /// Byte offset of the problem in the input.
+    pub position: usize,
+    pub message: String,
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at offset {}", self.message, self.position)
+    }
+}
+
+impl Error for SyntaxError {}
+
+/// Recursive descent parser over the media-ctl grammar.
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str) -> Parser<'a> {
+        Parser { input, pos: 0 }
+    }
+
+    fn error(&self, message: impl Into<String>) -> SyntaxError {
+        SyntaxError {
+            position: self.pos,
+            message: message.into(),
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    /// Consumes `token`, after optional whitespace, if it comes next.
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+        if self.rest().starts_with(token) {
+            self.pos += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), SyntaxError> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            Err(self.error(format!("expected `{token}`")))
+        }
+    }
+
+    fn end(&mut self) -> Result<(), SyntaxError> {
+        self.skip_whitespace();
+        if self.rest().is_empty() {
+            Ok(())
+        } else {
+            Err(self.error("unexpected trailing input"))
+        }
+    }
+
+    /// Consumes the longest prefix whose characters satisfy `pred`.
+    fn take_while(&mut self, pred: impl Fn(char) -> bool) -> &'a str {
+        let rest = self.rest();
+        let len = rest.find(|c| !pred(c)).unwrap_or(rest.len());
+        self.pos += len;
+        &rest[..len]
+    }
+
+    fn number(&mut self) -> Result<u32, SyntaxError> {
+        self.skip_whitespace();
+        self.digits()
+    }
+
+    /// A number starting right at the current position.
+    fn digits(&mut self) -> Result<u32, SyntaxError> {
+        let start = self.pos;
+        let digits = self.take_while(|c| c.is_ascii_digit());
+        digits.parse().map_err(|_| {
+            self.pos = start;
+            self.error("expected a number")
+        })
+    }
+
+    fn entity(&mut self) -> Result<EntityRef, SyntaxError> {
+        self.skip_whitespace();
+        let Some(quote) = self
+            .rest()
+            .chars()
+            .next()
+            .filter(|&c| c == '"' || c == '\'')
+        else {
+            return Ok(EntityRef::Id(EntityId(self.number()?)));
+        };
+        self.pos += 1;
+        let mut name = String::new();
+        loop {
+            let Some(len) = self.rest().find(quote) else {
+                return Err(self.error("unterminated entity name"));
+            };
+            name.push_str(&self.rest()[..len]);
+            self.pos += len + 1;
+            // A doubled quote stands for the quote itself.
+            if !self.rest().starts_with(quote) {
+                break;
+            }
+            name.push(quote);
+            self.pos += 1;
+        }
+        Ok(EntityRef::Name(name))
+    }
+
+    fn pad(&mut self) -> Result<PadRef, SyntaxError> {
+        let entity = self.entity()?;
+        self.expect(":")?;
+        Ok(PadRef {
+            entity,
+            index: self.number()?,
+        })
+    }
+
+    fn signed_number(&mut self) -> Result<i32, SyntaxError> {
+        let negative = self.eat("-");
+        let start = self.pos;
+        // No whitespace between the sign and the digits.
+        let value = i64::from(if negative {
+            self.digits()?
+        } else {
+            self.number()?
+        });
+        i32::try_from(if negative { -value } else { value }).map_err(|_| {
+            self.pos = start;
+            self.error("number out of range")
+        })
+    }
+
+    /// `width 'x' height`
+    fn size(&mut self) -> Result<(u32, u32), SyntaxError> {
+        let width = self.number()?;
+        self.expect("x")?;
+        Ok((width, self.number()?))
+    }
+
+    /// `'(' left ',' top ')/' width 'x' height`
+    fn rect(&mut self) -> Result<Rect, SyntaxError> {
+        self.expect("(")?;
+        let left = self.signed_number()?;
+        self.expect(",")?;
+        let top = self.signed_number()?;
+        self.expect(")")?;
+        self.expect("/")?;
+        let (width, height) = self.size()?;
+        Ok(Rect {
+            left,
+            top,
+            width,
+            height,
+        })
+    }
+
+    /// `numerator '/' denominator`
+    fn interval(&mut self) -> Result<(u32, u32), SyntaxError> {
+        let numerator = self.number()?;
+        self.expect("/")?;
+        Ok((numerator, self.number()?))
+    }
+
+    fn identifier(&mut self) -> Result<String, SyntaxError> {
+        self.skip_whitespace();
+        let ident = self.take_while(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if ident.is_empty() {
+            return Err(self.error("expected a name"));
+        }
+        Ok(ident.to_string())
+    }
+
+    fn pad_format(&mut self) -> Result<PadFormatRequest, SyntaxError> {
+        let pad = self.pad()?;
+        self.expect("[")?;
+        let properties = self.properties(Some("]"))?;
+        Ok(PadFormatRequest { pad, properties })
+    }
+
+    /// Whitespace-separated properties up to `closing`, or up to the end of
+    /// the input.
+    fn properties(&mut self, closing: Option<&str>) -> Result<PadFormatProperties, SyntaxError> {
+        let mut props = PadFormatProperties::default();
+        loop {
+            match closing {
+                Some(closing) if self.eat(closing) => break,
+                None if self.rest().trim().is_empty() => break,
+                _ => {}
+            }
+            if self.eat("fmt:") {
+                let code = self.identifier()?;
+                self.expect("/")?;
+                let (width, height) = self.size()?;
+                props.format = Some(MbusFormat {
+                    code,
+                    width,
+                    height,
+                });
+                // Older media-ctl versions accept the interval right after the size.
+                if self.eat("@") {
+                    props.interval = Some(self.interval()?);
+                }
+            } else if self.eat("field:") {
+                props.field = Some(self.identifier()?);
+            } else if self.eat("colorspace:") {
+                props.colorspace = Some(self.identifier()?);
+            } else if self.eat("xfer:") {
+                props.xfer = Some(self.identifier()?);
+            } else if self.eat("ycbcr:") {
+                props.ycbcr = Some(self.identifier()?);
+            } else if self.eat("quantization:") {
+                props.quantization = Some(self.identifier()?);
+            } else if self.eat("crop:") {
+                props.crop = Some(self.rect()?);
+            } else if self.eat("compose:") {
+                props.compose = Some(self.rect()?);
+            } else if self.eat("@") {
+                props.interval = Some(self.interval()?);
+            } else if self.rest().is_empty() {
+                return Err(self.error("expected `]`"));
+            } else {
+                return Err(self.error("unknown pad format property"));
+            }
+        }
+        Ok(props)
+    }
+
+    fn link(&mut self) -> Result<LinkRequest, SyntaxError> {
+        let source = self.pad()?;
+        self.expect("->")?;
+        let sink = self.pad()?;
+        self.expect("[")?;
+        let flags = self.number()?;
+        self.expect("]")?;
+        Ok(LinkRequest {
+            source,
+            sink,
+            flags,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pad(name: &str, index: u32) -> PadRef {
+        PadRef {
+            entity: EntityRef::Name(name.to_string()),
+            index,
+        }
+    }
+
+    #[test]
+    fn parse_link() {
+        let link: LinkRequest = "'imx219 1-0010':0 -> 'csi':0 [1]".parse().unwrap();
+        assert_eq!(
+            link,
+            LinkRequest {
+                source: pad("imx219 1-0010", 0),
+                sink: pad("csi", 0),
+                flags: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn parse_link_by_id() {
+        let link: LinkRequest = "1:0->\"csi\" :2[0]".parse().unwrap();
+        assert_eq!(link.source.entity, EntityRef::Id(EntityId(1)));
+        assert_eq!(link.sink, pad("csi", 2));
+        assert_eq!(link.flags, 0);
+    }
+
+    #[test]
+    fn parse_list_with_commas_in_names() {
+        let links =
+            LinkRequest::parse_list("'a, b':1 -> 'c':0 [1], \"d,e\":0 -> 'f,':0 [0]").unwrap();
+        assert_eq!(links.len(), 2);
+        assert_eq!(links[0].source, pad("a, b", 1));
+        assert_eq!(links[1].source, pad("d,e", 0));
+        assert_eq!(links[1].sink, pad("f,", 0));
+    }
+
+    #[test]
+    fn error_positions() {
+        let error = |s: &str| s.parse::<LinkRequest>().unwrap_err();
+        assert_eq!(error("'csi':0 -> 'isp':0").position, 18);
+        assert_eq!(error("'csi':0 => 'isp':0 [1]").position, 8);
+        assert_eq!(error("'csi':x -> 'isp':0 [1]").position, 6);
+        assert_eq!(error("'csi:0 -> isp:0 [1]").position, 1);
+        assert_eq!(error("'csi':0 -> 'isp':0 [1] x").position, 23);
+        let error = LinkRequest::parse_list("'a':0 -> 'b':0 [1],").unwrap_err();
+        assert_eq!(error.position, 19);
+        assert_eq!(error.message, "expected a number");
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for name in [
+            "imx219 1-0010",
+            "it's",
+            "say \"hi\"",
+            "it's \"both\"",
+            "''",
+            "a, b",
+        ] {
+            let link = LinkRequest {
+                source: pad(name, 1),
+                sink: PadRef {
+                    entity: EntityRef::Id(EntityId(7)),
+                    index: 0,
+                },
+                flags: 1,
+            };
+            let text = link.to_string();
+            assert_eq!(text.parse::<LinkRequest>().unwrap(), link, "{text}");
+            assert_eq!(
+                LinkRequest::parse_list(&format!("{text}, {text}")).unwrap(),
+                [link.clone(), link]
+            );
+        }
+    }
+
+    #[test]
+    fn parse_pad_format() {
+        let format: PadFormatRequest = "'imx219 1-0010':0 [fmt:SRGGB10_1X10/3280x2464 field:none]"
+            .parse()
+            .unwrap();
+        assert_eq!(format.pad, pad("imx219 1-0010", 0));
+        assert_eq!(
+            format.properties,
+            PadFormatProperties {
+                format: Some(MbusFormat {
+                    code: "SRGGB10_1X10".to_string(),
+                    width: 3280,
+                    height: 2464,
+                }),
+                field: Some("none".to_string()),
+                ..PadFormatProperties::default()
+            }
+        );
+        assert_eq!(
+            format.properties.format.unwrap().mbus_code().unwrap(),
+            "SRGGB10_1X10".parse().unwrap()
+        );
+    }
+
+    #[test]
+    fn parse_crop_compose_and_interval() {
+        let props: PadFormatProperties =
+            "fmt:UYVY8_1X16/640x480 crop:(-8,16)/624x448 compose:(0, 0)/320x240 @1/30"
+                .parse()
+                .unwrap();
+        assert_eq!(
+            props.crop,
+            Some(Rect {
+                left: -8,
+                top: 16,
+                width: 624,
+                height: 448,
+            })
+        );
+        assert_eq!(
+            props.compose,
+            Some(Rect {
+                left: 0,
+                top: 0,
+                width: 320,
+                height: 240,
+            })
+        );
+        assert_eq!(props.interval, Some((1, 30)));
+    }
+
+    #[test]
+    fn parse_interval_after_size() {
+        let props: PadFormatProperties = "fmt:SBGGR8_1X8/1920x1080@1/60".parse().unwrap();
+        assert_eq!(props.format.unwrap().width, 1920);
+        assert_eq!(props.interval, Some((1, 60)));
+    }
+
+    #[test]
+    fn parse_format_list() {
+        let formats = PadFormatRequest::parse_list(
+            "'csi, 0':0 [fmt:SRGGB10_1X10/3280x2464], 'isp':1 [colorspace:srgb xfer:srgb]",
+        )
+        .unwrap();
+        assert_eq!(formats.len(), 2);
+        assert_eq!(formats[0].pad, pad("csi, 0", 0));
+        assert_eq!(formats[1].properties.colorspace.as_deref(), Some("srgb"));
+        assert_eq!(formats[1].properties.xfer.as_deref(), Some("srgb"));
+    }
+
+    #[test]
+    fn format_errors() {
+        let error = |s: &str| s.parse::<PadFormatRequest>().unwrap_err();
+        assert_eq!(error("'csi':0 [fmt:SRGGB10_1X10/3280x2464").position, 35);
+        assert_eq!(error("'csi':0 [size:640x480]").position, 9);
+        assert_eq!(error("'csi':0 [fmt:SRGGB10_1X10/3280]").position, 30);
+    }
+
+    #[test]
+    fn signed_number_needs_adjacent_digits() {
+        let error = "crop:(- 5,0)/1x1"
+            .parse::<PadFormatProperties>()
+            .unwrap_err();
+        assert_eq!(error.position, 7);
+        assert_eq!(error.message, "expected a number");
+        let error = "crop:(-2147483649,0)/1x1"
+            .parse::<PadFormatProperties>()
+            .unwrap_err();
+        assert_eq!(error.message, "number out of range");
+        let props: PadFormatProperties = "crop:(-2147483648,0)/1x1".parse().unwrap();
+        assert_eq!(props.crop.unwrap().left, i32::MIN);
+    }
+}
@@ -0,0 +1,129 @@
+use std::{
+    collections::VecDeque,
+    os::fd::{AsFd, AsRawFd, BorrowedFd, RawFd},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use nix::errno::Errno;
+use nix::poll::{poll, PollFd, PollFlags};
+use nix::sys::inotify::{AddWatchFlags, InitFlags, Inotify};
+
+use crate::{discovery::media_index, request::poll_timeout, MediaError};
+
+/// A media controller node appearing or disappearing, see [`DeviceMonitor`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DeviceEvent {
+    Added(PathBuf),
+    Removed(PathBuf),
+}
+
+/// Watches `/dev` with inotify for `mediaN` nodes being created or removed,
+/// e.g. as USB cameras are plugged in and out.
+///
+/// Nodes present when the monitor is created are not reported, list them
+/// with [`enumerate_devices`](crate::enumerate_devices) after creating the
+/// monitor so none is missed. Iterating blocks until the next event.
+#[derive(Debug)]
+pub struct DeviceMonitor {
+    inotify: Inotify,
+    dir: PathBuf,
+    pending: VecDeque<DeviceEvent>,
+}
+
+impl DeviceMonitor {
+    pub fn new() -> Result<DeviceMonitor, MediaError> {
+        DeviceMonitor::watch(Path::new("/dev"))
+    }
+
+    /// Monitors `dir` instead of `/dev`.
+    pub fn watch(dir: impl AsRef<Path>) -> Result<DeviceMonitor, MediaError> {
+        let dir = dir.as_ref();
+        let inotify = Inotify::init(InitFlags::IN_CLOEXEC | InitFlags::IN_NONBLOCK)
+            .map_err(MediaError::Inotify)?;
+        let flags = AddWatchFlags::IN_CREATE
+            | AddWatchFlags::IN_DELETE
+            | AddWatchFlags::IN_MOVED_TO
+            | AddWatchFlags::IN_MOVED_FROM;
+        inotify.add_watch(dir, flags).map_err(MediaError::Inotify)?;
+        Ok(DeviceMonitor {
+            inotify,
+            dir: dir.to_path_buf(),
+            pending: VecDeque::new(),
+        })
+    }
+
+    /// Waits for the next event, or until `timeout` elapses, in which case
+    /// `None` is returned. `None` waits indefinitely.
+    pub fn next_event(
+        &mut self,
+        timeout: Option<Duration>,
+    ) -> Result<Option<DeviceEvent>, MediaError> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            if let Some(event) = self.pop_event() {
+                return Ok(Some(event));
+            }
+            let mut fds = [PollFd::new(&self.inotify, PollFlags::POLLIN)];
+            match poll(&mut fds, poll_timeout(deadline)) {
+                Ok(0) => return Ok(None),
+                Ok(_) | Err(Errno::EINTR) => {}
+                Err(errno) => return Err(MediaError::Poll(errno)),
+            }
+            self.read_events()?;
+        }
+    }
+
+    pub(crate) fn pop_event(&mut self) -> Option<DeviceEvent> {
+        self.pending.pop_front()
+    }
+
+    /// Queues the pending inotify events, returns `false` if there were none.
+    pub(crate) fn read_events(&mut self) -> Result<bool, MediaError> {
+        let events = match self.inotify.read_events() {
+            Ok(events) => events,
+            Err(Errno::EAGAIN) => return Ok(false),
+            Err(errno) => return Err(MediaError::Inotify(errno)),
+        };
+        for event in events {
+            let Some(path) = event.name.map(|name| self.dir.join(name)) else {
+                continue;
+            };
+            if media_index(&path).is_none() {
+                continue;
+            }
+            if event
+                .mask
+                .intersects(AddWatchFlags::IN_CREATE | AddWatchFlags::IN_MOVED_TO)
+            {
+                self.pending.push_back(DeviceEvent::Added(path));
+            } else {
+                self.pending.push_back(DeviceEvent::Removed(path));
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// The inotify fd, readable when events are pending, for integration into an
+/// event loop.
+impl AsFd for DeviceMonitor {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.inotify.as_fd()
+    }
+}
+
+impl AsRawFd for DeviceMonitor {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inotify.as_fd().as_raw_fd()
+    }
+}
+
+impl Iterator for DeviceMonitor {
+    type Item = Result<DeviceEvent, MediaError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_event(None).transpose()
+    }
+}
@@ -0,0 +1,68 @@
+use crate::{
+    EntityId, InterfaceId, MediaDevice, MediaError, MediaGraph, MediaPath, MediaV2IntfDevnode,
+    PadFlags,
+};
+
+/// Route from a camera sensor to a video capture node.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CapturePipeline {
+    pub sensor: EntityId,
+    /// Data links from the sensor to the video node entity.
+    pub path: MediaPath,
+    /// The `MEDIA_ENT_F_IO_V4L` entity at the end of the path.
+    pub video_node: EntityId,
+    /// V4L video interface controlling the video node.
+    pub interface: InterfaceId,
+    /// Character device of the interface, i.e. the `/dev/videoN` node.
+    pub devnode: Option<MediaV2IntfDevnode>,
+}
+
+impl MediaGraph {
+    /// For each camera sensor, the shortest route to every video capture node
+    /// it can reach through data links, enabled or not. A sensor feeding
+    /// several nodes (e.g. the main and self paths of an ISP) appears once per
+    /// node.
+    pub fn capture_pipelines(&self) -> Vec<CapturePipeline> {
+        let capture_nodes: Vec<_> = self
+            .topology()
+            .entities
+            .iter()
+            .filter(|entity| entity.is_video_io() && self.is_capture(entity.id))
+            .filter_map(|entity| {
+                let intf = self
+                    .interfaces(entity.id)
+                    .find(|intf| intf.intf_type.is_v4l_video())?;
+                Some((entity.id, intf))
+            })
+            .collect();
+
+        let mut pipelines = Vec::new();
+        for sensor in self.topology().camera_sensors() {
+            for (video_node, intf) in &capture_nodes {
+                if let Some(path) = self.find_path(sensor.id, *video_node) {
+                    pipelines.push(CapturePipeline {
+                        sensor: sensor.id,
+                        path,
+                        video_node: *video_node,
+                        interface: intf.id,
+                        devnode: intf.devnode,
+                    });
+                }
+            }
+        }
+        pipelines
+    }
+
+    /// Capture nodes only receive data, output nodes only send it.
+    fn is_capture(&self, entity: EntityId) -> bool {
+        let mut pads = self.pads(entity).map(|pad| pad.pad_flags()).peekable();
+        pads.peek().is_some() && pads.all(|flags| flags.contains(PadFlags::SINK))
+    }
+}
+
+impl MediaDevice {
+    pub fn capture_pipelines(&self) -> Result<Vec<CapturePipeline>, MediaError> {
+        Ok(MediaGraph::new(self.topology()?).capture_pipelines())
+    }
+}
@@ -0,0 +1,353 @@
+use std::{
+    collections::HashSet,
+    os::fd::{AsFd, BorrowedFd},
+};
+
+use crate::{
+    setup_pad_link, EntityId, EntityRef, LinkChange, LinkFlags, LinkId, LinkRequest, MediaDevice,
+    MediaError, MediaGraph, MediaV2Link, MediaV2Pad, PadId, PadRef, PipelineConfig, RetryPolicy,
+};
+
+/// One `MEDIA_IOC_SETUP_LINK` call planned by a link operation.
+pub(crate) struct LinkStep<'a> {
+    source: &'a MediaV2Pad,
+    sink: &'a MediaV2Pad,
+    flags: u32,
+    /// Flags of the link before and after the call, `None` if the link isn't
+    /// in the topology, in which case the kernel will reject the call.
+    change: Option<LinkChange>,
+}
+
+impl<'a> LinkStep<'a> {
+    fn toggle(
+        graph: &'a MediaGraph,
+        link: &MediaV2Link,
+        enabled: bool,
+    ) -> Result<LinkStep<'a>, MediaError> {
+        let find_pad = |id| graph.pad(id).ok_or(MediaError::UnknownPad(id));
+        let mut flags = link.link_flags();
+        flags.set(LinkFlags::ENABLED, enabled);
+        Ok(LinkStep {
+            source: find_pad(PadId(link.source_id))?,
+            sink: find_pad(PadId(link.sink_id))?,
+            flags: flags.bits(),
+            change: Some(LinkChange {
+                id: link.id,
+                old_flags: link.flags,
+                new_flags: flags.bits(),
+            }),
+        })
+    }
+
+    /// The step in media-ctl syntax, with pads addressed by entity name.
+    fn to_request(&self, graph: &MediaGraph) -> LinkRequest {
+        LinkRequest {
+            source: pad_ref(graph, self.source),
+            sink: pad_ref(graph, self.sink),
+            flags: self.flags,
+        }
+    }
+}
+
+/// Reference to `pad` by entity name, or by entity ID if the entity is
+/// missing from the topology.
+pub(crate) fn pad_ref(graph: &MediaGraph, pad: &MediaV2Pad) -> PadRef {
+    PadRef {
+        entity: match graph.entity(pad.entity_id) {
+            Some(entity) => EntityRef::Name(entity.name.to_string()),
+            None => EntityRef::Id(pad.entity_id),
+        },
+        index: pad.index,
+    }
+}
+
+/// The step of a single link setup between pads addressed by ID, performed
+/// even if the link is already in the requested state.
+pub(crate) fn plan_setup(
+    graph: &MediaGraph,
+    source_pad_id: PadId,
+    sink_pad_id: PadId,
+    flags: u32,
+) -> Result<Vec<LinkStep<'_>>, MediaError> {
+    let find_pad = |id| graph.pad(id).ok_or(MediaError::UnknownPad(id));
+    let source = find_pad(source_pad_id)?;
+    let sink = find_pad(sink_pad_id)?;
+    let change = graph
+        .links(source.id)
+        .find(|l| l.sink_pad() == Some(sink.id))
+        .map(|l| LinkChange {
+            id: l.id,
+            old_flags: l.flags,
+            new_flags: (l.flags & !LinkFlags::ENABLED.bits()) | (flags & LinkFlags::ENABLED.bits()),
+        });
+    Ok(vec![LinkStep {
+        source,
+        sink,
+        flags,
+        change,
+    }])
+}
+
+/// Steps setting up `links`, leaving out those already in the requested
+/// state. Fails without a plan if a pad can't be resolved or the changes
+/// don't pass [`MediaGraph::validate_links`].
+pub(crate) fn plan_links<'a>(
+    graph: &'a MediaGraph,
+    links: &[LinkRequest],
+) -> Result<Vec<LinkStep<'a>>, MediaError> {
+    let report = graph.validate_links(links)?;
+    if report.has_errors() {
+        return Err(MediaError::Validation(report));
+    }
+    let mut steps = Vec::new();
+    for link in links {
+        let source = link.source.resolve(graph)?;
+        let sink = link.sink.resolve(graph)?;
+        let current = graph
+            .links(source.id)
+            .find(|l| l.sink_pad() == Some(sink.id));
+        // SETUP_LINK fails with EINVAL unless the flags other than ENABLED
+        // are the current ones, so only ENABLED is taken from the request.
+        let change = current.map(|l| LinkChange {
+            id: l.id,
+            old_flags: l.flags,
+            new_flags: (l.flags & !LinkFlags::ENABLED.bits())
+                | (link.flags & LinkFlags::ENABLED.bits()),
+        });
+        if change.as_ref().is_some_and(|c| c.old_flags == c.new_flags) {
+            continue;
+        }
+        steps.push(LinkStep {
+            source,
+            sink,
+            flags: change.as_ref().map_or(link.flags, |c| c.new_flags),
+            change,
+        });
+    }
+    Ok(steps)
+}
+
+/// Steps enabling the shortest route from `from` to `to`, see
+/// [`MediaDevice::enable_path`].
+pub(crate) fn plan_path(
+    graph: &MediaGraph,
+    from: EntityId,
+    to: EntityId,
+    disable_conflicting: bool,
+) -> Result<Vec<LinkStep<'_>>, MediaError> {
+    let path = graph
+        .find_path(from, to)
+        .ok_or(MediaError::NoPath { from, to })?;
+    let path_links: HashSet<LinkId> = path.links().into_iter().collect();
+
+    let mut steps = Vec::new();
+    if disable_conflicting {
+        let mut seen = HashSet::new();
+        for hop in &path.hops {
+            for link in graph.links(hop.sink_pad) {
+                let flags = link.link_flags();
+                if path_links.contains(&link.id)
+                    || link.sink_pad() != Some(hop.sink_pad)
+                    || !seen.insert(link.id)
+                    || !flags.contains(LinkFlags::ENABLED)
+                    || flags.contains(LinkFlags::IMMUTABLE)
+                {
+                    continue;
+                }
+                steps.push(LinkStep::toggle(graph, link, false)?);
+            }
+        }
+    }
+    for hop in &path.hops {
+        let Some(link) = graph.links(hop.source_pad).find(|link| link.id == hop.link) else {
+            continue;
+        };
+        if !link.link_flags().contains(LinkFlags::ENABLED) {
+            steps.push(LinkStep::toggle(graph, link, true)?);
+        }
+    }
+    Ok(steps)
+}
+
+/// Steps disabling every enabled data link that isn't immutable.
+pub(crate) fn plan_reset(graph: &MediaGraph) -> Result<Vec<LinkStep<'_>>, MediaError> {
+    graph
+        .topology()
+        .data_links()
+        .filter(|link| {
+            let flags = link.link_flags();
+            flags.contains(LinkFlags::ENABLED) && !flags.contains(LinkFlags::IMMUTABLE)
+        })
+        .map(|link| LinkStep::toggle(graph, link, false))
+        .collect()
+}
+
+/// Performs `steps` in order, retrying as `retry` says. With `rollback`, a
+/// failure restores the links changed so far, latest first, before the
+/// error is returned.
+pub(crate) fn execute(
+    fd: BorrowedFd,
+    graph: &MediaGraph,
+    steps: &[LinkStep],
+    rollback: bool,
+    retry: &RetryPolicy,
+) -> Result<Vec<LinkChange>, MediaError> {
+    #[cfg(feature = "tracing")]
+    let _span = tracing::debug_span!("setup_links", steps = steps.len(), rollback).entered();
+    let mut applied: Vec<&LinkStep> = Vec::new();
+    for step in steps {
+        if let Err(error) = perform(fd, graph, step, step.flags, retry) {
+            if rollback {
+                if let Err(rollback) = undo(fd, graph, &applied, retry) {
+                    return Err(MediaError::RollbackFailed {
+                        error: Box::new(error),
+                        rollback: Box::new(rollback),
+                    });
+                }
+            }
+            return Err(error);
+        }
+        applied.push(step);
+    }
+    Ok(steps
+        .iter()
+        .filter_map(|step| step.change.clone())
+        .collect())
+}
+
+fn undo(
+    fd: BorrowedFd,
+    graph: &MediaGraph,
+    applied: &[&LinkStep],
+    retry: &RetryPolicy,
+) -> Result<(), MediaError> {
+    for step in applied.iter().rev() {
+        if let Some(change) = &step.change {
+            perform(fd, graph, step, change.old_flags, retry)?;
+        }
+    }
+    Ok(())
+}
+
+fn perform(
+    fd: BorrowedFd,
+    graph: &MediaGraph,
+    step: &LinkStep,
+    flags: u32,
+    retry: &RetryPolicy,
+) -> Result<(), MediaError> {
+    retry.run(
+        || LinkRequest {
+            flags,
+            ..step.to_request(graph)
+        },
+        || setup_pad_link(fd, graph, step.source, step.sink, flags),
+    )
+}
+
+/// Link operations that report the link setups they would perform, in
+/// media-ctl syntax, instead of performing them. See
+/// [`MediaDevice::dry_run`].
+#[derive(Debug, Clone, Copy)]
+pub struct DryRun<'a> {
+    device: &'a MediaDevice,
+}
+
+impl DryRun<'_> {
+    pub fn apply(&self, config: &PipelineConfig) -> Result<Vec<LinkRequest>, MediaError> {
+        self.apply_links(&config.link_requests())
+    }
+
+    pub fn apply_links(&self, links: &[LinkRequest]) -> Result<Vec<LinkRequest>, MediaError> {
+        let graph = self.device.link_graph()?;
+        Ok(requests(&graph, &plan_links(&graph, links)?))
+    }
+
+    pub fn enable_path(
+        &self,
+        from: EntityId,
+        to: EntityId,
+        disable_conflicting: bool,
+    ) -> Result<Vec<LinkRequest>, MediaError> {
+        let graph = self.device.link_graph()?;
+        Ok(requests(
+            &graph,
+            &plan_path(&graph, from, to, disable_conflicting)?,
+        ))
+    }
+
+    pub fn reset_links(&self) -> Result<Vec<LinkRequest>, MediaError> {
+        let graph = self.device.link_graph()?;
+        Ok(requests(&graph, &plan_reset(&graph)?))
+    }
+}
+
+fn requests(graph: &MediaGraph, steps: &[LinkStep]) -> Vec<LinkRequest> {
+    steps.iter().map(|step| step.to_request(graph)).collect()
+}
+
+impl MediaDevice {
+    /// Runs link operations in dry-run mode, so the plan can be reviewed
+    /// before being applied. The topology is read but not modified.
+    pub fn dry_run(&self) -> DryRun<'_> {
+        DryRun { device: self }
+    }
+
+    /// Resolves `links` against the current topology and applies them in
+    /// order with `MEDIA_IOC_SETUP_LINK`. Nothing is applied if a pad can't
+    /// be resolved or [`MediaGraph::validate_links`] reports an error.
+    ///
+    /// Links already in the requested state are skipped, so applying the
+    /// same requests again is a no-op and doesn't fail with `EBUSY` while
+    /// streaming. Returns the links that were changed.
+    pub fn apply_links(&self, links: &[LinkRequest]) -> Result<Vec<LinkChange>, MediaError> {
+        let graph = self.link_graph()?;
+        execute(
+            self.fd.as_fd(),
+            &graph,
+            &plan_links(&graph, links)?,
+            false,
+            &RetryPolicy::default(),
+        )
+    }
+
+    /// Like [`MediaDevice::apply_links`], but if a link setup fails, the
+    /// links changed so far are restored to their original flags before the
+    /// error is returned, so the pipeline isn't left half configured.
+    pub fn apply_links_transaction(
+        &self,
+        links: &[LinkRequest],
+    ) -> Result<Vec<LinkChange>, MediaError> {
+        let graph = self.link_graph()?;
+        execute(
+            self.fd.as_fd(),
+            &graph,
+            &plan_links(&graph, links)?,
+            true,
+            &RetryPolicy::default(),
+        )
+    }
+
+    /// Enables every link along the shortest route from `from` to `to`.
+    ///
+    /// With `disable_conflicting`, other enabled links into the sink pads of
+    /// the route are disabled first, e.g. a second sensor feeding the
+    /// same receiver. Immutable links are left alone. Returns the links that
+    /// were changed, in order.
+    pub fn enable_path(
+        &self,
+        from: EntityId,
+        to: EntityId,
+        disable_conflicting: bool,
+    ) -> Result<Vec<LinkChange>, MediaError> {
+        let graph = self.link_graph()?;
+        let steps = plan_path(&graph, from, to, disable_conflicting)?;
+        execute(
+            self.fd.as_fd(),
+            &graph,
+            &steps,
+            false,
+            &RetryPolicy::default(),
+        )
+    }
+}
@@ -0,0 +1,274 @@
+use std::{
+    os::fd::{AsFd, BorrowedFd},
+    path::Path,
+};
+
+use nix::errno::Errno;
+
+use crate::media_ffi::{self, media_v2_entity, media_v2_interface, media_v2_link, media_v2_pad};
+use crate::{
+    legacy,
+    raw::{self, TopologySections},
+    MediaDevice, MediaError, MediaV2Entity, MediaV2Interface, MediaV2Link, MediaV2Pad,
+    MediaV2Topology,
+};
+
+/// Selects the sections of the topology to fetch.
+///
+/// `MEDIA_IOC_G_TOPOLOGY` skips sections whose buffer pointer is null, so
+/// sections that aren't requested are neither copied by the kernel nor
+/// converted, and come back empty in the resulting [`MediaV2Topology`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct TopologyQuery {
+    entities: bool,
+    interfaces: bool,
+    pads: bool,
+    links: bool,
+}
+
+impl TopologyQuery {
+    /// A query with no sections selected.
+    pub fn new() -> TopologyQuery {
+        TopologyQuery::default()
+    }
+
+    /// A query selecting every section.
+    pub fn all() -> TopologyQuery {
+        TopologyQuery::new().entities().interfaces().pads().links()
+    }
+
+    pub fn entities(mut self) -> TopologyQuery {
+        self.entities = true;
+        self
+    }
+
+    pub fn interfaces(mut self) -> TopologyQuery {
+        self.interfaces = true;
+        self
+    }
+
+    pub fn pads(mut self) -> TopologyQuery {
+        self.pads = true;
+        self
+    }
+
+    pub fn links(mut self) -> TopologyQuery {
+        self.links = true;
+        self
+    }
+
+    pub fn fetch(&self, device: &MediaDevice) -> Result<MediaV2Topology, MediaError> {
+        self.fetch_from_fd(device.fd.as_fd())
+    }
+
+    pub fn fetch_from_fd(&self, fd: BorrowedFd) -> Result<MediaV2Topology, MediaError> {
+        let res = self.fetch_with(|topology, sections| raw::g_topology(fd, topology, sections));
+        match res {
+            // Kernels older than 4.5 don't implement G_TOPOLOGY.
+            Err(MediaError::UnsupportedKernel { .. }) => {
+                legacy::topology_from_legacy(fd).map(|topology| self.retain(topology))
+            }
+            res => res,
+        }
+    }
+
+    /// The two-phase fetch, issuing `MEDIA_IOC_G_TOPOLOGY` through
+    /// `g_topology`: a sizing call without buffers, then the fetching call
+    /// with buffers of the sizes it reported.
+    ///
+    /// Objects added in between don't fit: the kernel fails the fetch with
+    /// `ENOSPC`, and the sizing is redone with the new counts. Whatever
+    /// counts the kernel reports, sections are never read past the buffers.
+    fn fetch_with(
+        &self,
+        mut g_topology: impl FnMut(
+            &mut media_ffi::media_v2_topology,
+            TopologySections,
+        ) -> nix::Result<()>,
+    ) -> Result<MediaV2Topology, MediaError> {
+        let ioctl_error = |errno| MediaError::ioctl("MEDIA_IOC_G_TOPOLOGY", errno);
+        for _ in 0..MAX_ATTEMPTS {
+            let mut topology: media_ffi::media_v2_topology = raw::zeroed();
+            g_topology(&mut topology, TopologySections::default()).map_err(ioctl_error)?;
+            let version = topology.topology_version;
+
+            let mut entities: Vec<media_v2_entity> =
+                section_buffer(self.entities, topology.num_entities);
+            let mut interfaces: Vec<media_v2_interface> =
+                section_buffer(self.interfaces, topology.num_interfaces);
+            let mut pads: Vec<media_v2_pad> = section_buffer(self.pads, topology.num_pads);
+            let mut links: Vec<media_v2_link> = section_buffer(self.links, topology.num_links);
+            // The kernel skips the sections it isn't given a buffer for.
+            let sections = TopologySections {
+                entities: self.entities.then_some(&mut entities[..]),
+                interfaces: self.interfaces.then_some(&mut interfaces[..]),
+                pads: self.pads.then_some(&mut pads[..]),
+                links: self.links.then_some(&mut links[..]),
+            };
+
+            match g_topology(&mut topology, sections) {
+                Ok(()) => {}
+                // Objects were added since the sizing call.
+                Err(Errno::ENOSPC) => continue,
+                Err(errno) => return Err(ioctl_error(errno)),
+            }
+            if topology.topology_version != version {
+                return Err(MediaError::VersionChange {
+                    old_version: version,
+                    new_version: topology.topology_version,
+                });
+            }
+            entities.truncate(topology.num_entities as usize);
+            interfaces.truncate(topology.num_interfaces as usize);
+            pads.truncate(topology.num_pads as usize);
+            links.truncate(topology.num_links as usize);
+            return Ok(MediaV2Topology::from_ffi(
+                &topology,
+                &entities,
+                &interfaces,
+                &pads,
+                &links,
+            ));
+        }
+        Err(ioctl_error(Errno::ENOSPC))
+    }
+
+    fn retain(&self, mut topology: MediaV2Topology) -> MediaV2Topology {
+        if !self.entities {
+            topology.entities.clear();
+        }
+        if !self.interfaces {
+            topology.interfaces.clear();
+        }
+        if !self.pads {
+            topology.pads.clear();
+        }
+        if !self.links {
+            topology.links.clear();
+        }
+        topology
+    }
+}
+
+/// Attempts of [`TopologyQuery::fetch`] before giving up on a topology that
+/// keeps growing between the sizing and the fetching call.
+const MAX_ATTEMPTS: usize = 4;
+
+fn section_buffer<T: raw::Pod>(selected: bool, count: u32) -> Vec<T> {
+    if selected {
+        raw::zeroed_vec(count as usize)
+    } else {
+        Vec::new()
+    }
+}
+
+impl MediaDevice {
+    pub fn entities(&self) -> Result<Vec<MediaV2Entity>, MediaError> {
+        Ok(TopologyQuery::new().entities().fetch(self)?.entities)
+    }
+
+    pub fn interfaces(&self) -> Result<Vec<MediaV2Interface>, MediaError> {
+        Ok(TopologyQuery::new().interfaces().fetch(self)?.interfaces)
+    }
+
+    pub fn pads(&self) -> Result<Vec<MediaV2Pad>, MediaError> {
+        Ok(TopologyQuery::new().pads().fetch(self)?.pads)
+    }
+
+    pub fn links(&self) -> Result<Vec<MediaV2Link>, MediaError> {
+        Ok(TopologyQuery::new().links().fetch(self)?.links)
+    }
+}
+
+pub fn get_entities(path: impl AsRef<Path>) -> Result<Vec<MediaV2Entity>, MediaError> {
+    MediaDevice::open(path)?.entities()
+}
+
+pub fn get_interfaces(path: impl AsRef<Path>) -> Result<Vec<MediaV2Interface>, MediaError> {
+    MediaDevice::open(path)?.interfaces()
+}
+
+pub fn get_pads(path: impl AsRef<Path>) -> Result<Vec<MediaV2Pad>, MediaError> {
+    MediaDevice::open(path)?.pads()
+}
+
+pub fn get_links(path: impl AsRef<Path>) -> Result<Vec<MediaV2Link>, MediaError> {
+    MediaDevice::open(path)?.links()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A topology whose entity count is `counts[n]` at the `n`th sizing call,
+    /// failing every fetching call that comes before `fetch_at` with ENOSPC.
+    /// The fetching call reports one entity fewer than it was sized for.
+    struct FakeTopology {
+        counts: Vec<u32>,
+        fetch_at: usize,
+        calls: usize,
+        buffer_lens: Vec<usize>,
+    }
+
+    impl FakeTopology {
+        fn g_topology(
+            &mut self,
+            topology: &mut media_ffi::media_v2_topology,
+            sections: TopologySections,
+        ) -> nix::Result<()> {
+            let attempt = self.calls / 2;
+            let fetching = self.calls % 2 == 1;
+            self.calls += 1;
+            topology.topology_version = 1;
+            let count = self.counts[attempt.min(self.counts.len() - 1)];
+            let Some(entities) = sections.entities.filter(|_| fetching) else {
+                topology.num_entities = count;
+                return Ok(());
+            };
+            self.buffer_lens.push(entities.len());
+            if attempt < self.fetch_at {
+                topology.num_entities = count + 1;
+                return Err(Errno::ENOSPC);
+            }
+            topology.num_entities = count - 1;
+            for (id, entity) in (0..).zip(entities.iter_mut()) {
+                entity.id = id;
+            }
+            Ok(())
+        }
+    }
+
+    fn fetch(fake: &mut FakeTopology) -> Result<MediaV2Topology, MediaError> {
+        TopologyQuery::new()
+            .entities()
+            .fetch_with(|topology, sections| fake.g_topology(topology, sections))
+    }
+
+    #[test]
+    fn fetch_retries_with_resized_buffers() {
+        let mut fake = FakeTopology {
+            counts: vec![2, 3],
+            fetch_at: 1,
+            calls: 0,
+            buffer_lens: Vec::new(),
+        };
+        let topology = fetch(&mut fake).unwrap();
+        assert_eq!(fake.calls, 4);
+        assert_eq!(fake.buffer_lens, [2, 3]);
+        let ids: Vec<u32> = topology.entities.iter().map(|e| e.id().into()).collect();
+        assert_eq!(ids, [0, 1]);
+    }
+
+    #[test]
+    fn fetch_gives_up_after_max_attempts() {
+        let mut fake = FakeTopology {
+            counts: vec![1],
+            fetch_at: usize::MAX,
+            calls: 0,
+            buffer_lens: Vec::new(),
+        };
+        let err = fetch(&mut fake).unwrap_err();
+        assert_eq!(err.errno(), Some(Errno::ENOSPC));
+        assert_eq!(fake.calls, 2 * MAX_ATTEMPTS);
+    }
+}
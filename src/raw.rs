@@ -0,0 +1,587 @@
+//! The unsafe code of the crate: issuing the media ioctls, and viewing the
+//! UAPI structs they exchange as bytes.
+//!
+//! Everything here is safe to call, relying on these invariants:
+//!
+//! - The types implementing [`Pod`] are `#[repr(C)]` bindgen structs, or
+//!   the hand-written subdevice structs of `subdev_ffi`, of integers,
+//!   arrays and unions of those, without padding. Any bit pattern, all
+//!   zeroes included, is a valid value, and all their bytes are
+//!   initialized. `media_pad_desc` and `media_link_desc` have padding after
+//!   the pad index, so they aren't `Pod`: [`pad_desc`] and [`link_desc`]
+//!   build them field by field, and they're never viewed as bytes.
+//! - `MEDIA_IOC_G_TOPOLOGY` writes at most `num_*` objects through each
+//!   `ptr_*` it's given. The wrapper sets both from the same slice.
+//! - `VIDIOC_SUBDEV_G_ROUTING` and `VIDIOC_SUBDEV_S_ROUTING` write at most
+//!   `len_routes` routes through `routes`, set from the same slice.
+//! - `MEDIA_IOC_ENUM_LINKS` takes no sizes: it writes as many pads and
+//!   forward links as `MEDIA_IOC_ENUM_ENTITIES` reports for the entity. The
+//!   wrapper sizes the buffers from a `MEDIA_IOC_ENUM_ENTITIES` call issued
+//!   right before, which only a driver adding links to the entity in between
+//!   could invalidate.
+//!
+//! Outside of this module, only the public `unsafe` APIs remain:
+//! [`crate::MediaDevice::ioctl_raw`], `FromRawFd for MediaDevice`, and the
+//! C API of the `capi` feature.
+
+use std::{
+    mem::size_of,
+    os::fd::{AsRawFd, BorrowedFd, FromRawFd, OwnedFd},
+    os::raw::{c_char, c_int},
+    ptr,
+};
+
+use crate::media_ffi::{
+    media_device_info, media_entity_desc, media_link_desc, media_links_enum, media_pad_desc,
+    media_v2_entity, media_v2_interface, media_v2_link, media_v2_pad, media_v2_topology,
+};
+
+nix::ioctl_readwrite!(media_ioc_device_info, b'|', 0x00, media_device_info);
+nix::ioctl_readwrite!(media_ioc_enum_entities, b'|', 0x01, media_entity_desc);
+nix::ioctl_readwrite!(media_ioc_enum_links, b'|', 0x02, media_links_enum);
+nix::ioctl_readwrite!(media_ioc_setup_link, b'|', 0x03, media_link_desc);
+nix::ioctl_readwrite!(media_ioc_g_topology, b'|', 0x04, media_v2_topology);
+nix::ioctl_read!(media_ioc_request_alloc, b'|', 0x05, c_int);
+nix::ioctl_none!(media_request_ioc_queue, b'|', 0x80);
+nix::ioctl_none!(media_request_ioc_reinit, b'|', 0x81);
+
+#[cfg(feature = "subdev")]
+mod subdev_ioctls {
+    use crate::subdev_ffi::{
+        v4l2_subdev_client_capability, v4l2_subdev_format, v4l2_subdev_frame_interval_enum,
+        v4l2_subdev_frame_size_enum, v4l2_subdev_mbus_code_enum, v4l2_subdev_routing,
+        v4l2_subdev_selection,
+    };
+
+    nix::ioctl_readwrite!(vidioc_subdev_g_fmt, b'V', 4, v4l2_subdev_format);
+    nix::ioctl_readwrite!(vidioc_subdev_s_fmt, b'V', 5, v4l2_subdev_format);
+    nix::ioctl_readwrite!(vidioc_subdev_g_selection, b'V', 61, v4l2_subdev_selection);
+    nix::ioctl_readwrite!(vidioc_subdev_s_selection, b'V', 62, v4l2_subdev_selection);
+    nix::ioctl_readwrite!(
+        vidioc_subdev_enum_mbus_code,
+        b'V',
+        2,
+        v4l2_subdev_mbus_code_enum
+    );
+    nix::ioctl_readwrite!(
+        vidioc_subdev_enum_frame_size,
+        b'V',
+        74,
+        v4l2_subdev_frame_size_enum
+    );
+    nix::ioctl_readwrite!(
+        vidioc_subdev_enum_frame_interval,
+        b'V',
+        75,
+        v4l2_subdev_frame_interval_enum
+    );
+    nix::ioctl_readwrite!(vidioc_subdev_g_routing, b'V', 38, v4l2_subdev_routing);
+    nix::ioctl_readwrite!(vidioc_subdev_s_routing, b'V', 39, v4l2_subdev_routing);
+    nix::ioctl_readwrite!(
+        vidioc_subdev_s_client_cap,
+        b'V',
+        102,
+        v4l2_subdev_client_capability
+    );
+}
+#[cfg(feature = "subdev")]
+use subdev_ioctls::*;
+
+/// Plain old data: valid for any bit pattern and free of padding.
+///
+/// # Safety
+///
+/// Only implemented for types meeting the invariants of the
+/// [module documentation](self).
+pub(crate) unsafe trait Pod: Copy + 'static {}
+
+unsafe impl Pod for media_device_info {}
+unsafe impl Pod for media_entity_desc {}
+unsafe impl Pod for media_v2_topology {}
+unsafe impl Pod for media_v2_entity {}
+unsafe impl Pod for media_v2_interface {}
+unsafe impl Pod for media_v2_pad {}
+unsafe impl Pod for media_v2_link {}
+unsafe impl Pod for c_int {}
+#[cfg(feature = "subdev")]
+unsafe impl Pod for crate::subdev_ffi::v4l2_subdev_format {}
+#[cfg(feature = "subdev")]
+unsafe impl Pod for crate::subdev_ffi::v4l2_subdev_selection {}
+#[cfg(feature = "subdev")]
+unsafe impl Pod for crate::subdev_ffi::v4l2_subdev_mbus_code_enum {}
+#[cfg(feature = "subdev")]
+unsafe impl Pod for crate::subdev_ffi::v4l2_subdev_frame_size_enum {}
+#[cfg(feature = "subdev")]
+unsafe impl Pod for crate::subdev_ffi::v4l2_subdev_frame_interval_enum {}
+#[cfg(feature = "subdev")]
+unsafe impl Pod for crate::subdev_ffi::v4l2_subdev_route {}
+#[cfg(feature = "subdev")]
+unsafe impl Pod for crate::subdev_ffi::v4l2_subdev_routing {}
+#[cfg(feature = "subdev")]
+unsafe impl Pod for crate::subdev_ffi::v4l2_subdev_client_capability {}
+
+pub(crate) fn zeroed<T: Pod>() -> T {
+    unsafe { std::mem::zeroed() }
+}
+
+pub(crate) fn zeroed_vec<T: Pod>(len: usize) -> Vec<T> {
+    vec![zeroed(); len]
+}
+
+/// Refills `buffer` with `len` zeroed objects, reusing its allocation when
+/// it's large enough.
+pub(crate) fn reset<T: Pod>(buffer: &mut Vec<T>, len: usize) {
+    buffer.clear();
+    buffer.resize(len, zeroed());
+}
+
+pub(crate) fn pad_desc(entity: u32, index: u16, flags: u32) -> media_pad_desc {
+    media_pad_desc {
+        entity,
+        index,
+        flags,
+        reserved: [0; 2],
+    }
+}
+
+pub(crate) fn link_desc(
+    source: media_pad_desc,
+    sink: media_pad_desc,
+    flags: u32,
+) -> media_link_desc {
+    media_link_desc {
+        source,
+        sink,
+        flags,
+        reserved: [0; 2],
+    }
+}
+
+pub(crate) fn as_bytes<T: Pod>(value: &T) -> &[u8] {
+    unsafe { std::slice::from_raw_parts((value as *const T).cast(), size_of::<T>()) }
+}
+
+pub(crate) fn slice_as_bytes<T: Pod>(values: &[T]) -> &[u8] {
+    unsafe { std::slice::from_raw_parts(values.as_ptr().cast(), std::mem::size_of_val(values)) }
+}
+
+/// Reads a struct from its bytes, `None` if their size doesn't match.
+pub(crate) fn read<T: Pod>(bytes: &[u8]) -> Option<T> {
+    (bytes.len() == size_of::<T>()).then(|| unsafe { ptr::read_unaligned(bytes.as_ptr().cast()) })
+}
+
+/// Reads an array of structs, `None` if the bytes don't divide into whole
+/// structs.
+pub(crate) fn read_slice<T: Pod>(bytes: &[u8]) -> Option<Vec<T>> {
+    bytes.chunks(size_of::<T>()).map(read).collect()
+}
+
+/// A fixed-size kernel string as bytes. `c_char` is `i8` or `u8` depending
+/// on the target, both laid out like `u8`.
+pub(crate) fn c_chars_as_bytes(c_str: &[c_char]) -> &[u8] {
+    unsafe { std::slice::from_raw_parts(c_str.as_ptr().cast(), c_str.len()) }
+}
+
+/// `(major, minor)` of the device node union member of a legacy entity.
+pub(crate) fn entity_dev(entity: &media_entity_desc) -> (u32, u32) {
+    let dev = unsafe { entity.__bindgen_anon_1.dev };
+    (dev.major, dev.minor)
+}
+
+/// `(major, minor)` of the device node union member of an interface.
+pub(crate) fn intf_devnode(intf: &media_v2_interface) -> (u32, u32) {
+    let devnode = unsafe { intf.__bindgen_anon_1.devnode };
+    (devnode.major, devnode.minor)
+}
+
+pub(crate) fn device_info(fd: BorrowedFd, info: &mut media_device_info) -> nix::Result<()> {
+    traced("MEDIA_IOC_DEVICE_INFO", fd, unsafe {
+        media_ioc_device_info(fd.as_raw_fd(), info)
+    })
+    .map(drop)
+}
+
+pub(crate) fn setup_link(fd: BorrowedFd, link: &mut media_link_desc) -> nix::Result<()> {
+    traced("MEDIA_IOC_SETUP_LINK", fd, unsafe {
+        media_ioc_setup_link(fd.as_raw_fd(), link)
+    })
+    .map(drop)
+}
+
+pub(crate) fn enum_entity(fd: BorrowedFd, entity: &mut media_entity_desc) -> nix::Result<()> {
+    traced("MEDIA_IOC_ENUM_ENTITIES", fd, unsafe {
+        media_ioc_enum_entities(fd.as_raw_fd(), entity)
+    })
+    .map(drop)
+}
+
+/// `MEDIA_IOC_ENUM_LINKS` for `entity`, returning its entity description,
+/// pads and forward links.
+pub(crate) fn enum_links(
+    fd: BorrowedFd,
+    entity: u32,
+) -> nix::Result<(media_entity_desc, Vec<media_pad_desc>, Vec<media_link_desc>)> {
+    let mut desc: media_entity_desc = zeroed();
+    desc.id = entity;
+    enum_entity(fd, &mut desc)?;
+    let mut pads = vec![pad_desc(0, 0, 0); desc.pads.into()];
+    let mut links = vec![link_desc(pad_desc(0, 0, 0), pad_desc(0, 0, 0), 0); desc.links.into()];
+    let mut links_enum = media_links_enum {
+        entity,
+        pads: pads.as_mut_ptr(),
+        links: links.as_mut_ptr(),
+        reserved: [0; 4],
+    };
+    traced("MEDIA_IOC_ENUM_LINKS", fd, unsafe {
+        media_ioc_enum_links(fd.as_raw_fd(), &mut links_enum)
+    })?;
+    Ok((desc, pads, links))
+}
+
+/// Buffers for the sections of `MEDIA_IOC_G_TOPOLOGY`. Sections left `None`
+/// are only counted by the kernel.
+#[derive(Default)]
+pub(crate) struct TopologySections<'a> {
+    pub(crate) entities: Option<&'a mut [media_v2_entity]>,
+    pub(crate) interfaces: Option<&'a mut [media_v2_interface]>,
+    pub(crate) pads: Option<&'a mut [media_v2_pad]>,
+    pub(crate) links: Option<&'a mut [media_v2_link]>,
+}
+
+/// `MEDIA_IOC_G_TOPOLOGY`, filling `sections`. The `num_*` and `ptr_*`
+/// fields of `topology` are overwritten from them. On success, `num_*` is
+/// the number of objects the kernel stored, at most the section length.
+pub(crate) fn g_topology(
+    fd: BorrowedFd,
+    topology: &mut media_v2_topology,
+    sections: TopologySections,
+) -> nix::Result<()> {
+    fn section<T>(section: Option<&mut [T]>) -> (u32, u64) {
+        match section {
+            Some(section) => (
+                u32::try_from(section.len()).unwrap_or(u32::MAX),
+                section.as_mut_ptr() as u64,
+            ),
+            None => (0, 0),
+        }
+    }
+    (topology.num_entities, topology.ptr_entities) = section(sections.entities);
+    (topology.num_interfaces, topology.ptr_interfaces) = section(sections.interfaces);
+    (topology.num_pads, topology.ptr_pads) = section(sections.pads);
+    (topology.num_links, topology.ptr_links) = section(sections.links);
+    let passed = [
+        (topology.ptr_entities, topology.num_entities),
+        (topology.ptr_interfaces, topology.num_interfaces),
+        (topology.ptr_pads, topology.num_pads),
+        (topology.ptr_links, topology.num_links),
+    ];
+    traced("MEDIA_IOC_G_TOPOLOGY", fd, unsafe {
+        media_ioc_g_topology(fd.as_raw_fd(), topology)
+    })?;
+    let stored = [
+        topology.num_entities,
+        topology.num_interfaces,
+        topology.num_pads,
+        topology.num_links,
+    ];
+    for ((ptr, capacity), stored) in passed.into_iter().zip(stored) {
+        debug_assert!(
+            ptr == 0 || stored <= capacity,
+            "MEDIA_IOC_G_TOPOLOGY stored {stored} objects in a buffer of {capacity}"
+        );
+    }
+    Ok(())
+}
+
+pub(crate) fn request_alloc(fd: BorrowedFd) -> nix::Result<OwnedFd> {
+    let mut request_fd: c_int = -1;
+    traced("MEDIA_IOC_REQUEST_ALLOC", fd, unsafe {
+        media_ioc_request_alloc(fd.as_raw_fd(), &mut request_fd)
+    })?;
+    Ok(unsafe { OwnedFd::from_raw_fd(request_fd) })
+}
+
+pub(crate) fn request_queue(request: BorrowedFd) -> nix::Result<()> {
+    traced("MEDIA_REQUEST_IOC_QUEUE", request, unsafe {
+        media_request_ioc_queue(request.as_raw_fd())
+    })
+    .map(drop)
+}
+
+pub(crate) fn request_reinit(request: BorrowedFd) -> nix::Result<()> {
+    traced("MEDIA_REQUEST_IOC_REINIT", request, unsafe {
+        media_request_ioc_reinit(request.as_raw_fd())
+    })
+    .map(drop)
+}
+
+#[cfg(feature = "subdev")]
+pub(crate) fn subdev_g_fmt(
+    fd: BorrowedFd,
+    format: &mut crate::subdev_ffi::v4l2_subdev_format,
+) -> nix::Result<()> {
+    traced("VIDIOC_SUBDEV_G_FMT", fd, unsafe {
+        vidioc_subdev_g_fmt(fd.as_raw_fd(), format)
+    })
+    .map(drop)
+}
+
+#[cfg(feature = "subdev")]
+pub(crate) fn subdev_s_fmt(
+    fd: BorrowedFd,
+    format: &mut crate::subdev_ffi::v4l2_subdev_format,
+) -> nix::Result<()> {
+    traced("VIDIOC_SUBDEV_S_FMT", fd, unsafe {
+        vidioc_subdev_s_fmt(fd.as_raw_fd(), format)
+    })
+    .map(drop)
+}
+
+#[cfg(feature = "subdev")]
+pub(crate) fn subdev_g_selection(
+    fd: BorrowedFd,
+    selection: &mut crate::subdev_ffi::v4l2_subdev_selection,
+) -> nix::Result<()> {
+    traced("VIDIOC_SUBDEV_G_SELECTION", fd, unsafe {
+        vidioc_subdev_g_selection(fd.as_raw_fd(), selection)
+    })
+    .map(drop)
+}
+
+#[cfg(feature = "subdev")]
+pub(crate) fn subdev_s_selection(
+    fd: BorrowedFd,
+    selection: &mut crate::subdev_ffi::v4l2_subdev_selection,
+) -> nix::Result<()> {
+    traced("VIDIOC_SUBDEV_S_SELECTION", fd, unsafe {
+        vidioc_subdev_s_selection(fd.as_raw_fd(), selection)
+    })
+    .map(drop)
+}
+
+#[cfg(feature = "subdev")]
+pub(crate) fn subdev_enum_mbus_code(
+    fd: BorrowedFd,
+    code: &mut crate::subdev_ffi::v4l2_subdev_mbus_code_enum,
+) -> nix::Result<()> {
+    traced("VIDIOC_SUBDEV_ENUM_MBUS_CODE", fd, unsafe {
+        vidioc_subdev_enum_mbus_code(fd.as_raw_fd(), code)
+    })
+    .map(drop)
+}
+
+#[cfg(feature = "subdev")]
+pub(crate) fn subdev_enum_frame_size(
+    fd: BorrowedFd,
+    size: &mut crate::subdev_ffi::v4l2_subdev_frame_size_enum,
+) -> nix::Result<()> {
+    traced("VIDIOC_SUBDEV_ENUM_FRAME_SIZE", fd, unsafe {
+        vidioc_subdev_enum_frame_size(fd.as_raw_fd(), size)
+    })
+    .map(drop)
+}
+
+#[cfg(feature = "subdev")]
+pub(crate) fn subdev_enum_frame_interval(
+    fd: BorrowedFd,
+    interval: &mut crate::subdev_ffi::v4l2_subdev_frame_interval_enum,
+) -> nix::Result<()> {
+    traced("VIDIOC_SUBDEV_ENUM_FRAME_INTERVAL", fd, unsafe {
+        vidioc_subdev_enum_frame_interval(fd.as_raw_fd(), interval)
+    })
+    .map(drop)
+}
+
+/// `VIDIOC_SUBDEV_G_ROUTING` into `routes`. The `len_routes` and `routes`
+/// fields of `routing` are overwritten from the slice. On success and on
+/// `ENOSPC`, `num_routes` is the number of routes of the subdevice, of which
+/// at most the slice length were stored.
+#[cfg(feature = "subdev")]
+pub(crate) fn subdev_g_routing(
+    fd: BorrowedFd,
+    routing: &mut crate::subdev_ffi::v4l2_subdev_routing,
+    routes: &mut [crate::subdev_ffi::v4l2_subdev_route],
+) -> nix::Result<()> {
+    routing.len_routes = u32::try_from(routes.len()).unwrap_or(u32::MAX);
+    routing.routes = routes.as_mut_ptr() as u64;
+    traced("VIDIOC_SUBDEV_G_ROUTING", fd, unsafe {
+        vidioc_subdev_g_routing(fd.as_raw_fd(), routing)
+    })?;
+    debug_assert!(
+        routing.num_routes <= routing.len_routes,
+        "VIDIOC_SUBDEV_G_ROUTING stored {} routes in a buffer of {}",
+        routing.num_routes,
+        routing.len_routes
+    );
+    Ok(())
+}
+
+/// `VIDIOC_SUBDEV_S_ROUTING` of `routes`, which the kernel overwrites with
+/// the routing it applied, like [`subdev_g_routing`].
+#[cfg(feature = "subdev")]
+pub(crate) fn subdev_s_routing(
+    fd: BorrowedFd,
+    routing: &mut crate::subdev_ffi::v4l2_subdev_routing,
+    routes: &mut [crate::subdev_ffi::v4l2_subdev_route],
+) -> nix::Result<()> {
+    routing.len_routes = u32::try_from(routes.len()).unwrap_or(u32::MAX);
+    routing.num_routes = routing.len_routes;
+    routing.routes = routes.as_mut_ptr() as u64;
+    traced("VIDIOC_SUBDEV_S_ROUTING", fd, unsafe {
+        vidioc_subdev_s_routing(fd.as_raw_fd(), routing)
+    })
+    .map(drop)
+}
+
+#[cfg(feature = "subdev")]
+pub(crate) fn subdev_s_client_cap(
+    fd: BorrowedFd,
+    cap: &mut crate::subdev_ffi::v4l2_subdev_client_capability,
+) -> nix::Result<()> {
+    traced("VIDIOC_SUBDEV_S_CLIENT_CAP", fd, unsafe {
+        vidioc_subdev_s_client_cap(fd.as_raw_fd(), cap)
+    })
+    .map(drop)
+}
+
+/// Reports the outcome of an ioctl on `fd` as a `tracing` event, a no-op
+/// without the `tracing` feature.
+#[inline]
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+pub(crate) fn traced<T>(
+    ioctl: &'static str,
+    fd: BorrowedFd,
+    res: nix::Result<T>,
+) -> nix::Result<T> {
+    #[cfg(feature = "tracing")]
+    match &res {
+        Ok(_) => tracing::trace!(ioctl, fd = fd.as_raw_fd(), path = %FdPath(fd)),
+        Err(errno) => tracing::debug!(
+            ioctl,
+            fd = fd.as_raw_fd(),
+            path = %FdPath(fd),
+            %errno,
+            "ioctl failed"
+        ),
+    }
+    res
+}
+
+/// Displays the path a descriptor was opened from, only resolved when the
+/// event is recorded.
+#[cfg(feature = "tracing")]
+struct FdPath<'a>(BorrowedFd<'a>);
+
+#[cfg(feature = "tracing")]
+impl std::fmt::Display for FdPath<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match crate::fd_path(self.0) {
+            Ok(path) => path.display().fmt(f),
+            Err(_) => f.write_str("?"),
+        }
+    }
+}
+
+/// The read end of a pipe whose write end is closed.
+pub(crate) fn closed_pipe() -> nix::Result<OwnedFd> {
+    let (read, write) = nix::unistd::pipe()?;
+    let _ = nix::unistd::close(write);
+    Ok(unsafe { OwnedFd::from_raw_fd(read) })
+}
+
+// No ioctls below, so these run under Miri: `cargo miri test raw::`.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pad(id: u32) -> media_v2_pad {
+        media_v2_pad {
+            id,
+            entity_id: id + 1,
+            flags: 2,
+            index: 3,
+            reserved: [0; 4],
+        }
+    }
+
+    #[test]
+    fn zeroed_vec_is_zeroed() {
+        let pads: Vec<media_v2_pad> = zeroed_vec(3);
+        assert_eq!(pads.len(), 3);
+        assert!(slice_as_bytes(&pads).iter().all(|&byte| byte == 0));
+        assert!(zeroed_vec::<media_v2_pad>(0).is_empty());
+    }
+
+    #[test]
+    fn reset_zeroes_and_resizes() {
+        let mut pads = vec![pad(1), pad(2)];
+        reset(&mut pads, 4);
+        assert_eq!(pads.len(), 4);
+        assert!(slice_as_bytes(&pads).iter().all(|&byte| byte == 0));
+
+        pads[0] = pad(5);
+        reset(&mut pads, 1);
+        assert_eq!(pads.len(), 1);
+        assert_eq!({ pads[0].id }, 0);
+    }
+
+    #[test]
+    fn as_bytes_matches_fields() {
+        let pad = pad(0x0403_0201);
+        let bytes = as_bytes(&pad);
+        assert_eq!(bytes.len(), size_of::<media_v2_pad>());
+        assert_eq!(bytes[..4], 0x0403_0201u32.to_ne_bytes());
+        assert_eq!(bytes[12..16], 3u32.to_ne_bytes());
+    }
+
+    #[test]
+    fn read_round_trips() {
+        let pad = pad(7);
+        let read: media_v2_pad = read(as_bytes(&pad)).unwrap();
+        assert_eq!(as_bytes(&read), as_bytes(&pad));
+    }
+
+    #[test]
+    fn read_unaligned_bytes() {
+        let pad = pad(7);
+        let mut bytes = vec![0];
+        bytes.extend_from_slice(as_bytes(&pad));
+        let read: media_v2_pad = read(&bytes[1..]).unwrap();
+        assert_eq!(as_bytes(&read), as_bytes(&pad));
+    }
+
+    #[test]
+    fn read_rejects_wrong_size() {
+        let pad = pad(7);
+        let bytes = as_bytes(&pad);
+        assert!(read::<media_v2_pad>(&bytes[1..]).is_none());
+        assert!(read::<media_v2_pad>(&[]).is_none());
+    }
+
+    #[test]
+    fn read_slice_round_trips() {
+        let pads = vec![pad(1), pad(2), pad(3)];
+        let read: Vec<media_v2_pad> = read_slice(slice_as_bytes(&pads)).unwrap();
+        assert_eq!(slice_as_bytes(&read), slice_as_bytes(&pads));
+        assert!(read_slice::<media_v2_pad>(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn read_slice_rejects_partial_struct() {
+        let pads = vec![pad(1), pad(2)];
+        let bytes = slice_as_bytes(&pads);
+        assert!(read_slice::<media_v2_pad>(&bytes[..bytes.len() - 1]).is_none());
+    }
+
+    #[test]
+    fn c_chars_as_bytes_keeps_all_chars() {
+        let mut info: media_device_info = zeroed();
+        for (c, &b) in info.driver.iter_mut().zip(b"uvcvideo") {
+            *c = b as c_char;
+        }
+        let bytes = c_chars_as_bytes(&info.driver);
+        assert_eq!(bytes.len(), 16);
+        assert_eq!(&bytes[..8], b"uvcvideo");
+        assert!(bytes[8..].iter().all(|&byte| byte == 0));
+    }
+}
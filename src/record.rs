@@ -0,0 +1,543 @@
+//! Recording of the ioctls issued through a [`MediaBackend`], and their
+//! replay without the device.
+//!
+//! A recording is a text file with one ioctl per line: the ioctl name, the
+//! errno it returned (`0` on success), the argument struct as the kernel left
+//! it, and for `MEDIA_IOC_G_TOPOLOGY` the sections the kernel filled in, all
+//! in hexadecimal. `struct media_link_desc` has padding, so the argument of
+//! `MEDIA_IOC_SETUP_LINK` is recorded field by field instead: the entity,
+//! index and flags of the source and sink pads, then the link flags, each as
+//! a little-endian `u32`:
+//!
+//! ```text
+//! # mc-api ioctl recording 1
+//! MEDIA_IOC_DEVICE_INFO 0 766976696300...
+//! MEDIA_IOC_G_TOPOLOGY 0 0300000000000000... entities=0100000073656e73... pads=...
+//! ```
+//!
+//! Pointers inside argument structs are recorded as they were and ignored on
+//! replay.
+
+use std::{
+    collections::VecDeque,
+    fmt::Write as _,
+    fs,
+    mem::size_of,
+    os::fd::{AsFd, AsRawFd},
+    path::Path,
+    sync::Mutex,
+};
+
+use nix::errno::Errno;
+
+use crate::media_ffi::{
+    self, media_device_info, media_link_desc, media_pad_desc, media_v2_entity, media_v2_interface,
+    media_v2_link, media_v2_pad,
+};
+use crate::{
+    legacy, plan,
+    raw::{self, Pod, TopologySections},
+    EntityRef, LinkRequest, LinkSetupError, MediaBackend, MediaDevice, MediaDeviceInfo, MediaError,
+    MediaGraph, MediaV2Topology, PadId, PadRef, Request,
+};
+
+const HEADER: &str = "# mc-api ioctl recording 1";
+
+/// One ioctl of a recording.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IoctlRecord {
+    pub ioctl: String,
+    /// `0` if the ioctl succeeded.
+    pub errno: i32,
+    /// The argument struct after the call.
+    pub arg: Vec<u8>,
+    /// Named buffers the kernel wrote to, e.g. the `entities` section of
+    /// `MEDIA_IOC_G_TOPOLOGY`.
+    pub buffers: Vec<(String, Vec<u8>)>,
+}
+
+impl IoctlRecord {
+    fn buffer(&self, name: &str) -> &[u8] {
+        self.buffers
+            .iter()
+            .find(|(buffer, _)| buffer == name)
+            .map_or(&[][..], |(_, bytes)| bytes)
+    }
+}
+
+/// A [`MediaBackend`] forwarding to a device and recording every ioctl it
+/// issues, for [`ReplayBackend`].
+///
+/// Unlike [`MediaDevice::topology`], kernels without `MEDIA_IOC_G_TOPOLOGY`
+/// fail with [`MediaError::UnsupportedKernel`] rather than falling back to
+/// the legacy ioctls.
+#[derive(Debug)]
+pub struct RecordingBackend {
+    device: MediaDevice,
+    records: Mutex<Vec<IoctlRecord>>,
+}
+
+impl RecordingBackend {
+    pub fn new(device: MediaDevice) -> RecordingBackend {
+        RecordingBackend {
+            device,
+            records: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn records(&self) -> Vec<IoctlRecord> {
+        self.lock().clone()
+    }
+
+    /// The recording in the text format of the [module documentation](self).
+    pub fn to_text(&self) -> String {
+        records_to_text(&self.lock())
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), MediaError> {
+        let path = path.as_ref();
+        fs::write(path, self.to_text()).map_err(|source| MediaError::Open {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    pub fn into_device(self) -> MediaDevice {
+        self.device
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<IoctlRecord>> {
+        self.records
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn record<T: Pod>(
+        &self,
+        ioctl: &'static str,
+        res: nix::Result<()>,
+        arg: &T,
+    ) -> Result<(), MediaError> {
+        self.record_with_buffers(ioctl, res, arg, Vec::new())
+    }
+
+    fn record_with_buffers<T: Pod>(
+        &self,
+        ioctl: &'static str,
+        res: nix::Result<()>,
+        arg: &T,
+        buffers: Vec<(String, Vec<u8>)>,
+    ) -> Result<(), MediaError> {
+        self.lock().push(IoctlRecord {
+            ioctl: ioctl.to_string(),
+            errno: res.err().map_or(0, |errno| errno as i32),
+            arg: raw::as_bytes(arg).to_vec(),
+            buffers,
+        });
+        res.map_err(|errno| MediaError::ioctl(ioctl, errno))
+    }
+}
+
+impl MediaBackend for RecordingBackend {
+    fn device_info(&self) -> Result<MediaDeviceInfo, MediaError> {
+        let mut info: media_device_info = raw::zeroed();
+        let res = raw::device_info(self.device.as_fd(), &mut info);
+        self.record("MEDIA_IOC_DEVICE_INFO", res, &info)?;
+        Ok(MediaDeviceInfo::from_ffi(&info))
+    }
+
+    fn topology(&self) -> Result<MediaV2Topology, MediaError> {
+        let fd = self.device.as_fd();
+        let mut topology: media_ffi::media_v2_topology = raw::zeroed();
+        let res = raw::g_topology(fd, &mut topology, TopologySections::default());
+        self.record("MEDIA_IOC_G_TOPOLOGY", res, &topology)?;
+        let version = topology.topology_version;
+
+        let mut entities: Vec<media_v2_entity> = raw::zeroed_vec(topology.num_entities as usize);
+        let mut interfaces: Vec<media_v2_interface> =
+            raw::zeroed_vec(topology.num_interfaces as usize);
+        let mut pads: Vec<media_v2_pad> = raw::zeroed_vec(topology.num_pads as usize);
+        let mut links: Vec<media_v2_link> = raw::zeroed_vec(topology.num_links as usize);
+        let sections = TopologySections {
+            entities: Some(&mut entities),
+            interfaces: Some(&mut interfaces),
+            pads: Some(&mut pads),
+            links: Some(&mut links),
+        };
+        let res = raw::g_topology(fd, &mut topology, sections);
+        if res.is_ok() {
+            entities.truncate(topology.num_entities as usize);
+            interfaces.truncate(topology.num_interfaces as usize);
+            pads.truncate(topology.num_pads as usize);
+            links.truncate(topology.num_links as usize);
+        }
+        let buffers = vec![
+            (
+                "entities".to_string(),
+                raw::slice_as_bytes(&entities).to_vec(),
+            ),
+            (
+                "interfaces".to_string(),
+                raw::slice_as_bytes(&interfaces).to_vec(),
+            ),
+            ("pads".to_string(), raw::slice_as_bytes(&pads).to_vec()),
+            ("links".to_string(), raw::slice_as_bytes(&links).to_vec()),
+        ];
+        self.record_with_buffers("MEDIA_IOC_G_TOPOLOGY", res, &topology, buffers)?;
+        if topology.topology_version != version {
+            return Err(MediaError::VersionChange {
+                old_version: version,
+                new_version: topology.topology_version,
+            });
+        }
+        Ok(MediaV2Topology::from_ffi(
+            &topology,
+            &entities,
+            &interfaces,
+            &pads,
+            &links,
+        ))
+    }
+
+    fn setup_link(
+        &self,
+        source_pad_id: PadId,
+        sink_pad_id: PadId,
+        flags: u32,
+    ) -> Result<(), MediaError> {
+        let graph = MediaGraph::new(self.topology()?);
+        // Repairing the pad indexes would take unrecorded legacy ioctls.
+        legacy::require_pad_indexes(self.device.as_fd(), graph.topology())?;
+        let find_pad = |id: PadId| graph.pad(id).ok_or(MediaError::UnknownPad(id));
+        let (source, sink) = (find_pad(source_pad_id)?, find_pad(sink_pad_id)?);
+        let mut link = raw::link_desc(source.to_ffi_desc()?, sink.to_ffi_desc()?, flags);
+        let res = raw::setup_link(self.device.as_fd(), &mut link);
+        self.lock().push(IoctlRecord {
+            ioctl: "MEDIA_IOC_SETUP_LINK".to_string(),
+            errno: res.err().map_or(0, |errno| errno as i32),
+            arg: link_desc_to_bytes(&link),
+            buffers: Vec::new(),
+        });
+        res.map_err(|errno| MediaError::ioctl("MEDIA_IOC_SETUP_LINK", errno))
+            .map_err(|err| link_setup_error(err, &graph, source_pad_id, sink_pad_id))
+    }
+
+    fn request_alloc(&self) -> Result<Request, MediaError> {
+        let res = self.device.alloc_request();
+        let (errno, request_fd) = match &res {
+            Ok(request) => (0, request.fd().as_raw_fd()),
+            Err(err) => (err.errno().map_or(0, |errno| errno as i32), -1),
+        };
+        self.lock().push(IoctlRecord {
+            ioctl: "MEDIA_IOC_REQUEST_ALLOC".to_string(),
+            errno,
+            arg: request_fd.to_ne_bytes().to_vec(),
+            buffers: Vec::new(),
+        });
+        res
+    }
+}
+
+impl AsFd for RecordingBackend {
+    fn as_fd(&self) -> std::os::fd::BorrowedFd<'_> {
+        self.device.as_fd()
+    }
+}
+
+/// A [`MediaBackend`] answering from a recording made by
+/// [`RecordingBackend`], e.g. one sent along with a bug report.
+///
+/// The calls must come in the order they were recorded; a call the next
+/// record doesn't match, including a link setup of other pads or flags,
+/// fails with [`MediaError::ReplayMismatch`]. Requests are not bound to any
+/// device: they can be held and polled, but queueing them fails.
+#[derive(Debug)]
+pub struct ReplayBackend {
+    records: Mutex<VecDeque<IoctlRecord>>,
+}
+
+impl ReplayBackend {
+    pub fn new(records: Vec<IoctlRecord>) -> ReplayBackend {
+        ReplayBackend {
+            records: Mutex::new(records.into()),
+        }
+    }
+
+    /// Parses a recording in the text format of the
+    /// [module documentation](self).
+    pub fn from_text(text: &str) -> Result<ReplayBackend, MediaError> {
+        let mut lines = text.lines();
+        if lines.next() != Some(HEADER) {
+            return Err(MediaError::InvalidRecording("missing header".to_string()));
+        }
+        let records = lines
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(index, line)| {
+                parse_record(line).map_err(|reason| {
+                    MediaError::InvalidRecording(format!("line {}: {reason}", index + 2))
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(ReplayBackend::new(records))
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<ReplayBackend, MediaError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|source| MediaError::Open {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        ReplayBackend::from_text(&text)
+    }
+
+    /// Number of records not replayed yet.
+    pub fn remaining(&self) -> usize {
+        self.lock().len()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, VecDeque<IoctlRecord>> {
+        self.records
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Takes the next record, which must be of `ioctl`, failing with the
+    /// recorded errno.
+    fn next(&self, ioctl: &'static str) -> Result<IoctlRecord, MediaError> {
+        recorded_result(ioctl, self.next_record(ioctl)?)
+    }
+
+    /// Takes the next record, which must be of `ioctl`, whatever its errno.
+    fn next_record(&self, ioctl: &'static str) -> Result<IoctlRecord, MediaError> {
+        let record = self.lock().pop_front();
+        match record {
+            Some(record) if record.ioctl == ioctl => Ok(record),
+            record => Err(MediaError::ReplayMismatch {
+                expected: record.map(|record| record.ioctl),
+                found: ioctl,
+                link: None,
+            }),
+        }
+    }
+}
+
+impl MediaBackend for ReplayBackend {
+    fn device_info(&self) -> Result<MediaDeviceInfo, MediaError> {
+        let record = self.next("MEDIA_IOC_DEVICE_INFO")?;
+        let info: media_device_info = recorded_struct(&record.arg)?;
+        Ok(MediaDeviceInfo::from_ffi(&info))
+    }
+
+    fn topology(&self) -> Result<MediaV2Topology, MediaError> {
+        let probe: media_ffi::media_v2_topology =
+            recorded_struct(&self.next("MEDIA_IOC_G_TOPOLOGY")?.arg)?;
+        let record = self.next("MEDIA_IOC_G_TOPOLOGY")?;
+        let topology: media_ffi::media_v2_topology = recorded_struct(&record.arg)?;
+        if topology.topology_version != probe.topology_version {
+            return Err(MediaError::VersionChange {
+                old_version: probe.topology_version,
+                new_version: topology.topology_version,
+            });
+        }
+        Ok(MediaV2Topology::from_ffi(
+            &topology,
+            &recorded_structs::<media_v2_entity>(record.buffer("entities"))?,
+            &recorded_structs::<media_v2_interface>(record.buffer("interfaces"))?,
+            &recorded_structs::<media_v2_pad>(record.buffer("pads"))?,
+            &recorded_structs::<media_v2_link>(record.buffer("links"))?,
+        ))
+    }
+
+    fn setup_link(
+        &self,
+        source_pad_id: PadId,
+        sink_pad_id: PadId,
+        flags: u32,
+    ) -> Result<(), MediaError> {
+        let graph = MediaGraph::new(self.topology()?);
+        let record = self.next_record("MEDIA_IOC_SETUP_LINK")?;
+        let recorded = link_request(&link_desc_from_bytes(&record.arg)?);
+        let find_pad = |id: PadId| graph.pad(id).ok_or(MediaError::UnknownPad(id));
+        let (source, sink) = (find_pad(source_pad_id)?, find_pad(sink_pad_id)?);
+        let replayed = link_request(&raw::link_desc(
+            source.to_ffi_desc()?,
+            sink.to_ffi_desc()?,
+            flags,
+        ));
+        if replayed != recorded {
+            return Err(MediaError::ReplayMismatch {
+                expected: Some(record.ioctl),
+                found: "MEDIA_IOC_SETUP_LINK",
+                link: Some(Box::new((recorded, replayed))),
+            });
+        }
+        recorded_result("MEDIA_IOC_SETUP_LINK", record)
+            .map(drop)
+            .map_err(|err| link_setup_error(err, &graph, source_pad_id, sink_pad_id))
+    }
+
+    fn request_alloc(&self) -> Result<Request, MediaError> {
+        self.next("MEDIA_IOC_REQUEST_ALLOC")?;
+        Request::detached().map_err(|errno| MediaError::ioctl("MEDIA_IOC_REQUEST_ALLOC", errno))
+    }
+}
+
+/// Reports a failed `MEDIA_IOC_SETUP_LINK` like [`MediaDevice::setup_link`].
+fn link_setup_error(
+    err: MediaError,
+    graph: &MediaGraph,
+    source_pad_id: PadId,
+    sink_pad_id: PadId,
+) -> MediaError {
+    let (Some(source), Some(sink)) = (graph.pad(source_pad_id), graph.pad(sink_pad_id)) else {
+        return err;
+    };
+    match err {
+        MediaError::Ioctl { errno, .. } => MediaError::LinkSetup {
+            source: plan::pad_ref(graph, source),
+            sink: plan::pad_ref(graph, sink),
+            cause: LinkSetupError::from_errno(errno),
+        },
+        err => err,
+    }
+}
+
+fn records_to_text(records: &[IoctlRecord]) -> String {
+    let mut text = format!("{HEADER}\n");
+    for record in records {
+        let _ = write!(
+            text,
+            "{} {} {}",
+            record.ioctl,
+            record.errno,
+            hex(&record.arg)
+        );
+        for (name, bytes) in &record.buffers {
+            let _ = write!(text, " {name}={}", hex(bytes));
+        }
+        text.push('\n');
+    }
+    text
+}
+
+fn parse_record(line: &str) -> Result<IoctlRecord, String> {
+    let mut fields = line.split_whitespace();
+    let mut next = |what: &str| fields.next().ok_or_else(|| format!("missing {what}"));
+    let ioctl = next("ioctl name")?.to_string();
+    let errno = next("errno")?
+        .parse()
+        .map_err(|_| "invalid errno".to_string())?;
+    let arg = unhex(next("argument")?)?;
+    let buffers = fields
+        .map(|field| {
+            let (name, bytes) = field
+                .split_once('=')
+                .ok_or_else(|| format!("invalid buffer {field:?}"))?;
+            Ok((name.to_string(), unhex(bytes)?))
+        })
+        .collect::<Result<_, String>>()?;
+    Ok(IoctlRecord {
+        ioctl,
+        errno,
+        arg,
+        buffers,
+    })
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut hex, byte| {
+        let _ = write!(hex, "{byte:02x}");
+        hex
+    })
+}
+
+fn unhex(hex: &str) -> Result<Vec<u8>, String> {
+    if !hex.len().is_multiple_of(2) {
+        return Err("odd number of hex digits".to_string());
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            hex.get(i..i + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                .ok_or_else(|| format!("invalid hex {hex:?}"))
+        })
+        .collect()
+}
+
+/// Fails with the errno of `record`, an `ioctl` record, if any.
+fn recorded_result(ioctl: &'static str, record: IoctlRecord) -> Result<IoctlRecord, MediaError> {
+    match record.errno {
+        0 => Ok(record),
+        errno => Err(MediaError::ioctl(ioctl, Errno::from_i32(errno))),
+    }
+}
+
+/// `link` in media-ctl syntax, with entities addressed by ID.
+fn link_request(link: &media_link_desc) -> LinkRequest {
+    let pad = |pad: &media_pad_desc| PadRef {
+        entity: EntityRef::Id(pad.entity.into()),
+        index: pad.index.into(),
+    };
+    LinkRequest {
+        source: pad(&link.source),
+        sink: pad(&link.sink),
+        flags: link.flags,
+    }
+}
+
+const LINK_DESC_FIELDS: usize = 7;
+
+/// The fields of `link` in the order of the [module documentation](self).
+fn link_desc_to_bytes(link: &media_link_desc) -> Vec<u8> {
+    [
+        link.source.entity,
+        link.source.index.into(),
+        link.source.flags,
+        link.sink.entity,
+        link.sink.index.into(),
+        link.sink.flags,
+        link.flags,
+    ]
+    .iter()
+    .flat_map(|field| field.to_le_bytes())
+    .collect()
+}
+
+fn link_desc_from_bytes(bytes: &[u8]) -> Result<media_link_desc, MediaError> {
+    if bytes.len() != LINK_DESC_FIELDS * size_of::<u32>() {
+        return Err(MediaError::InvalidRecording(format!(
+            "{} bytes recorded for a {LINK_DESC_FIELDS}-field link",
+            bytes.len()
+        )));
+    }
+    let mut fields = bytes
+        .chunks_exact(size_of::<u32>())
+        .map(|field| u32::from_le_bytes([field[0], field[1], field[2], field[3]]));
+    let mut field = || fields.next().unwrap_or_default();
+    let mut pad = || -> Result<media_pad_desc, MediaError> {
+        let entity = field();
+        let index = u16::try_from(field())
+            .map_err(|_| MediaError::InvalidRecording("pad index out of range".to_string()))?;
+        Ok(raw::pad_desc(entity, index, field()))
+    };
+    let (source, sink) = (pad()?, pad()?);
+    Ok(raw::link_desc(source, sink, field()))
+}
+
+fn recorded_struct<T: Pod>(bytes: &[u8]) -> Result<T, MediaError> {
+    raw::read(bytes).ok_or_else(|| recorded_size_error::<T>(bytes))
+}
+
+fn recorded_structs<T: Pod>(bytes: &[u8]) -> Result<Vec<T>, MediaError> {
+    raw::read_slice(bytes).ok_or_else(|| recorded_size_error::<T>(bytes))
+}
+
+fn recorded_size_error<T>(bytes: &[u8]) -> MediaError {
+    MediaError::InvalidRecording(format!(
+        "{} bytes recorded for {}-byte structs",
+        bytes.len(),
+        size_of::<T>()
+    ))
+}
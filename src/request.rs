@@ -0,0 +1,135 @@
+use std::{
+    os::fd::{AsFd, AsRawFd, BorrowedFd, IntoRawFd, OwnedFd, RawFd},
+    time::{Duration, Instant},
+};
+
+use nix::errno::Errno;
+use nix::libc::c_int;
+use nix::poll::{poll, PollFd, PollFlags};
+
+use crate::{raw, Feature, MediaDevice, MediaError};
+
+/// A media request allocated with `MEDIA_IOC_REQUEST_ALLOC`.
+///
+/// Controls and buffers are bound to the request by passing its fd to the
+/// V4L2 ioctls, see [`Request::fd`]. The request fd is closed when the value
+/// is dropped.
+#[derive(Debug)]
+pub struct Request {
+    fd: OwnedFd,
+}
+
+impl Request {
+    /// Borrows the request fd, e.g. to fill `request_fd` of `v4l2_buffer` or
+    /// `v4l2_ext_controls`.
+    pub fn fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+
+    /// Queues the request for processing (`MEDIA_REQUEST_IOC_QUEUE`).
+    pub fn queue(&self) -> Result<(), MediaError> {
+        raw::request_queue(self.fd.as_fd())
+            .map_err(|errno| MediaError::ioctl("MEDIA_REQUEST_IOC_QUEUE", errno))?;
+        Ok(())
+    }
+
+    /// Blocks until the request completes or `timeout` elapses. Returns
+    /// `false` on timeout. `None` waits indefinitely.
+    ///
+    /// Completion is signalled by the kernel as `POLLPRI` on the request fd.
+    pub fn wait(&self, timeout: Option<Duration>) -> Result<bool, MediaError> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            match self.poll_completion(poll_timeout(deadline)) {
+                Err(Errno::EINTR) => continue,
+                res => return res.map_err(MediaError::Poll),
+            }
+        }
+    }
+
+    /// Checks whether the request has completed without blocking.
+    pub fn is_done(&self) -> Result<bool, MediaError> {
+        self.poll_completion(0).map_err(MediaError::Poll)
+    }
+
+    fn poll_completion(&self, timeout_ms: c_int) -> Result<bool, Errno> {
+        let mut fds = [PollFd::new(&self.fd, PollFlags::POLLPRI)];
+        let ready = poll(&mut fds, timeout_ms)?;
+        Ok(ready > 0
+            && fds[0]
+                .revents()
+                .is_some_and(|revents| revents.contains(PollFlags::POLLPRI)))
+    }
+
+    /// Resets a completed request so it can be reused
+    /// (`MEDIA_REQUEST_IOC_REINIT`).
+    pub fn reinit(&self) -> Result<(), MediaError> {
+        raw::request_reinit(self.fd.as_fd())
+            .map_err(|errno| MediaError::ioctl("MEDIA_REQUEST_IOC_REINIT", errno))?;
+        Ok(())
+    }
+}
+
+impl AsFd for Request {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
+
+impl AsRawFd for Request {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+impl IntoRawFd for Request {
+    fn into_raw_fd(self) -> RawFd {
+        self.fd.into_raw_fd()
+    }
+}
+
+impl From<Request> for OwnedFd {
+    fn from(request: Request) -> OwnedFd {
+        request.fd
+    }
+}
+
+/// Milliseconds left until `deadline` as a `poll` timeout, `-1` for none.
+pub(crate) fn poll_timeout(deadline: Option<Instant>) -> c_int {
+    match deadline {
+        None => -1,
+        Some(deadline) => {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            // Round up so a sub-millisecond remainder doesn't turn into a busy poll.
+            remaining
+                .as_nanos()
+                .div_ceil(1_000_000)
+                .min(c_int::MAX as u128) as c_int
+        }
+    }
+}
+
+impl Request {
+    /// A request not bound to any device, backed by the read end of a pipe,
+    /// for backends that don't talk to the kernel. It can be held and
+    /// polled, but queueing or reinitializing it fails.
+    pub(crate) fn detached() -> Result<Request, Errno> {
+        Ok(Request {
+            fd: raw::closed_pipe()?,
+        })
+    }
+}
+
+impl MediaDevice {
+    pub fn alloc_request(&self) -> Result<Request, MediaError> {
+        let fd = raw::request_alloc(self.fd.as_fd()).map_err(|errno| {
+            if errno == Errno::ENOTTY {
+                if let Err(err) = self.require(Feature::RequestApi) {
+                    return err;
+                }
+            }
+            MediaError::ioctl("MEDIA_IOC_REQUEST_ALLOC", errno)
+        })?;
+        Ok(Request { fd })
+    }
+}
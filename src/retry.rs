@@ -0,0 +1,216 @@
+use std::{fmt, os::fd::AsFd, sync::Arc, thread, time::Duration};
+
+use nix::errno::Errno;
+
+use crate::{
+    plan, EntityId, LinkChange, LinkRequest, MediaDevice, MediaError, PadId, PipelineConfig,
+};
+
+/// A link setup refused with `EBUSY`, passed to the callback of a
+/// [`RetryPolicy`] before it is retried.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BusyRetry {
+    pub link: LinkRequest,
+    /// Attempts made so far, starting at 1.
+    pub attempt: u32,
+    /// How long the policy sleeps before the next attempt.
+    pub delay: Duration,
+}
+
+type OnBusy = dyn Fn(&BusyRetry) -> bool + Send + Sync;
+
+/// How link setups refused with `EBUSY`, while the pipeline streams, are
+/// retried. See [`MediaDevice::with_retry`].
+///
+/// The delay between attempts starts at [`RetryPolicy::delay`] and is
+/// multiplied by [`RetryPolicy::backoff`] after every attempt, up to
+/// [`RetryPolicy::max_delay`]. The default policy makes a single attempt.
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use mc_api::{LinkRequest, MediaDevice, RetryPolicy};
+///
+/// let device = MediaDevice::by_index(0)?;
+/// let policy = RetryPolicy::new(10)
+///     .delay(Duration::from_millis(20))
+///     .on_busy(|retry| {
+///         eprintln!("{} busy, attempt {}", retry.link, retry.attempt);
+///         true
+///     });
+/// device
+///     .with_retry(&policy)
+///     .apply_links(&LinkRequest::parse_list("'csi':1 -> 'isp':0 [1]")?)?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Clone)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    delay: Duration,
+    backoff: u32,
+    max_delay: Duration,
+    on_busy: Option<Arc<OnBusy>>,
+}
+
+impl RetryPolicy {
+    /// Makes up to `max_attempts` attempts per link setup, waiting 10 ms
+    /// before the first retry and doubling the delay up to a second.
+    pub fn new(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: max_attempts.max(1),
+            delay: Duration::from_millis(10),
+            backoff: 2,
+            max_delay: Duration::from_secs(1),
+            on_busy: None,
+        }
+    }
+
+    pub fn delay(mut self, delay: Duration) -> RetryPolicy {
+        self.delay = delay;
+        self
+    }
+
+    /// Factor the delay grows by after every attempt, `1` for a fixed delay.
+    pub fn backoff(mut self, backoff: u32) -> RetryPolicy {
+        self.backoff = backoff.max(1);
+        self
+    }
+
+    pub fn max_delay(mut self, max_delay: Duration) -> RetryPolicy {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Calls `on_busy` before every retry, e.g. for logging. Returning
+    /// `false` aborts, failing with the `EBUSY` error of the last attempt.
+    pub fn on_busy(
+        mut self,
+        on_busy: impl Fn(&BusyRetry) -> bool + Send + Sync + 'static,
+    ) -> RetryPolicy {
+        self.on_busy = Some(Arc::new(on_busy));
+        self
+    }
+
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// Runs `setup` until it succeeds, fails with anything but `EBUSY`, the
+    /// attempts are exhausted or the callback aborts.
+    pub(crate) fn run(
+        &self,
+        link: impl Fn() -> LinkRequest,
+        mut setup: impl FnMut() -> Result<(), MediaError>,
+    ) -> Result<(), MediaError> {
+        let mut delay = self.delay;
+        let mut attempt = 1;
+        loop {
+            match setup() {
+                Err(err) if err.errno() == Some(Errno::EBUSY) && attempt < self.max_attempts => {
+                    if let Some(on_busy) = &self.on_busy {
+                        if !on_busy(&BusyRetry {
+                            link: link(),
+                            attempt,
+                            delay,
+                        }) {
+                            return Err(err);
+                        }
+                    }
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!(link = %link(), attempt, ?delay, "link busy, retrying");
+                    thread::sleep(delay);
+                    delay = delay.saturating_mul(self.backoff).min(self.max_delay);
+                    attempt += 1;
+                }
+                res => return res,
+            }
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> RetryPolicy {
+        RetryPolicy::new(1)
+    }
+}
+
+impl fmt::Debug for RetryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RetryPolicy")
+            .field("max_attempts", &self.max_attempts)
+            .field("delay", &self.delay)
+            .field("backoff", &self.backoff)
+            .field("max_delay", &self.max_delay)
+            .field("on_busy", &self.on_busy.is_some())
+            .finish()
+    }
+}
+
+/// Link operations retrying setups refused with `EBUSY` as a
+/// [`RetryPolicy`] says. See [`MediaDevice::with_retry`].
+#[derive(Debug, Clone, Copy)]
+pub struct Retrying<'a> {
+    device: &'a MediaDevice,
+    policy: &'a RetryPolicy,
+}
+
+impl Retrying<'_> {
+    pub fn setup_link(
+        &self,
+        source_pad_id: PadId,
+        sink_pad_id: PadId,
+        flags: u32,
+    ) -> Result<(), MediaError> {
+        let graph = self.device.link_graph()?;
+        let steps = plan::plan_setup(&graph, source_pad_id, sink_pad_id, flags)?;
+        plan::execute(self.device.as_fd(), &graph, &steps, false, self.policy).map(drop)
+    }
+
+    pub fn apply(&self, config: &PipelineConfig) -> Result<Vec<LinkChange>, MediaError> {
+        self.apply_links(&config.link_requests())
+    }
+
+    pub fn apply_links(&self, links: &[LinkRequest]) -> Result<Vec<LinkChange>, MediaError> {
+        let graph = self.device.link_graph()?;
+        let steps = plan::plan_links(&graph, links)?;
+        plan::execute(self.device.as_fd(), &graph, &steps, false, self.policy)
+    }
+
+    /// The links changed so far are restored with the same policy if a link
+    /// setup fails, see [`MediaDevice::apply_links_transaction`].
+    pub fn apply_links_transaction(
+        &self,
+        links: &[LinkRequest],
+    ) -> Result<Vec<LinkChange>, MediaError> {
+        let graph = self.device.link_graph()?;
+        let steps = plan::plan_links(&graph, links)?;
+        plan::execute(self.device.as_fd(), &graph, &steps, true, self.policy)
+    }
+
+    pub fn enable_path(
+        &self,
+        from: EntityId,
+        to: EntityId,
+        disable_conflicting: bool,
+    ) -> Result<Vec<LinkChange>, MediaError> {
+        let graph = self.device.link_graph()?;
+        let steps = plan::plan_path(&graph, from, to, disable_conflicting)?;
+        plan::execute(self.device.as_fd(), &graph, &steps, false, self.policy)
+    }
+
+    pub fn reset_links(&self) -> Result<Vec<LinkChange>, MediaError> {
+        let graph = self.device.link_graph()?;
+        let steps = plan::plan_reset(&graph)?;
+        plan::execute(self.device.as_fd(), &graph, &steps, false, self.policy)
+    }
+}
+
+impl MediaDevice {
+    /// Runs link operations retrying the link setups refused with `EBUSY`,
+    /// e.g. while a pipeline is stopping, as `policy` says.
+    pub fn with_retry<'a>(&'a self, policy: &'a RetryPolicy) -> Retrying<'a> {
+        Retrying {
+            device: self,
+            policy,
+        }
+    }
+}
@@ -0,0 +1,309 @@
+use std::{collections::HashMap, fmt};
+
+use crate::{
+    matcher::glob_match, EntityFunction, EntityId, LinkFlags, MediaV2Entity, MediaV2Pad,
+    MediaV2Topology, PadId,
+};
+
+/// Expected shape of a topology, for checking boards against a golden
+/// description in CI, see [`check_topology`].
+///
+/// Entities and links are identified by entity name and pad index. Fields
+/// left unset are wildcards, so volatile values such as object IDs and the
+/// topology version only need to be given when they must match.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct TopologySpec {
+    pub topology_version: Option<u64>,
+    pub entities: Vec<EntitySpec>,
+    pub links: Vec<LinkSpec>,
+    /// Accept entities and data links the spec doesn't mention.
+    pub allow_extra: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct EntitySpec {
+    /// Entity name, may contain `*` and `?` wildcards.
+    pub name: String,
+    pub id: Option<u32>,
+    pub function: Option<EntityFunction>,
+    pub flags: Option<u32>,
+    /// Number of pads.
+    pub pads: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct LinkSpec {
+    /// Source entity name, may contain wildcards.
+    pub source: String,
+    pub source_pad: u32,
+    /// Sink entity name, may contain wildcards.
+    pub sink: String,
+    pub sink_pad: u32,
+    pub enabled: Option<bool>,
+    pub immutable: Option<bool>,
+}
+
+impl fmt::Display for LinkSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "\"{}\":{} -> \"{}\":{}",
+            self.source, self.source_pad, self.sink, self.sink_pad
+        )
+    }
+}
+
+/// A difference between a [`TopologySpec`] and an actual topology.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SpecMismatch {
+    TopologyVersion {
+        expected: u64,
+        actual: u64,
+    },
+    MissingEntity {
+        name: String,
+    },
+    UnexpectedEntity {
+        name: String,
+    },
+    EntityField {
+        name: String,
+        field: &'static str,
+        expected: String,
+        actual: String,
+    },
+    MissingLink {
+        link: String,
+    },
+    UnexpectedLink {
+        link: String,
+    },
+    LinkFlags {
+        link: String,
+        expected: String,
+        actual: String,
+    },
+}
+
+impl fmt::Display for SpecMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpecMismatch::TopologyVersion { expected, actual } => {
+                write!(f, "topology version is {actual}, expected {expected}")
+            }
+            SpecMismatch::MissingEntity { name } => write!(f, "missing entity {name:?}"),
+            SpecMismatch::UnexpectedEntity { name } => write!(f, "unexpected entity {name:?}"),
+            SpecMismatch::EntityField {
+                name,
+                field,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "entity {name:?} has {field} {actual}, expected {expected}"
+            ),
+            SpecMismatch::MissingLink { link } => write!(f, "missing link {link}"),
+            SpecMismatch::UnexpectedLink { link } => write!(f, "unexpected link {link}"),
+            SpecMismatch::LinkFlags {
+                link,
+                expected,
+                actual,
+            } => write!(f, "link {link} is {actual}, expected {expected}"),
+        }
+    }
+}
+
+impl TopologySpec {
+    #[cfg(feature = "json")]
+    pub fn from_json(json: &str) -> Result<TopologySpec, crate::MediaError> {
+        serde_json::from_str(json).map_err(crate::MediaError::Json)
+    }
+
+    #[cfg(feature = "toml")]
+    pub fn from_toml(toml: &str) -> Result<TopologySpec, crate::MediaError> {
+        toml::from_str(toml).map_err(crate::MediaError::Toml)
+    }
+}
+
+/// Compares `actual` against `expected`. An empty list means it matches.
+pub fn check_topology(expected: &TopologySpec, actual: &MediaV2Topology) -> Vec<SpecMismatch> {
+    let mut mismatches = Vec::new();
+
+    if let Some(version) = expected.topology_version {
+        if version != actual.topology_version {
+            mismatches.push(SpecMismatch::TopologyVersion {
+                expected: version,
+                actual: actual.topology_version,
+            });
+        }
+    }
+
+    let mut matched_entities = vec![false; actual.entities.len()];
+    for spec in &expected.entities {
+        let found: Vec<_> = actual
+            .entities
+            .iter()
+            .enumerate()
+            .filter(|(_, entity)| glob_match(&spec.name, &entity.name))
+            .collect();
+        if found.is_empty() {
+            mismatches.push(SpecMismatch::MissingEntity {
+                name: spec.name.clone(),
+            });
+        }
+        for (index, entity) in found {
+            matched_entities[index] = true;
+            check_entity(spec, entity, actual, &mut mismatches);
+        }
+    }
+    if !expected.allow_extra {
+        for (entity, matched) in actual.entities.iter().zip(matched_entities) {
+            if !matched {
+                mismatches.push(SpecMismatch::UnexpectedEntity {
+                    name: entity.name.to_string(),
+                });
+            }
+        }
+    }
+
+    let names: HashMap<EntityId, &str> = actual
+        .entities
+        .iter()
+        .map(|entity| (entity.id, &*entity.name))
+        .collect();
+    let pads: HashMap<PadId, &MediaV2Pad> = actual.pads.iter().map(|pad| (pad.id, pad)).collect();
+    let endpoint = |id: Option<PadId>| {
+        let pad = pads.get(&id?)?;
+        Some((names.get(&pad.entity_id).copied().unwrap_or(""), pad.index))
+    };
+    let data_links: Vec<_> = actual
+        .data_links()
+        .filter_map(|link| {
+            Some((
+                link,
+                endpoint(link.source_pad())?,
+                endpoint(link.sink_pad())?,
+            ))
+        })
+        .collect();
+
+    let mut matched_links = vec![false; data_links.len()];
+    for spec in &expected.links {
+        let mut found = false;
+        for (index, (link, source, sink)) in data_links.iter().enumerate() {
+            if !(glob_match(&spec.source, source.0)
+                && source.1 == spec.source_pad
+                && glob_match(&spec.sink, sink.0)
+                && sink.1 == spec.sink_pad)
+            {
+                continue;
+            }
+            found = true;
+            matched_links[index] = true;
+            let flags = link.link_flags();
+            let enabled = flags.contains(LinkFlags::ENABLED);
+            let immutable = flags.contains(LinkFlags::IMMUTABLE);
+            if spec.enabled.is_some_and(|e| e != enabled)
+                || spec.immutable.is_some_and(|i| i != immutable)
+            {
+                mismatches.push(SpecMismatch::LinkFlags {
+                    link: format_link(*source, *sink),
+                    expected: format_state(spec.enabled, spec.immutable),
+                    actual: format_state(Some(enabled), Some(immutable)),
+                });
+            }
+        }
+        if !found {
+            mismatches.push(SpecMismatch::MissingLink {
+                link: spec.to_string(),
+            });
+        }
+    }
+    if !expected.allow_extra {
+        for ((_, source, sink), matched) in data_links.iter().zip(matched_links) {
+            if !matched {
+                mismatches.push(SpecMismatch::UnexpectedLink {
+                    link: format_link(*source, *sink),
+                });
+            }
+        }
+    }
+
+    mismatches
+}
+
+/// Panics listing every mismatch if `actual` doesn't match `expected`.
+#[track_caller]
+pub fn assert_topology_matches(expected: &TopologySpec, actual: &MediaV2Topology) {
+    let mismatches = check_topology(expected, actual);
+    if !mismatches.is_empty() {
+        let lines: Vec<_> = mismatches.iter().map(|m| format!("  {m}")).collect();
+        panic!("topology doesn't match the spec:\n{}", lines.join("\n"));
+    }
+}
+
+fn check_entity(
+    spec: &EntitySpec,
+    entity: &MediaV2Entity,
+    topology: &MediaV2Topology,
+    mismatches: &mut Vec<SpecMismatch>,
+) {
+    let mut field = |field: &'static str, expected: Option<String>, actual: String| {
+        if let Some(expected) = expected {
+            if expected != actual {
+                mismatches.push(SpecMismatch::EntityField {
+                    name: entity.name.to_string(),
+                    field,
+                    expected,
+                    actual,
+                });
+            }
+        }
+    };
+    field(
+        "id",
+        spec.id.map(|id| id.to_string()),
+        entity.id.to_string(),
+    );
+    field(
+        "function",
+        spec.function.map(|f| f.to_string()),
+        entity.function.to_string(),
+    );
+    field(
+        "flags",
+        spec.flags.map(|flags| format!("{flags:#x}")),
+        format!("{:#x}", entity.flags),
+    );
+    let pads = topology
+        .pads
+        .iter()
+        .filter(|pad| pad.entity_id == entity.id)
+        .count();
+    field("pads", spec.pads.map(|n| n.to_string()), pads.to_string());
+}
+
+fn format_link(source: (&str, u32), sink: (&str, u32)) -> String {
+    format!("\"{}\":{} -> \"{}\":{}", source.0, source.1, sink.0, sink.1)
+}
+
+fn format_state(enabled: Option<bool>, immutable: Option<bool>) -> String {
+    let mut state = Vec::new();
+    match enabled {
+        Some(true) => state.push("enabled"),
+        Some(false) => state.push("disabled"),
+        None => {}
+    }
+    match immutable {
+        Some(true) => state.push("immutable"),
+        Some(false) => state.push("mutable"),
+        None => {}
+    }
+    state.join(", ")
+}
@@ -0,0 +1,812 @@
+use std::{
+    collections::{hash_map::Entry, BTreeMap, HashMap},
+    fmt,
+    fs::OpenOptions,
+    io,
+    os::fd::{AsFd, AsRawFd, BorrowedFd, OwnedFd, RawFd},
+    path::Path,
+};
+
+use bitflags::bitflags;
+use nix::errno::Errno;
+
+use crate::{
+    legacy, plan, raw,
+    subdev_ffi::{
+        self, v4l2_mbus_framefmt, v4l2_rect, v4l2_subdev_client_capability, v4l2_subdev_format,
+        v4l2_subdev_frame_interval_enum, v4l2_subdev_frame_size_enum, v4l2_subdev_mbus_code_enum,
+        v4l2_subdev_route, v4l2_subdev_routing, v4l2_subdev_selection,
+    },
+    EntityId, FormatRejection, LinkFlags, MbusCode, MediaDevice, MediaError, MediaGraph, MediaPath,
+    MediaV2Pad, PadFlags, PadId, Rect,
+};
+
+/// Which configuration of a subdevice an operation accesses.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Which {
+    /// The scratch configuration of the file handle, for negotiating formats
+    /// without touching the hardware.
+    Try,
+    /// The configuration the device streams with.
+    #[default]
+    Active,
+}
+
+impl Which {
+    fn to_ffi(self) -> u32 {
+        match self {
+            Which::Try => subdev_ffi::V4L2_SUBDEV_FORMAT_TRY,
+            Which::Active => subdev_ffi::V4L2_SUBDEV_FORMAT_ACTIVE,
+        }
+    }
+}
+
+/// A stream of a subdevice pad, by pad index and stream ID.
+///
+/// Pads of subdevices supporting the streams API multiplex several streams,
+/// e.g. the virtual channels of a CSI-2 link. Other pads only carry stream
+/// `0`, which is what a bare pad index converts to:
+///
+/// ```
+/// use mc_api::PadStream;
+///
+/// assert_eq!(PadStream::from(1), PadStream::new(1, 0));
+/// assert_eq!(PadStream::from((1, 2)).to_string(), "1/2");
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PadStream {
+    pub pad: u32,
+    pub stream: u32,
+}
+
+impl PadStream {
+    pub fn new(pad: u32, stream: u32) -> PadStream {
+        PadStream { pad, stream }
+    }
+}
+
+impl From<u32> for PadStream {
+    fn from(pad: u32) -> PadStream {
+        PadStream::new(pad, 0)
+    }
+}
+
+impl From<(u32, u32)> for PadStream {
+    fn from((pad, stream): (u32, u32)) -> PadStream {
+        PadStream::new(pad, stream)
+    }
+}
+
+/// `pad/stream`, as `media-ctl` writes it.
+impl fmt::Display for PadStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.pad, self.stream)
+    }
+}
+
+bitflags! {
+    /// Flags of a [`SubdevRoute`] (`V4L2_SUBDEV_ROUTE_FL_*`).
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct RouteFlags: u32 {
+        /// The route carries data. Inactive routes are only reported by
+        /// drivers with a fixed routing table.
+        const ACTIVE = subdev_ffi::V4L2_SUBDEV_ROUTE_FL_ACTIVE;
+    }
+}
+
+/// A route of a subdevice, from a stream of a sink pad to a stream of a
+/// source pad, `struct v4l2_subdev_route`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SubdevRoute {
+    pub sink: PadStream,
+    pub source: PadStream,
+    pub flags: RouteFlags,
+}
+
+impl SubdevRoute {
+    /// An active route.
+    pub fn new(sink: impl Into<PadStream>, source: impl Into<PadStream>) -> SubdevRoute {
+        SubdevRoute {
+            sink: sink.into(),
+            source: source.into(),
+            flags: RouteFlags::ACTIVE,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.flags.contains(RouteFlags::ACTIVE)
+    }
+
+    fn from_ffi(route: &v4l2_subdev_route) -> SubdevRoute {
+        SubdevRoute {
+            sink: PadStream::new(route.sink_pad, route.sink_stream),
+            source: PadStream::new(route.source_pad, route.source_stream),
+            flags: RouteFlags::from_bits_retain(route.flags),
+        }
+    }
+
+    fn to_ffi(self) -> v4l2_subdev_route {
+        v4l2_subdev_route {
+            sink_pad: self.sink.pad,
+            sink_stream: self.sink.stream,
+            source_pad: self.source.pad,
+            source_stream: self.source.stream,
+            flags: self.flags.bits(),
+            reserved: [0; 5],
+        }
+    }
+}
+
+/// `sink/stream -> source/stream [flags]`, as `media-ctl` writes it.
+impl fmt::Display for SubdevRoute {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} -> {} [{}]",
+            self.sink,
+            self.source,
+            self.flags.bits()
+        )
+    }
+}
+
+/// Rectangle of a pad addressed by a selection (`V4L2_SEL_TGT_*`).
+///
+/// Crop rectangles select the part of the image on a sink pad that is
+/// processed, compose rectangles where the processed image is placed, e.g.
+/// the output size of a scaler. The defaults, bounds and native size are
+/// read-only.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SelectionTarget {
+    Crop,
+    CropDefault,
+    CropBounds,
+    NativeSize,
+    Compose,
+    ComposeDefault,
+    ComposeBounds,
+    ComposePadded,
+}
+
+impl SelectionTarget {
+    fn to_ffi(self) -> u32 {
+        match self {
+            SelectionTarget::Crop => subdev_ffi::V4L2_SEL_TGT_CROP,
+            SelectionTarget::CropDefault => subdev_ffi::V4L2_SEL_TGT_CROP_DEFAULT,
+            SelectionTarget::CropBounds => subdev_ffi::V4L2_SEL_TGT_CROP_BOUNDS,
+            SelectionTarget::NativeSize => subdev_ffi::V4L2_SEL_TGT_NATIVE_SIZE,
+            SelectionTarget::Compose => subdev_ffi::V4L2_SEL_TGT_COMPOSE,
+            SelectionTarget::ComposeDefault => subdev_ffi::V4L2_SEL_TGT_COMPOSE_DEFAULT,
+            SelectionTarget::ComposeBounds => subdev_ffi::V4L2_SEL_TGT_COMPOSE_BOUNDS,
+            SelectionTarget::ComposePadded => subdev_ffi::V4L2_SEL_TGT_COMPOSE_PADDED,
+        }
+    }
+}
+
+bitflags! {
+    /// Constraints on how the driver may adjust a selection rectangle
+    /// (`V4L2_SEL_FLAG_*`).
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct SelectionFlags: u32 {
+        /// The rectangle may only grow.
+        const GE = subdev_ffi::V4L2_SEL_FLAG_GE;
+        /// The rectangle may only shrink.
+        const LE = subdev_ffi::V4L2_SEL_FLAG_LE;
+        /// Don't propagate the change to the other pads of the subdevice.
+        const KEEP_CONFIG = subdev_ffi::V4L2_SEL_FLAG_KEEP_CONFIG;
+    }
+}
+
+fn rect_from_ffi(rect: &v4l2_rect) -> Rect {
+    Rect {
+        left: rect.left,
+        top: rect.top,
+        width: rect.width,
+        height: rect.height,
+    }
+}
+
+fn rect_to_ffi(rect: &Rect) -> v4l2_rect {
+    v4l2_rect {
+        left: rect.left,
+        top: rect.top,
+        width: rect.width,
+        height: rect.height,
+    }
+}
+
+/// Media bus format of a subdevice pad, `struct v4l2_mbus_framefmt`.
+///
+/// The enumerated fields keep the raw V4L2 values, `0` meaning the driver's
+/// default where the kernel defines one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SubdevFormat {
+    pub width: u32,
+    pub height: u32,
+    /// `MEDIA_BUS_FMT_*` code.
+    pub code: u32,
+    /// `enum v4l2_field`.
+    pub field: u32,
+    /// `enum v4l2_colorspace`.
+    pub colorspace: u32,
+    /// `enum v4l2_ycbcr_encoding`, or `enum v4l2_hsv_encoding` for HSV
+    /// formats.
+    pub ycbcr_enc: u16,
+    /// `enum v4l2_quantization`.
+    pub quantization: u16,
+    /// `enum v4l2_xfer_func`.
+    pub xfer_func: u16,
+    /// `V4L2_MBUS_FRAMEFMT_*` flags.
+    pub flags: u16,
+}
+
+impl SubdevFormat {
+    /// A format with the other fields left to the driver's defaults. `code`
+    /// is a [`MbusCode`] or its raw value.
+    pub fn new(code: impl Into<u32>, width: u32, height: u32) -> SubdevFormat {
+        SubdevFormat {
+            code: code.into(),
+            width,
+            height,
+            ..SubdevFormat::default()
+        }
+    }
+
+    pub fn mbus_code(&self) -> MbusCode {
+        self.code.into()
+    }
+
+    fn from_ffi(format: &v4l2_mbus_framefmt) -> SubdevFormat {
+        SubdevFormat {
+            width: format.width,
+            height: format.height,
+            code: format.code,
+            field: format.field,
+            colorspace: format.colorspace,
+            ycbcr_enc: format.ycbcr_enc,
+            quantization: format.quantization,
+            xfer_func: format.xfer_func,
+            flags: format.flags,
+        }
+    }
+
+    fn to_ffi(self) -> v4l2_mbus_framefmt {
+        v4l2_mbus_framefmt {
+            width: self.width,
+            height: self.height,
+            code: self.code,
+            field: self.field,
+            colorspace: self.colorspace,
+            ycbcr_enc: self.ycbcr_enc,
+            quantization: self.quantization,
+            xfer_func: self.xfer_func,
+            flags: self.flags,
+            reserved: [0; 10],
+        }
+    }
+}
+
+/// `CODE/WIDTHxHEIGHT`, as `media-ctl` writes it, e.g. `SRGGB10_1X10/3280x2464`.
+impl fmt::Display for SubdevFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.mbus_code().name() {
+            Some(name) => f.write_str(name.trim_start_matches("MEDIA_BUS_FMT_"))?,
+            None => write!(f, "{:#x}", self.code)?,
+        }
+        write!(f, "/{}x{}", self.width, self.height)
+    }
+}
+
+/// Frame sizes a pad supports for a media bus code, `struct
+/// v4l2_subdev_frame_size_enum`.
+///
+/// Drivers report either a single size, with equal minimum and maximum, or
+/// a range in which they adjust any size to one they support.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FrameSizeRange {
+    pub min_width: u32,
+    pub max_width: u32,
+    pub min_height: u32,
+    pub max_height: u32,
+}
+
+impl FrameSizeRange {
+    pub fn is_discrete(&self) -> bool {
+        self.min_width == self.max_width && self.min_height == self.max_height
+    }
+
+    pub fn contains(&self, width: u32, height: u32) -> bool {
+        (self.min_width..=self.max_width).contains(&width)
+            && (self.min_height..=self.max_height).contains(&height)
+    }
+}
+
+/// An open V4L2 subdevice node (`/dev/v4l-subdevN`), configuring the pads
+/// of the entity it controls.
+///
+/// ```no_run
+/// use mc_api::{MediaDevice, MediaGraph, Subdev, Which};
+///
+/// let graph = MediaGraph::new(MediaDevice::by_index(0)?.topology()?);
+/// let sensor = graph.entity_by_name("imx219 1-0010").unwrap();
+/// let subdev = Subdev::for_entity(&graph, sensor.id())?;
+/// let format = subdev.get_fmt(0, Which::Active)?;
+/// println!("{}x{}", format.width, format.height);
+/// # Ok::<(), mc_api::MediaError>(())
+/// ```
+#[derive(Debug)]
+pub struct Subdev {
+    fd: OwnedFd,
+}
+
+impl Subdev {
+    /// Opens the node read-write if permitted, read-only otherwise, like
+    /// [`crate::OpenMode::Auto`].
+    pub fn open(path: impl AsRef<Path>) -> Result<Subdev, MediaError> {
+        let path = path.as_ref();
+        let open = |write| OpenOptions::new().read(true).write(write).open(path);
+        let file = match open(true) {
+            Err(err)
+                if err.kind() == io::ErrorKind::PermissionDenied
+                    || err.raw_os_error() == Some(Errno::EROFS as i32) =>
+            {
+                open(false)
+            }
+            res => res,
+        }
+        .map_err(|source| MediaError::Open {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Ok(Subdev::from_fd(file.into()))
+    }
+
+    /// Opens the subdevice node of `entity`, found through its
+    /// `MEDIA_INTF_T_V4L_SUBDEV` interface. Fails with
+    /// [`MediaError::NoSubdevNode`] if the entity has none, or its node isn't
+    /// registered.
+    pub fn for_entity(graph: &MediaGraph, entity: EntityId) -> Result<Subdev, MediaError> {
+        let path = graph
+            .interfaces(entity)
+            .filter(|intf| intf.intf_type().is_v4l_subdev())
+            .find_map(|intf| intf.devnode()?.path())
+            .ok_or(MediaError::NoSubdevNode(entity))?;
+        Subdev::open(path)
+    }
+
+    pub fn from_fd(fd: OwnedFd) -> Subdev {
+        Subdev { fd }
+    }
+
+    /// `VIDIOC_SUBDEV_G_FMT`: the format of `pad`, a pad index or a
+    /// [`PadStream`].
+    pub fn get_fmt(
+        &self,
+        pad: impl Into<PadStream>,
+        which: Which,
+    ) -> Result<SubdevFormat, MediaError> {
+        let pad = pad.into();
+        let mut format: v4l2_subdev_format = raw::zeroed();
+        format.which = which.to_ffi();
+        format.pad = pad.pad;
+        format.stream = pad.stream;
+        raw::subdev_g_fmt(self.fd.as_fd(), &mut format)
+            .map_err(|errno| MediaError::ioctl("VIDIOC_SUBDEV_G_FMT", errno))?;
+        Ok(SubdevFormat::from_ffi(&format.format))
+    }
+
+    /// `VIDIOC_SUBDEV_S_FMT`: sets the format of `pad`. Drivers adjust what
+    /// they don't support to the closest match rather than failing, the
+    /// format actually set is returned.
+    pub fn set_fmt(
+        &self,
+        pad: impl Into<PadStream>,
+        which: Which,
+        format: &SubdevFormat,
+    ) -> Result<SubdevFormat, MediaError> {
+        let pad = pad.into();
+        let mut subdev_format: v4l2_subdev_format = raw::zeroed();
+        subdev_format.which = which.to_ffi();
+        subdev_format.pad = pad.pad;
+        subdev_format.stream = pad.stream;
+        subdev_format.format = format.to_ffi();
+        raw::subdev_s_fmt(self.fd.as_fd(), &mut subdev_format)
+            .map_err(|errno| MediaError::ioctl("VIDIOC_SUBDEV_S_FMT", errno))?;
+        Ok(SubdevFormat::from_ffi(&subdev_format.format))
+    }
+
+    /// `VIDIOC_SUBDEV_G_SELECTION`: the `target` rectangle of `pad`.
+    pub fn get_selection(
+        &self,
+        pad: impl Into<PadStream>,
+        which: Which,
+        target: SelectionTarget,
+    ) -> Result<Rect, MediaError> {
+        let pad = pad.into();
+        let mut selection: v4l2_subdev_selection = raw::zeroed();
+        selection.which = which.to_ffi();
+        selection.pad = pad.pad;
+        selection.stream = pad.stream;
+        selection.target = target.to_ffi();
+        raw::subdev_g_selection(self.fd.as_fd(), &mut selection)
+            .map_err(|errno| MediaError::ioctl("VIDIOC_SUBDEV_G_SELECTION", errno))?;
+        Ok(rect_from_ffi(&selection.r))
+    }
+
+    /// `VIDIOC_SUBDEV_S_SELECTION`: sets the `target` rectangle of `pad`,
+    /// returning the rectangle the driver adjusted it to within `flags`.
+    pub fn set_selection(
+        &self,
+        pad: impl Into<PadStream>,
+        which: Which,
+        target: SelectionTarget,
+        rect: &Rect,
+        flags: SelectionFlags,
+    ) -> Result<Rect, MediaError> {
+        let pad = pad.into();
+        let mut selection: v4l2_subdev_selection = raw::zeroed();
+        selection.which = which.to_ffi();
+        selection.pad = pad.pad;
+        selection.stream = pad.stream;
+        selection.target = target.to_ffi();
+        selection.flags = flags.bits();
+        selection.r = rect_to_ffi(rect);
+        raw::subdev_s_selection(self.fd.as_fd(), &mut selection)
+            .map_err(|errno| MediaError::ioctl("VIDIOC_SUBDEV_S_SELECTION", errno))?;
+        Ok(rect_from_ffi(&selection.r))
+    }
+
+    /// `VIDIOC_SUBDEV_ENUM_MBUS_CODE`: the media bus codes `pad` supports,
+    /// in the driver's order of preference.
+    pub fn enum_mbus_codes(
+        &self,
+        pad: impl Into<PadStream>,
+        which: Which,
+    ) -> Result<Vec<MbusCode>, MediaError> {
+        let pad = pad.into();
+        enumerate("VIDIOC_SUBDEV_ENUM_MBUS_CODE", |index| {
+            let mut code: v4l2_subdev_mbus_code_enum = raw::zeroed();
+            code.pad = pad.pad;
+            code.stream = pad.stream;
+            code.index = index;
+            code.which = which.to_ffi();
+            raw::subdev_enum_mbus_code(self.fd.as_fd(), &mut code)?;
+            Ok(MbusCode::from(code.code))
+        })
+    }
+
+    /// `VIDIOC_SUBDEV_ENUM_FRAME_SIZE`: the frame sizes `pad` supports for
+    /// `code`.
+    pub fn enum_frame_sizes(
+        &self,
+        pad: impl Into<PadStream>,
+        which: Which,
+        code: MbusCode,
+    ) -> Result<Vec<FrameSizeRange>, MediaError> {
+        let pad = pad.into();
+        enumerate("VIDIOC_SUBDEV_ENUM_FRAME_SIZE", |index| {
+            let mut size: v4l2_subdev_frame_size_enum = raw::zeroed();
+            size.pad = pad.pad;
+            size.stream = pad.stream;
+            size.index = index;
+            size.code = code.into();
+            size.which = which.to_ffi();
+            raw::subdev_enum_frame_size(self.fd.as_fd(), &mut size)?;
+            Ok(FrameSizeRange {
+                min_width: size.min_width,
+                max_width: size.max_width,
+                min_height: size.min_height,
+                max_height: size.max_height,
+            })
+        })
+    }
+
+    /// `VIDIOC_SUBDEV_ENUM_FRAME_INTERVAL`: the frame intervals `pad`
+    /// supports for `code` at `width`x`height`, as `(numerator, denominator)`
+    /// in seconds.
+    pub fn enum_frame_intervals(
+        &self,
+        pad: impl Into<PadStream>,
+        which: Which,
+        code: MbusCode,
+        width: u32,
+        height: u32,
+    ) -> Result<Vec<(u32, u32)>, MediaError> {
+        let pad = pad.into();
+        enumerate("VIDIOC_SUBDEV_ENUM_FRAME_INTERVAL", |index| {
+            let mut interval: v4l2_subdev_frame_interval_enum = raw::zeroed();
+            interval.pad = pad.pad;
+            interval.stream = pad.stream;
+            interval.index = index;
+            interval.code = code.into();
+            interval.width = width;
+            interval.height = height;
+            interval.which = which.to_ffi();
+            raw::subdev_enum_frame_interval(self.fd.as_fd(), &mut interval)?;
+            Ok((interval.interval.numerator, interval.interval.denominator))
+        })
+    }
+
+    /// `VIDIOC_SUBDEV_S_CLIENT_CAP`: opts this file handle into the streams
+    /// API, which the routing ioctls and non-zero stream IDs require. Returns
+    /// whether the kernel granted it, `false` if it was built without stream
+    /// support.
+    pub fn enable_streams(&self) -> Result<bool, MediaError> {
+        let mut cap = v4l2_subdev_client_capability {
+            capabilities: subdev_ffi::V4L2_SUBDEV_CLIENT_CAP_STREAMS,
+        };
+        raw::subdev_s_client_cap(self.fd.as_fd(), &mut cap)
+            .map_err(|errno| MediaError::ioctl("VIDIOC_SUBDEV_S_CLIENT_CAP", errno))?;
+        Ok(cap.capabilities & subdev_ffi::V4L2_SUBDEV_CLIENT_CAP_STREAMS != 0)
+    }
+
+    /// `VIDIOC_SUBDEV_G_ROUTING`: the routing table of the subdevice. Needs
+    /// [`Subdev::enable_streams`], fails with
+    /// [`MediaError::UnsupportedKernel`] for subdevices without routing.
+    pub fn get_routing(&self, which: Which) -> Result<Vec<SubdevRoute>, MediaError> {
+        let mut capacity = ROUTES_CAPACITY;
+        for _ in 0..MAX_ATTEMPTS {
+            let mut routes: Vec<v4l2_subdev_route> = raw::zeroed_vec(capacity);
+            let mut routing: v4l2_subdev_routing = raw::zeroed();
+            routing.which = which.to_ffi();
+            match raw::subdev_g_routing(self.fd.as_fd(), &mut routing, &mut routes) {
+                Ok(()) => {
+                    routes.truncate(routing.num_routes as usize);
+                    return Ok(routes.iter().map(SubdevRoute::from_ffi).collect());
+                }
+                // The table grew past the buffer, `num_routes` is its size.
+                Err(Errno::ENOSPC) => capacity = routing.num_routes as usize,
+                Err(errno) => return Err(MediaError::ioctl("VIDIOC_SUBDEV_G_ROUTING", errno)),
+            }
+        }
+        Err(MediaError::ioctl("VIDIOC_SUBDEV_G_ROUTING", Errno::ENOSPC))
+    }
+
+    /// `VIDIOC_SUBDEV_S_ROUTING`: replaces the routing table, resetting the
+    /// formats and selections of the routed streams. Drivers may adjust the
+    /// table, the one actually set is returned.
+    pub fn set_routing(
+        &self,
+        which: Which,
+        routes: &[SubdevRoute],
+    ) -> Result<Vec<SubdevRoute>, MediaError> {
+        let mut buffer: Vec<v4l2_subdev_route> = routes.iter().map(|r| r.to_ffi()).collect();
+        let mut routing: v4l2_subdev_routing = raw::zeroed();
+        routing.which = which.to_ffi();
+        match raw::subdev_s_routing(self.fd.as_fd(), &mut routing, &mut buffer) {
+            Ok(()) => {}
+            // The table was set, but the driver added routes that didn't fit
+            // in the buffer.
+            Err(Errno::ENOSPC) => return self.get_routing(which),
+            Err(errno) => return Err(MediaError::ioctl("VIDIOC_SUBDEV_S_ROUTING", errno)),
+        }
+        buffer.truncate(routing.num_routes as usize);
+        Ok(buffer.iter().map(SubdevRoute::from_ffi).collect())
+    }
+}
+
+/// Calls `get` with indexes counting up from 0 until it fails with `EINVAL`,
+/// which ends the enumeration ioctls, or [`ENUMERATION_LIMIT`] items were
+/// read.
+fn enumerate<T>(
+    ioctl: &'static str,
+    mut get: impl FnMut(u32) -> nix::Result<T>,
+) -> Result<Vec<T>, MediaError> {
+    let mut items = Vec::new();
+    for index in 0..ENUMERATION_LIMIT {
+        match get(index) {
+            Ok(item) => items.push(item),
+            Err(Errno::EINVAL) => return Ok(items),
+            Err(errno) => return Err(MediaError::ioctl(ioctl, errno)),
+        }
+    }
+    Err(MediaError::EnumerationLimit {
+        ioctl,
+        limit: ENUMERATION_LIMIT,
+    })
+}
+
+/// Items [`enumerate`] reads before giving up on a driver that never ends
+/// the enumeration.
+const ENUMERATION_LIMIT: u32 = 4096;
+
+/// Routes read by the first `VIDIOC_SUBDEV_G_ROUTING` attempt.
+const ROUTES_CAPACITY: usize = 16;
+const MAX_ATTEMPTS: usize = 4;
+
+impl AsFd for Subdev {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
+
+impl AsRawFd for Subdev {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+impl From<OwnedFd> for Subdev {
+    fn from(fd: OwnedFd) -> Subdev {
+        Subdev::from_fd(fd)
+    }
+}
+
+impl From<Subdev> for OwnedFd {
+    fn from(subdev: Subdev) -> OwnedFd {
+        subdev.fd
+    }
+}
+
+impl MediaGraph {
+    /// Active routing table of `entity`, only present in graphs built by
+    /// [`MediaDevice::graph_with_routing`] or given one with
+    /// [`MediaGraph::set_routing`].
+    pub fn routing(&self, entity: EntityId) -> Option<&[SubdevRoute]> {
+        self.routing.get(&entity).map(Vec::as_slice)
+    }
+
+    /// Records the routing table of `entity`, e.g. for planning a
+    /// configuration offline.
+    pub fn set_routing(&mut self, entity: EntityId, routes: Vec<SubdevRoute>) {
+        self.routing.insert(entity, routes);
+    }
+
+    /// Follows `stream` of `pad` downstream: over the enabled data link of
+    /// each source pad, keeping the stream ID, and through the active route
+    /// of each entity from its sink pad to a source pad.
+    ///
+    /// The path starts with `(pad, stream)` and ends where no enabled link or
+    /// active route continues it. Entities without a routing table end it
+    /// too, as where their streams go isn't known.
+    pub fn stream_path(&self, pad: PadId, stream: u32) -> Vec<(PadId, u32)> {
+        let mut path = vec![(pad, stream)];
+        let mut current = (pad, stream);
+        while let Some(next) = self.next_stream_hop(current.0, current.1) {
+            if path.contains(&next) {
+                break;
+            }
+            path.push(next);
+            current = next;
+        }
+        path
+    }
+
+    /// Sets `format` on every pad along `path`, from the source pad of the
+    /// first hop to the sink pad of the last, as bring-up scripts do with
+    /// `media-ctl -V`. The whole path is first negotiated on the `TRY`
+    /// configuration, so a format refused half-way leaves the hardware
+    /// untouched, then set on the `ACTIVE` one. Returns the formats set.
+    ///
+    /// Each pad receives the format the previous one was set to, including
+    /// the colorimetry the driver filled in. A pad fails with
+    /// [`MediaError::FormatRejected`] if the ioctl fails or the driver
+    /// changes the media bus code or frame size, so paths through scalers or
+    /// format converters need their pads set one by one. Entities without a
+    /// subdevice node, like the video device at the end of a capture path,
+    /// are skipped.
+    ///
+    /// Pads are addressed by index, which `MEDIA_IOC_G_TOPOLOGY` of kernels
+    /// older than 4.19 doesn't report: on those, the graph must come from
+    /// [`MediaDevice::graph_with_legacy`](crate::MediaDevice::graph_with_legacy),
+    /// or this fails with [`MediaError::MalformedTopology`].
+    pub fn propagate_format(
+        &self,
+        path: &MediaPath,
+        format: &SubdevFormat,
+    ) -> Result<Vec<(PadId, SubdevFormat)>, MediaError> {
+        if !legacy::has_pad_indexes(self.topology()) {
+            return Err(MediaError::MalformedTopology(
+                "pads of an entity share an index".to_string(),
+            ));
+        }
+        // TRY formats belong to the file handle, the nodes stay open across
+        // both passes.
+        let mut subdevs: HashMap<EntityId, Option<Subdev>> = HashMap::new();
+        let mut pads = Vec::new();
+        for hop in &path.hops {
+            for (entity, pad) in [
+                (hop.source_entity, hop.source_pad),
+                (hop.sink_entity, hop.sink_pad),
+            ] {
+                if let Entry::Vacant(entry) = subdevs.entry(entity) {
+                    entry.insert(match Subdev::for_entity(self, entity) {
+                        Ok(subdev) => Some(subdev),
+                        Err(MediaError::NoSubdevNode(_)) => None,
+                        Err(err) => return Err(err),
+                    });
+                }
+                if subdevs[&entity].is_some() {
+                    pads.push(self.pad(pad).ok_or(MediaError::UnknownPad(pad))?);
+                }
+            }
+        }
+        self.set_formats(&subdevs, &pads, Which::Try, format)?;
+        self.set_formats(&subdevs, &pads, Which::Active, format)
+    }
+
+    fn set_formats(
+        &self,
+        subdevs: &HashMap<EntityId, Option<Subdev>>,
+        pads: &[&MediaV2Pad],
+        which: Which,
+        format: &SubdevFormat,
+    ) -> Result<Vec<(PadId, SubdevFormat)>, MediaError> {
+        let mut current = *format;
+        let mut set = Vec::with_capacity(pads.len());
+        for pad in pads {
+            let rejected = |cause| MediaError::FormatRejected {
+                pad: plan::pad_ref(self, pad),
+                which,
+                format: current,
+                cause,
+            };
+            let subdev = subdevs[&pad.entity_id]
+                .as_ref()
+                .expect("only pads of subdevices are set");
+            let applied = subdev
+                .set_fmt(pad.index, which, &current)
+                .map_err(|err| rejected(FormatRejection::Failed(Box::new(err))))?;
+            if (applied.code, applied.width, applied.height)
+                != (current.code, current.width, current.height)
+            {
+                return Err(rejected(FormatRejection::Adjusted(applied)));
+            }
+            current = applied;
+            set.push((pad.id, applied));
+        }
+        Ok(set)
+    }
+
+    fn next_stream_hop(&self, pad_id: PadId, stream: u32) -> Option<(PadId, u32)> {
+        let pad = self.pad(pad_id)?;
+        if pad.pad_flags().contains(PadFlags::SOURCE) {
+            let link = self.links(pad_id).find(|link| {
+                link.source_pad() == Some(pad_id) && link.link_flags().contains(LinkFlags::ENABLED)
+            })?;
+            return Some((link.sink_pad()?, stream));
+        }
+        let route = self
+            .routing(pad.entity_id)?
+            .iter()
+            .find(|route| route.is_active() && route.sink == PadStream::new(pad.index, stream))?;
+        let source = self.pad_by_index(pad.entity_id, route.source.pad)?;
+        Some((source.id, route.source.stream))
+    }
+}
+
+impl MediaDevice {
+    /// The graph with the active routing table of every subdevice supporting
+    /// the streams API, see [`MediaGraph::routing`]. Entities without a
+    /// subdevice node or routing support are left without one.
+    pub fn graph_with_routing(&self) -> Result<MediaGraph, MediaError> {
+        let mut graph = MediaGraph::new(self.topology()?);
+        let mut routing = BTreeMap::new();
+        for entity in &graph.topology().entities {
+            let subdev = match Subdev::for_entity(&graph, entity.id) {
+                Ok(subdev) => subdev,
+                Err(MediaError::NoSubdevNode(_)) => continue,
+                Err(err) => return Err(err),
+            };
+            match subdev.enable_streams() {
+                Ok(true) => {}
+                Ok(false) | Err(MediaError::UnsupportedKernel { .. }) => continue,
+                Err(err) => return Err(err),
+            }
+            match subdev.get_routing(Which::Active) {
+                Ok(routes) => {
+                    routing.insert(entity.id, routes);
+                }
+                Err(MediaError::UnsupportedKernel { .. }) => {}
+                Err(err) => return Err(err),
+            }
+        }
+        graph.routing = routing;
+        Ok(graph)
+    }
+}
@@ -0,0 +1,159 @@
+//! Structs of the V4L2 subdevice UAPI (`linux/v4l2-subdev.h` and
+//! `linux/v4l2-mediabus.h`), in the layout of kernel 6.3 and later, which
+//! added the stream fields in place of reserved words.
+#![allow(non_camel_case_types)]
+
+pub const V4L2_SUBDEV_FORMAT_TRY: u32 = 0;
+pub const V4L2_SUBDEV_FORMAT_ACTIVE: u32 = 1;
+
+pub const V4L2_SEL_TGT_CROP: u32 = 0x0000;
+pub const V4L2_SEL_TGT_CROP_DEFAULT: u32 = 0x0001;
+pub const V4L2_SEL_TGT_CROP_BOUNDS: u32 = 0x0002;
+pub const V4L2_SEL_TGT_NATIVE_SIZE: u32 = 0x0003;
+pub const V4L2_SEL_TGT_COMPOSE: u32 = 0x0100;
+pub const V4L2_SEL_TGT_COMPOSE_DEFAULT: u32 = 0x0101;
+pub const V4L2_SEL_TGT_COMPOSE_BOUNDS: u32 = 0x0102;
+pub const V4L2_SEL_TGT_COMPOSE_PADDED: u32 = 0x0103;
+
+pub const V4L2_SEL_FLAG_GE: u32 = 0x0000_0001;
+pub const V4L2_SEL_FLAG_LE: u32 = 0x0000_0002;
+pub const V4L2_SEL_FLAG_KEEP_CONFIG: u32 = 0x0000_0004;
+
+pub const V4L2_SUBDEV_ROUTE_FL_ACTIVE: u32 = 0x0000_0001;
+
+pub const V4L2_SUBDEV_CLIENT_CAP_STREAMS: u64 = 1 << 0;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct v4l2_rect {
+    pub left: i32,
+    pub top: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct v4l2_mbus_framefmt {
+    pub width: u32,
+    pub height: u32,
+    pub code: u32,
+    pub field: u32,
+    pub colorspace: u32,
+    /// Union with `hsv_enc`.
+    pub ycbcr_enc: u16,
+    pub quantization: u16,
+    pub xfer_func: u16,
+    pub flags: u16,
+    pub reserved: [u16; 10],
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct v4l2_subdev_format {
+    pub which: u32,
+    pub pad: u32,
+    pub format: v4l2_mbus_framefmt,
+    pub stream: u32,
+    pub reserved: [u32; 7],
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct v4l2_subdev_selection {
+    pub which: u32,
+    pub pad: u32,
+    pub target: u32,
+    pub flags: u32,
+    pub r: v4l2_rect,
+    pub stream: u32,
+    pub reserved: [u32; 7],
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct v4l2_subdev_mbus_code_enum {
+    pub pad: u32,
+    pub index: u32,
+    pub code: u32,
+    pub which: u32,
+    pub flags: u32,
+    pub stream: u32,
+    pub reserved: [u32; 6],
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct v4l2_subdev_frame_size_enum {
+    pub index: u32,
+    pub pad: u32,
+    pub code: u32,
+    pub min_width: u32,
+    pub max_width: u32,
+    pub min_height: u32,
+    pub max_height: u32,
+    pub which: u32,
+    pub stream: u32,
+    pub reserved: [u32; 7],
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct v4l2_fract {
+    pub numerator: u32,
+    pub denominator: u32,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct v4l2_subdev_frame_interval_enum {
+    pub index: u32,
+    pub pad: u32,
+    pub code: u32,
+    pub width: u32,
+    pub height: u32,
+    pub interval: v4l2_fract,
+    pub which: u32,
+    pub stream: u32,
+    pub reserved: [u32; 7],
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct v4l2_subdev_route {
+    pub sink_pad: u32,
+    pub sink_stream: u32,
+    pub source_pad: u32,
+    pub source_stream: u32,
+    pub flags: u32,
+    pub reserved: [u32; 5],
+}
+
+/// The layout of kernel 6.10, which enabled the streams API for userspace
+/// and split the route count into `len_routes` and `num_routes`.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct v4l2_subdev_routing {
+    pub which: u32,
+    pub len_routes: u32,
+    pub routes: u64,
+    pub num_routes: u32,
+    pub reserved: [u32; 11],
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct v4l2_subdev_client_capability {
+    pub capabilities: u64,
+}
+
+const _: () = {
+    assert!(std::mem::size_of::<v4l2_mbus_framefmt>() == 48);
+    assert!(std::mem::size_of::<v4l2_subdev_format>() == 88);
+    assert!(std::mem::size_of::<v4l2_subdev_selection>() == 64);
+    assert!(std::mem::size_of::<v4l2_subdev_mbus_code_enum>() == 48);
+    assert!(std::mem::size_of::<v4l2_subdev_frame_size_enum>() == 64);
+    assert!(std::mem::size_of::<v4l2_subdev_frame_interval_enum>() == 64);
+    assert!(std::mem::size_of::<v4l2_subdev_route>() == 40);
+    assert!(std::mem::size_of::<v4l2_subdev_routing>() == 64);
+};
@@ -0,0 +1,130 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    discovery::dev_media_paths, EntityId, MediaDevice, MediaDeviceInfo, MediaError, MediaGraph,
+    MediaV2Entity,
+};
+
+/// An object ID qualified by the index of the device it belongs to within a
+/// [`SystemGraph`]. Object IDs are only unique per media device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SystemId<T> {
+    pub device: usize,
+    pub id: T,
+}
+
+/// One media device loaded into a [`SystemGraph`].
+#[derive(Debug, Clone)]
+pub struct SystemDevice {
+    pub path: PathBuf,
+    pub info: MediaDeviceInfo,
+    pub graph: MediaGraph,
+}
+
+impl SystemDevice {
+    /// Bus type from `bus_info`, e.g. `platform`, `usb` or `pci`.
+    pub fn bus(&self) -> String {
+        let bus_info = &self.info.bus_info;
+        let end = bus_info.find([':', '-']).unwrap_or(bus_info.len());
+        bus_info[..end].to_ascii_lowercase()
+    }
+}
+
+/// Topologies of several media devices, for queries across the whole system.
+#[derive(Debug, Clone, Default)]
+pub struct SystemGraph {
+    devices: Vec<SystemDevice>,
+}
+
+impl SystemGraph {
+    pub fn new() -> SystemGraph {
+        SystemGraph::default()
+    }
+
+    /// Loads every `/dev/mediaN` node.
+    pub fn load_all() -> Result<SystemGraph, MediaError> {
+        SystemGraph::load(dev_media_paths()?)
+    }
+
+    pub fn load<I, P>(paths: I) -> Result<SystemGraph, MediaError>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        let mut system = SystemGraph::new();
+        for path in paths {
+            let path = path.as_ref();
+            system.add_device(path, &MediaDevice::open(path)?)?;
+        }
+        Ok(system)
+    }
+
+    /// Snapshots `device` and returns its index in the system.
+    pub fn add_device(
+        &mut self,
+        path: impl AsRef<Path>,
+        device: &MediaDevice,
+    ) -> Result<usize, MediaError> {
+        let path = path.as_ref();
+        self.devices.push(SystemDevice {
+            path: path.to_path_buf(),
+            info: device.device_info()?,
+            graph: MediaGraph::new(device.topology()?),
+        });
+        Ok(self.devices.len() - 1)
+    }
+
+    pub fn devices(&self) -> &[SystemDevice] {
+        &self.devices
+    }
+
+    pub fn device(&self, index: usize) -> Option<&SystemDevice> {
+        self.devices.get(index)
+    }
+
+    pub fn entity(&self, id: SystemId<EntityId>) -> Option<&MediaV2Entity> {
+        self.device(id.device)?.graph.entity(id.id)
+    }
+
+    /// Entities of all devices, in device order.
+    pub fn entities(&self) -> impl Iterator<Item = (SystemId<EntityId>, &MediaV2Entity)> {
+        self.devices.iter().enumerate().flat_map(|(device, dev)| {
+            dev.graph.topology().entities.iter().map(move |entity| {
+                (
+                    SystemId {
+                        device,
+                        id: entity.id,
+                    },
+                    entity,
+                )
+            })
+        })
+    }
+
+    pub fn camera_sensors(&self) -> impl Iterator<Item = (SystemId<EntityId>, &MediaV2Entity)> {
+        self.entities()
+            .filter(|(_, entity)| entity.is_camera_sensor())
+    }
+
+    /// Entities named `name` on any device.
+    pub fn entities_by_name<'a>(
+        &'a self,
+        name: &'a str,
+    ) -> impl Iterator<Item = (SystemId<EntityId>, &'a MediaV2Entity)> {
+        self.entities()
+            .filter(move |(_, entity)| &*entity.name == name)
+    }
+
+    /// Device indexes grouped by [`SystemDevice::bus`].
+    pub fn devices_by_bus(&self) -> BTreeMap<String, Vec<usize>> {
+        let mut buses: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+        for (index, device) in self.devices.iter().enumerate() {
+            buses.entry(device.bus()).or_default().push(index);
+        }
+        buses
+    }
+}
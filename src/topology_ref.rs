@@ -0,0 +1,416 @@
+use std::{
+    ffi::CStr,
+    os::fd::{AsFd, BorrowedFd},
+};
+
+use nix::errno::Errno;
+
+use crate::media_ffi::{self, media_v2_entity, media_v2_interface, media_v2_link, media_v2_pad};
+use crate::{
+    c_str_bytes, get_topology_version_from_fd, legacy,
+    raw::{self, TopologySections},
+    EntityFlags, EntityFunction, EntityId, InterfaceId, InterfaceType, LinkFlags, LinkId, LinkKind,
+    MediaDevice, MediaError, MediaV2IntfDevnode, MediaV2Topology, NameInterner, PadFlags, PadId,
+    TopologyCounts,
+};
+
+/// Reusable buffers `MEDIA_IOC_G_TOPOLOGY` writes the kernel structs into.
+///
+/// The buffers grow to the largest topology seen and are never shrunk, so
+/// once they fit the graph, [`MediaDevice::topology_ref`] doesn't allocate,
+/// and neither does [`MediaDevice::topology_into`] once the target topology
+/// has held a graph as large.
+#[derive(Default)]
+pub struct TopologyBuffers {
+    entities: Vec<media_v2_entity>,
+    interfaces: Vec<media_v2_interface>,
+    pads: Vec<media_v2_pad>,
+    links: Vec<media_v2_link>,
+}
+
+impl TopologyBuffers {
+    pub fn new() -> TopologyBuffers {
+        TopologyBuffers::default()
+    }
+
+    /// Buffers already sized for `counts` objects.
+    pub fn with_capacity(counts: TopologyCounts) -> TopologyBuffers {
+        let mut buffers = TopologyBuffers::new();
+        buffers.reserve(counts);
+        buffers
+    }
+
+    /// Number of objects of each section that fit without reallocating.
+    pub fn capacity(&self) -> TopologyCounts {
+        TopologyCounts {
+            entities: self.entities.capacity(),
+            interfaces: self.interfaces.capacity(),
+            pads: self.pads.capacity(),
+            links: self.links.capacity(),
+        }
+    }
+
+    fn reserve(&mut self, counts: TopologyCounts) {
+        fn reserve<T>(buffer: &mut Vec<T>, count: usize) {
+            buffer.clear();
+            buffer.reserve(count);
+        }
+        reserve(&mut self.entities, counts.entities);
+        reserve(&mut self.interfaces, counts.interfaces);
+        reserve(&mut self.pads, counts.pads);
+        reserve(&mut self.links, counts.links);
+    }
+}
+
+impl std::fmt::Debug for TopologyBuffers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TopologyBuffers")
+            .field("capacity", &self.capacity())
+            .finish()
+    }
+}
+
+/// A topology read into [`TopologyBuffers`], borrowing entities, interfaces,
+/// pads and links straight from the kernel structs instead of converting
+/// them into a [`crate::MediaV2Topology`].
+#[derive(Clone, Copy)]
+pub struct TopologyRef<'buf> {
+    topology_version: u64,
+    entities: &'buf [media_v2_entity],
+    interfaces: &'buf [media_v2_interface],
+    pads: &'buf [media_v2_pad],
+    links: &'buf [media_v2_link],
+}
+
+impl<'buf> TopologyRef<'buf> {
+    pub fn topology_version(&self) -> u64 {
+        self.topology_version
+    }
+
+    pub fn counts(&self) -> TopologyCounts {
+        TopologyCounts {
+            entities: self.entities.len(),
+            interfaces: self.interfaces.len(),
+            pads: self.pads.len(),
+            links: self.links.len(),
+        }
+    }
+
+    pub fn entities(&self) -> impl ExactSizeIterator<Item = EntityView<'buf>> {
+        self.entities.iter().map(EntityView)
+    }
+
+    pub fn interfaces(&self) -> impl ExactSizeIterator<Item = InterfaceView<'buf>> {
+        self.interfaces.iter().map(InterfaceView)
+    }
+
+    pub fn pads(&self) -> impl ExactSizeIterator<Item = PadView<'buf>> {
+        self.pads.iter().map(PadView)
+    }
+
+    pub fn links(&self) -> impl ExactSizeIterator<Item = LinkView<'buf>> {
+        self.links.iter().map(LinkView)
+    }
+
+    pub fn entity(&self, id: EntityId) -> Option<EntityView<'buf>> {
+        self.entities().find(|entity| entity.id() == id)
+    }
+
+    pub fn entity_by_name(&self, name: &str) -> Option<EntityView<'buf>> {
+        self.entities()
+            .find(|entity| entity.name_bytes() == name.as_bytes())
+    }
+
+    pub fn pad(&self, id: PadId) -> Option<PadView<'buf>> {
+        self.pads().find(|pad| pad.id() == id)
+    }
+}
+
+impl std::fmt::Debug for TopologyRef<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TopologyRef")
+            .field("topology_version", &self.topology_version)
+            .field("entities", &self.entities().collect::<Vec<_>>())
+            .field("interfaces", &self.interfaces().collect::<Vec<_>>())
+            .field("pads", &self.pads().collect::<Vec<_>>())
+            .field("links", &self.links().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// An entity of a [`TopologyRef`].
+#[derive(Clone, Copy)]
+pub struct EntityView<'buf>(&'buf media_v2_entity);
+
+impl<'buf> EntityView<'buf> {
+    pub fn id(&self) -> EntityId {
+        self.0.id.into()
+    }
+
+    /// The name, `None` if the kernel bytes aren't valid UTF-8.
+    pub fn name(&self) -> Option<&'buf str> {
+        std::str::from_utf8(self.name_bytes()).ok()
+    }
+
+    /// The name, `None` if the driver didn't NUL-terminate it.
+    pub fn name_c_str(&self) -> Option<&'buf CStr> {
+        CStr::from_bytes_until_nul(raw::c_chars_as_bytes(&self.0.name)).ok()
+    }
+
+    /// The name exactly as reported by the kernel, without the terminating
+    /// NUL.
+    pub fn name_bytes(&self) -> &'buf [u8] {
+        c_str_bytes(&self.0.name)
+    }
+
+    pub fn function(&self) -> EntityFunction {
+        self.0.function.into()
+    }
+
+    pub fn flags(&self) -> u32 {
+        self.0.flags
+    }
+
+    pub fn entity_flags(&self) -> EntityFlags {
+        EntityFlags::from_bits_retain(self.0.flags)
+    }
+}
+
+/// An interface of a [`TopologyRef`].
+#[derive(Clone, Copy)]
+pub struct InterfaceView<'buf>(&'buf media_v2_interface);
+
+impl InterfaceView<'_> {
+    pub fn id(&self) -> InterfaceId {
+        self.0.id.into()
+    }
+
+    pub fn intf_type(&self) -> InterfaceType {
+        self.0.intf_type.into()
+    }
+
+    pub fn flags(&self) -> u32 {
+        self.0.flags
+    }
+
+    pub fn devnode(&self) -> Option<MediaV2IntfDevnode> {
+        MediaV2IntfDevnode::from_ffi(self.0)
+    }
+}
+
+/// A pad of a [`TopologyRef`].
+#[derive(Clone, Copy)]
+pub struct PadView<'buf>(&'buf media_v2_pad);
+
+impl PadView<'_> {
+    pub fn id(&self) -> PadId {
+        self.0.id.into()
+    }
+
+    pub fn entity_id(&self) -> EntityId {
+        self.0.entity_id.into()
+    }
+
+    pub fn flags(&self) -> u32 {
+        self.0.flags
+    }
+
+    pub fn pad_flags(&self) -> PadFlags {
+        PadFlags::from_bits_retain(self.0.flags)
+    }
+
+    /// Index of the pad within its entity.
+    pub fn index(&self) -> u32 {
+        self.0.index
+    }
+}
+
+/// A link of a [`TopologyRef`].
+#[derive(Clone, Copy)]
+pub struct LinkView<'buf>(&'buf media_v2_link);
+
+impl LinkView<'_> {
+    pub fn id(&self) -> LinkId {
+        self.0.id.into()
+    }
+
+    pub fn source_id(&self) -> u32 {
+        self.0.source_id
+    }
+
+    pub fn sink_id(&self) -> u32 {
+        self.0.sink_id
+    }
+
+    pub fn flags(&self) -> u32 {
+        self.0.flags
+    }
+
+    pub fn link_flags(&self) -> LinkFlags {
+        LinkFlags::from_bits_retain(self.0.flags)
+    }
+
+    pub fn kind(&self) -> LinkKind {
+        self.link_flags().kind()
+    }
+
+    /// Source pad of a data link.
+    pub fn source_pad(&self) -> Option<PadId> {
+        (self.kind() == LinkKind::Data).then_some(PadId(self.0.source_id))
+    }
+
+    /// Sink pad of a data link.
+    pub fn sink_pad(&self) -> Option<PadId> {
+        (self.kind() == LinkKind::Data).then_some(PadId(self.0.sink_id))
+    }
+}
+
+macro_rules! debug_view {
+    ($view:ident, $($field:ident),*) => {
+        impl std::fmt::Debug for $view<'_> {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.debug_struct(stringify!($view))
+                    $(.field(stringify!($field), &self.$field()))*
+                    .finish()
+            }
+        }
+    };
+}
+
+debug_view!(EntityView, id, name_bytes, function, flags);
+debug_view!(InterfaceView, id, intf_type, flags, devnode);
+debug_view!(PadView, id, entity_id, flags, index);
+debug_view!(LinkView, id, source_id, sink_id, flags);
+
+impl MediaDevice {
+    /// Reads the topology into `buffers` and borrows it from there, see
+    /// [`get_topology_ref_from_fd`].
+    pub fn topology_ref<'buf>(
+        &self,
+        buffers: &'buf mut TopologyBuffers,
+    ) -> Result<TopologyRef<'buf>, MediaError> {
+        get_topology_ref_from_fd(self.fd.as_fd(), buffers)
+    }
+
+    /// Overwrites `topology` with the current topology, see
+    /// [`get_topology_into_from_fd`].
+    pub fn topology_into(
+        &self,
+        buffers: &mut TopologyBuffers,
+        topology: &mut MediaV2Topology,
+    ) -> Result<(), MediaError> {
+        get_topology_into_from_fd(self.fd.as_fd(), buffers, topology)
+    }
+}
+
+/// Reads the topology through `buffers` into `topology`, reusing the
+/// allocations of both.
+///
+/// Unlike [`get_topology_ref_from_fd`], the topology of kernels older than
+/// 4.5 is emulated from the legacy ioctls, which allocates.
+pub fn get_topology_into_from_fd(
+    fd: BorrowedFd,
+    buffers: &mut TopologyBuffers,
+    topology: &mut MediaV2Topology,
+) -> Result<(), MediaError> {
+    match buffers.fill(fd) {
+        Ok(topology_version) => {
+            topology.assign_ffi(
+                topology_version,
+                &buffers.entities,
+                &buffers.interfaces,
+                &buffers.pads,
+                &buffers.links,
+            );
+            Ok(())
+        }
+        Err(MediaError::UnsupportedKernel { .. }) => {
+            *topology = legacy::topology_from_legacy(fd)?;
+            Ok(())
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Reads the topology through `buffers` like [`get_topology_into_from_fd`],
+/// with the entity names taken from `interner`.
+pub(crate) fn get_topology_interned_from_fd(
+    fd: BorrowedFd,
+    buffers: &mut TopologyBuffers,
+    interner: &mut NameInterner,
+) -> Result<MediaV2Topology, MediaError> {
+    match buffers.fill(fd) {
+        Ok(topology_version) => Ok(MediaV2Topology::from_ffi_with_names(
+            topology_version,
+            &buffers.entities,
+            &buffers.interfaces,
+            &buffers.pads,
+            &buffers.links,
+            |entity| interner.intern_c_str(&entity.name),
+        )),
+        Err(MediaError::UnsupportedKernel { .. }) => {
+            let mut topology = legacy::topology_from_legacy(fd)?;
+            topology.intern_names(interner);
+            Ok(topology)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Reads the topology into `buffers` with a single `MEDIA_IOC_G_TOPOLOGY`
+/// call when it fits, growing the buffers and repeating the call otherwise.
+///
+/// Kernels older than 4.5 fail with [`MediaError::UnsupportedKernel`]: the
+/// topology emulated from the legacy ioctls has no kernel structs to borrow.
+/// Before 4.19, the kernel doesn't report pad indexes and they read 0.
+pub fn get_topology_ref_from_fd<'buf>(
+    fd: BorrowedFd,
+    buffers: &'buf mut TopologyBuffers,
+) -> Result<TopologyRef<'buf>, MediaError> {
+    let topology_version = buffers.fill(fd)?;
+    Ok(TopologyRef {
+        topology_version,
+        entities: &buffers.entities,
+        interfaces: &buffers.interfaces,
+        pads: &buffers.pads,
+        links: &buffers.links,
+    })
+}
+
+impl TopologyBuffers {
+    /// Reads the topology into the buffers, returning its version.
+    fn fill(&mut self, fd: BorrowedFd) -> Result<u64, MediaError> {
+        for _ in 0..MAX_ATTEMPTS {
+            let capacity = self.capacity();
+            raw::reset(&mut self.entities, capacity.entities);
+            raw::reset(&mut self.interfaces, capacity.interfaces);
+            raw::reset(&mut self.pads, capacity.pads);
+            raw::reset(&mut self.links, capacity.links);
+
+            let mut topology: media_ffi::media_v2_topology = raw::zeroed();
+            let sections = TopologySections {
+                entities: Some(&mut self.entities),
+                interfaces: Some(&mut self.interfaces),
+                pads: Some(&mut self.pads),
+                links: Some(&mut self.links),
+            };
+            match raw::g_topology(fd, &mut topology, sections) {
+                Ok(()) => {
+                    self.entities.truncate(topology.num_entities as usize);
+                    self.interfaces.truncate(topology.num_interfaces as usize);
+                    self.pads.truncate(topology.num_pads as usize);
+                    self.links.truncate(topology.num_links as usize);
+                    return Ok(topology.topology_version);
+                }
+                // One of the sections didn't fit, ask for the current counts.
+                Err(Errno::ENOSPC) => self.reserve(get_topology_version_from_fd(fd)?.counts),
+                Err(errno) => return Err(MediaError::ioctl("MEDIA_IOC_G_TOPOLOGY", errno)),
+            }
+        }
+        Err(MediaError::ioctl("MEDIA_IOC_G_TOPOLOGY", Errno::ENOSPC))
+    }
+}
+
+/// Attempts of [`get_topology_ref_from_fd`] before giving up on a topology
+/// that keeps outgrowing the buffers.
+const MAX_ATTEMPTS: usize = 4;
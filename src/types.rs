@@ -0,0 +1,369 @@
+use std::{error::Error, fmt, str::FromStr};
+
+use crate::{consts, media_ffi};
+
+/// Declares an enum over a family of kernel constants, with an `Unknown(u32)`
+/// catch-all, lossless `u32` conversions, and `Display`/`FromStr` using the
+/// constant names from the UAPI header.
+macro_rules! kernel_enum {
+    (
+        $(#[$meta:meta])*
+        pub enum $name:ident, prefix = $prefix:literal {
+            $($(#[$vmeta:meta])* $variant:ident = $value:ident,)*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum $name {
+            $($(#[$vmeta])* $variant,)*
+            /// A value this version of the crate doesn't know about.
+            Unknown(u32),
+        }
+
+        impl $name {
+            /// The UAPI constant name, e.g. `MEDIA_ENT_F_CAM_SENSOR`.
+            pub fn name(&self) -> Option<&'static str> {
+                match self {
+                    $($name::$variant => Some(stringify!($value)),)*
+                    $name::Unknown(_) => None,
+                }
+            }
+        }
+
+        impl From<u32> for $name {
+            fn from(value: u32) -> $name {
+                match value {
+                    $(consts::$value => $name::$variant,)*
+                    value => $name::Unknown(value),
+                }
+            }
+        }
+
+        impl From<$name> for u32 {
+            fn from(value: $name) -> u32 {
+                match value {
+                    $($name::$variant => consts::$value,)*
+                    $name::Unknown(value) => value,
+                }
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                match self.name() {
+                    Some(name) => f.write_str(name),
+                    None => write!(f, "{:#010x}", u32::from(*self)),
+                }
+            }
+        }
+
+        /// Accepts the UAPI constant name with or without its prefix
+        /// (case-insensitive), or a decimal or `0x` hexadecimal value.
+        impl FromStr for $name {
+            type Err = ParseEnumError;
+
+            fn from_str(s: &str) -> Result<$name, ParseEnumError> {
+                let upper = s.trim().to_ascii_uppercase();
+                let short = upper.strip_prefix($prefix).unwrap_or(&upper);
+                $(
+                    if short == &stringify!($value)[$prefix.len()..] {
+                        return Ok($name::$variant);
+                    }
+                )*
+                parse_u32(&upper)
+                    .map($name::from)
+                    .ok_or_else(|| ParseEnumError(s.to_string()))
+            }
+        }
+
+        /// Mostly known values, sometimes one the crate doesn't know.
+        #[cfg(feature = "proptest")]
+        impl proptest::arbitrary::Arbitrary for $name {
+            type Parameters = ();
+            type Strategy = proptest::strategy::BoxedStrategy<$name>;
+
+            fn arbitrary_with(_: ()) -> Self::Strategy {
+                use proptest::strategy::Strategy;
+                proptest::prop_oneof![
+                    4 => proptest::sample::select(vec![$($name::$variant,)*]),
+                    1 => proptest::num::u32::ANY.prop_map($name::from),
+                ]
+                .boxed()
+            }
+        }
+
+        /// Serialized as its `Display` string.
+        #[cfg(feature = "serde")]
+        impl serde::Serialize for $name {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_str(self)
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        impl<'de> serde::Deserialize<'de> for $name {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<$name, D::Error> {
+                let s = String::deserialize(deserializer)?;
+                s.parse().map_err(serde::de::Error::custom)
+            }
+        }
+    };
+}
+
+fn parse_u32(s: &str) -> Option<u32> {
+    match s.strip_prefix("0X") {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+/// Error returned when a string names none of the known constants.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ParseEnumError(pub String);
+
+impl fmt::Display for ParseEnumError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unrecognized value {:?}", self.0)
+    }
+}
+
+impl Error for ParseEnumError {}
+
+kernel_enum! {
+    /// Main function of an entity (`MEDIA_ENT_F_*`).
+    ///
+    /// `MEDIA_ENT_F_UNKNOWN` is reported as `Unknown(0)`.
+    pub enum EntityFunction, prefix = "MEDIA_ENT_F_" {
+        V4l2SubdevUnknown = MEDIA_ENT_F_V4L2_SUBDEV_UNKNOWN,
+        DtvDemod = MEDIA_ENT_F_DTV_DEMOD,
+        TsDemux = MEDIA_ENT_F_TS_DEMUX,
+        DtvCa = MEDIA_ENT_F_DTV_CA,
+        DtvNetDecap = MEDIA_ENT_F_DTV_NET_DECAP,
+        IoV4l = MEDIA_ENT_F_IO_V4L,
+        IoDtv = MEDIA_ENT_F_IO_DTV,
+        IoVbi = MEDIA_ENT_F_IO_VBI,
+        IoSwradio = MEDIA_ENT_F_IO_SWRADIO,
+        CamSensor = MEDIA_ENT_F_CAM_SENSOR,
+        Flash = MEDIA_ENT_F_FLASH,
+        Lens = MEDIA_ENT_F_LENS,
+        Tuner = MEDIA_ENT_F_TUNER,
+        IfVidDecoder = MEDIA_ENT_F_IF_VID_DECODER,
+        IfAudDecoder = MEDIA_ENT_F_IF_AUD_DECODER,
+        AudioCapture = MEDIA_ENT_F_AUDIO_CAPTURE,
+        AudioPlayback = MEDIA_ENT_F_AUDIO_PLAYBACK,
+        AudioMixer = MEDIA_ENT_F_AUDIO_MIXER,
+        ProcVideoComposer = MEDIA_ENT_F_PROC_VIDEO_COMPOSER,
+        ProcVideoPixelFormatter = MEDIA_ENT_F_PROC_VIDEO_PIXEL_FORMATTER,
+        ProcVideoPixelEncConv = MEDIA_ENT_F_PROC_VIDEO_PIXEL_ENC_CONV,
+        ProcVideoLut = MEDIA_ENT_F_PROC_VIDEO_LUT,
+        ProcVideoScaler = MEDIA_ENT_F_PROC_VIDEO_SCALER,
+        ProcVideoStatistics = MEDIA_ENT_F_PROC_VIDEO_STATISTICS,
+        ProcVideoEncoder = MEDIA_ENT_F_PROC_VIDEO_ENCODER,
+        ProcVideoDecoder = MEDIA_ENT_F_PROC_VIDEO_DECODER,
+        ProcVideoIsp = MEDIA_ENT_F_PROC_VIDEO_ISP,
+        VidMux = MEDIA_ENT_F_VID_MUX,
+        /// Video interface bridge, e.g. a MIPI CSI-2 receiver.
+        VidIfBridge = MEDIA_ENT_F_VID_IF_BRIDGE,
+        AtvDecoder = MEDIA_ENT_F_ATV_DECODER,
+        DvDecoder = MEDIA_ENT_F_DV_DECODER,
+        DvEncoder = MEDIA_ENT_F_DV_ENCODER,
+        /// RF connector, e.g. the antenna input of a TV tuner.
+        ConnRf = MEDIA_ENT_F_CONN_RF,
+        ConnSvideo = MEDIA_ENT_F_CONN_SVIDEO,
+        ConnComposite = MEDIA_ENT_F_CONN_COMPOSITE,
+    }
+}
+
+kernel_enum! {
+    /// Type of an interface (`MEDIA_INTF_T_*`).
+    pub enum InterfaceType, prefix = "MEDIA_INTF_T_" {
+        DvbFe = MEDIA_INTF_T_DVB_FE,
+        DvbDemux = MEDIA_INTF_T_DVB_DEMUX,
+        DvbDvr = MEDIA_INTF_T_DVB_DVR,
+        DvbCa = MEDIA_INTF_T_DVB_CA,
+        DvbNet = MEDIA_INTF_T_DVB_NET,
+        V4lVideo = MEDIA_INTF_T_V4L_VIDEO,
+        V4lVbi = MEDIA_INTF_T_V4L_VBI,
+        V4lRadio = MEDIA_INTF_T_V4L_RADIO,
+        V4lSubdev = MEDIA_INTF_T_V4L_SUBDEV,
+        V4lSwradio = MEDIA_INTF_T_V4L_SWRADIO,
+        V4lTouch = MEDIA_INTF_T_V4L_TOUCH,
+        AlsaPcmCapture = MEDIA_INTF_T_ALSA_PCM_CAPTURE,
+        AlsaPcmPlayback = MEDIA_INTF_T_ALSA_PCM_PLAYBACK,
+        AlsaControl = MEDIA_INTF_T_ALSA_CONTROL,
+        AlsaCompress = MEDIA_INTF_T_ALSA_COMPRESS,
+        AlsaRawmidi = MEDIA_INTF_T_ALSA_RAWMIDI,
+        AlsaHwdep = MEDIA_INTF_T_ALSA_HWDEP,
+        AlsaSequencer = MEDIA_INTF_T_ALSA_SEQUENCER,
+        AlsaTimer = MEDIA_INTF_T_ALSA_TIMER,
+    }
+}
+
+kernel_enum! {
+    /// Media bus format code (`MEDIA_BUS_FMT_*`), describing how pixels are
+    /// transferred between subdevices, e.g. `SRGGB10_1X10` for 10-bit RGGB
+    /// Bayer samples, one per bus cycle.
+    ///
+    /// `Display` and `FromStr` use the names media-ctl does:
+    ///
+    /// ```
+    /// use mc_api::MbusCode;
+    ///
+    /// let code: MbusCode = "SRGGB10_1X10".parse().unwrap();
+    /// assert_eq!(code, MbusCode::Srggb10_1x10);
+    /// assert_eq!(u32::from(code), 0x300f);
+    /// ```
+    pub enum MbusCode, prefix = "MEDIA_BUS_FMT_" {
+        Fixed = MEDIA_BUS_FMT_FIXED,
+        // RGB
+        Rgb444_1x12 = MEDIA_BUS_FMT_RGB444_1X12,
+        Rgb444_2x8PadhiBe = MEDIA_BUS_FMT_RGB444_2X8_PADHI_BE,
+        Rgb444_2x8PadhiLe = MEDIA_BUS_FMT_RGB444_2X8_PADHI_LE,
+        Rgb555_2x8PadhiBe = MEDIA_BUS_FMT_RGB555_2X8_PADHI_BE,
+        Rgb555_2x8PadhiLe = MEDIA_BUS_FMT_RGB555_2X8_PADHI_LE,
+        Rgb565_1x16 = MEDIA_BUS_FMT_RGB565_1X16,
+        Bgr565_2x8Be = MEDIA_BUS_FMT_BGR565_2X8_BE,
+        Bgr565_2x8Le = MEDIA_BUS_FMT_BGR565_2X8_LE,
+        Rgb565_2x8Be = MEDIA_BUS_FMT_RGB565_2X8_BE,
+        Rgb565_2x8Le = MEDIA_BUS_FMT_RGB565_2X8_LE,
+        Rgb666_1x18 = MEDIA_BUS_FMT_RGB666_1X18,
+        Rbg888_1x24 = MEDIA_BUS_FMT_RBG888_1X24,
+        Rgb666_1x24Cpadhi = MEDIA_BUS_FMT_RGB666_1X24_CPADHI,
+        Rgb666_1x7x3Spwg = MEDIA_BUS_FMT_RGB666_1X7X3_SPWG,
+        Bgr888_1x24 = MEDIA_BUS_FMT_BGR888_1X24,
+        Bgr888_3x8 = MEDIA_BUS_FMT_BGR888_3X8,
+        Gbr888_1x24 = MEDIA_BUS_FMT_GBR888_1X24,
+        Rgb888_1x24 = MEDIA_BUS_FMT_RGB888_1X24,
+        Rgb888_2x12Be = MEDIA_BUS_FMT_RGB888_2X12_BE,
+        Rgb888_2x12Le = MEDIA_BUS_FMT_RGB888_2X12_LE,
+        Rgb888_3x8 = MEDIA_BUS_FMT_RGB888_3X8,
+        Rgb888_3x8Delta = MEDIA_BUS_FMT_RGB888_3X8_DELTA,
+        Rgb888_1x7x4Spwg = MEDIA_BUS_FMT_RGB888_1X7X4_SPWG,
+        Rgb888_1x7x4Jeida = MEDIA_BUS_FMT_RGB888_1X7X4_JEIDA,
+        Rgb666_1x30Cpadlo = MEDIA_BUS_FMT_RGB666_1X30_CPADLO,
+        Rgb888_1x30Cpadlo = MEDIA_BUS_FMT_RGB888_1X30_CPADLO,
+        Argb8888_1x32 = MEDIA_BUS_FMT_ARGB8888_1X32,
+        Rgb888_1x32Padhi = MEDIA_BUS_FMT_RGB888_1X32_PADHI,
+        Rgb101010_1x30 = MEDIA_BUS_FMT_RGB101010_1X30,
+        Rgb666_1x36Cpadlo = MEDIA_BUS_FMT_RGB666_1X36_CPADLO,
+        Rgb888_1x36Cpadlo = MEDIA_BUS_FMT_RGB888_1X36_CPADLO,
+        Rgb121212_1x36 = MEDIA_BUS_FMT_RGB121212_1X36,
+        Rgb161616_1x48 = MEDIA_BUS_FMT_RGB161616_1X48,
+        // YUV, including grey
+        Y8_1x8 = MEDIA_BUS_FMT_Y8_1X8,
+        Uv8_1x8 = MEDIA_BUS_FMT_UV8_1X8,
+        Uyvy8_1_5x8 = MEDIA_BUS_FMT_UYVY8_1_5X8,
+        Vyuy8_1_5x8 = MEDIA_BUS_FMT_VYUY8_1_5X8,
+        Yuyv8_1_5x8 = MEDIA_BUS_FMT_YUYV8_1_5X8,
+        Yvyu8_1_5x8 = MEDIA_BUS_FMT_YVYU8_1_5X8,
+        Uyvy8_2x8 = MEDIA_BUS_FMT_UYVY8_2X8,
+        Vyuy8_2x8 = MEDIA_BUS_FMT_VYUY8_2X8,
+        Yuyv8_2x8 = MEDIA_BUS_FMT_YUYV8_2X8,
+        Yvyu8_2x8 = MEDIA_BUS_FMT_YVYU8_2X8,
+        Y10_1x10 = MEDIA_BUS_FMT_Y10_1X10,
+        Y10_2x8PadhiLe = MEDIA_BUS_FMT_Y10_2X8_PADHI_LE,
+        Uyvy10_2x10 = MEDIA_BUS_FMT_UYVY10_2X10,
+        Vyuy10_2x10 = MEDIA_BUS_FMT_VYUY10_2X10,
+        Yuyv10_2x10 = MEDIA_BUS_FMT_YUYV10_2X10,
+        Yvyu10_2x10 = MEDIA_BUS_FMT_YVYU10_2X10,
+        Y12_1x12 = MEDIA_BUS_FMT_Y12_1X12,
+        Uyvy12_2x12 = MEDIA_BUS_FMT_UYVY12_2X12,
+        Vyuy12_2x12 = MEDIA_BUS_FMT_VYUY12_2X12,
+        Yuyv12_2x12 = MEDIA_BUS_FMT_YUYV12_2X12,
+        Yvyu12_2x12 = MEDIA_BUS_FMT_YVYU12_2X12,
+        Y14_1x14 = MEDIA_BUS_FMT_Y14_1X14,
+        Uyvy8_1x16 = MEDIA_BUS_FMT_UYVY8_1X16,
+        Vyuy8_1x16 = MEDIA_BUS_FMT_VYUY8_1X16,
+        Yuyv8_1x16 = MEDIA_BUS_FMT_YUYV8_1X16,
+        Yvyu8_1x16 = MEDIA_BUS_FMT_YVYU8_1X16,
+        Ydyuydyv8_1x16 = MEDIA_BUS_FMT_YDYUYDYV8_1X16,
+        Uyvy10_1x20 = MEDIA_BUS_FMT_UYVY10_1X20,
+        Vyuy10_1x20 = MEDIA_BUS_FMT_VYUY10_1X20,
+        Yuyv10_1x20 = MEDIA_BUS_FMT_YUYV10_1X20,
+        Yvyu10_1x20 = MEDIA_BUS_FMT_YVYU10_1X20,
+        Vuy8_1x24 = MEDIA_BUS_FMT_VUY8_1X24,
+        Yuv8_1x24 = MEDIA_BUS_FMT_YUV8_1X24,
+        Uyyvyy8_0_5x24 = MEDIA_BUS_FMT_UYYVYY8_0_5X24,
+        Uyvy12_1x24 = MEDIA_BUS_FMT_UYVY12_1X24,
+        Vyuy12_1x24 = MEDIA_BUS_FMT_VYUY12_1X24,
+        Yuyv12_1x24 = MEDIA_BUS_FMT_YUYV12_1X24,
+        Yvyu12_1x24 = MEDIA_BUS_FMT_YVYU12_1X24,
+        Yuv10_1x30 = MEDIA_BUS_FMT_YUV10_1X30,
+        Uyyvyy10_0_5x30 = MEDIA_BUS_FMT_UYYVYY10_0_5X30,
+        Ayuv8_1x32 = MEDIA_BUS_FMT_AYUV8_1X32,
+        Uyyvyy12_0_5x36 = MEDIA_BUS_FMT_UYYVYY12_0_5X36,
+        Yuv12_1x36 = MEDIA_BUS_FMT_YUV12_1X36,
+        Yuv16_1x48 = MEDIA_BUS_FMT_YUV16_1X48,
+        Uyyvyy16_0_5x48 = MEDIA_BUS_FMT_UYYVYY16_0_5X48,
+        // Bayer
+        Sbggr8_1x8 = MEDIA_BUS_FMT_SBGGR8_1X8,
+        Sgbrg8_1x8 = MEDIA_BUS_FMT_SGBRG8_1X8,
+        Sgrbg8_1x8 = MEDIA_BUS_FMT_SGRBG8_1X8,
+        Srggb8_1x8 = MEDIA_BUS_FMT_SRGGB8_1X8,
+        Sbggr10Alaw8_1x8 = MEDIA_BUS_FMT_SBGGR10_ALAW8_1X8,
+        Sgbrg10Alaw8_1x8 = MEDIA_BUS_FMT_SGBRG10_ALAW8_1X8,
+        Sgrbg10Alaw8_1x8 = MEDIA_BUS_FMT_SGRBG10_ALAW8_1X8,
+        Srggb10Alaw8_1x8 = MEDIA_BUS_FMT_SRGGB10_ALAW8_1X8,
+        Sbggr10Dpcm8_1x8 = MEDIA_BUS_FMT_SBGGR10_DPCM8_1X8,
+        Sgbrg10Dpcm8_1x8 = MEDIA_BUS_FMT_SGBRG10_DPCM8_1X8,
+        Sgrbg10Dpcm8_1x8 = MEDIA_BUS_FMT_SGRBG10_DPCM8_1X8,
+        Srggb10Dpcm8_1x8 = MEDIA_BUS_FMT_SRGGB10_DPCM8_1X8,
+        Sbggr10_2x8PadhiBe = MEDIA_BUS_FMT_SBGGR10_2X8_PADHI_BE,
+        Sbggr10_2x8PadhiLe = MEDIA_BUS_FMT_SBGGR10_2X8_PADHI_LE,
+        Sbggr10_2x8PadloBe = MEDIA_BUS_FMT_SBGGR10_2X8_PADLO_BE,
+        Sbggr10_2x8PadloLe = MEDIA_BUS_FMT_SBGGR10_2X8_PADLO_LE,
+        Sbggr10_1x10 = MEDIA_BUS_FMT_SBGGR10_1X10,
+        Sgbrg10_1x10 = MEDIA_BUS_FMT_SGBRG10_1X10,
+        Sgrbg10_1x10 = MEDIA_BUS_FMT_SGRBG10_1X10,
+        Srggb10_1x10 = MEDIA_BUS_FMT_SRGGB10_1X10,
+        Sbggr12_1x12 = MEDIA_BUS_FMT_SBGGR12_1X12,
+        Sgbrg12_1x12 = MEDIA_BUS_FMT_SGBRG12_1X12,
+        Sgrbg12_1x12 = MEDIA_BUS_FMT_SGRBG12_1X12,
+        Srggb12_1x12 = MEDIA_BUS_FMT_SRGGB12_1X12,
+        Sbggr14_1x14 = MEDIA_BUS_FMT_SBGGR14_1X14,
+        Sgbrg14_1x14 = MEDIA_BUS_FMT_SGBRG14_1X14,
+        Sgrbg14_1x14 = MEDIA_BUS_FMT_SGRBG14_1X14,
+        Srggb14_1x14 = MEDIA_BUS_FMT_SRGGB14_1X14,
+        Sbggr16_1x16 = MEDIA_BUS_FMT_SBGGR16_1X16,
+        Sgbrg16_1x16 = MEDIA_BUS_FMT_SGBRG16_1X16,
+        Sgrbg16_1x16 = MEDIA_BUS_FMT_SGRBG16_1X16,
+        Srggb16_1x16 = MEDIA_BUS_FMT_SRGGB16_1X16,
+        // JPEG
+        Jpeg1x8 = MEDIA_BUS_FMT_JPEG_1X8,
+        // Vendor specific
+        S5cUyvyJpeg1x8 = MEDIA_BUS_FMT_S5C_UYVY_JPEG_1X8,
+        // HSV
+        Ahsv8888_1x32 = MEDIA_BUS_FMT_AHSV8888_1X32,
+        // Metadata
+        MetadataFixed = MEDIA_BUS_FMT_METADATA_FIXED,
+    }
+}
+
+impl InterfaceType {
+    /// A `/dev/videoN` node.
+    pub fn is_v4l_video(&self) -> bool {
+        *self == InterfaceType::V4lVideo
+    }
+
+    /// A `/dev/v4l-subdevN` node.
+    pub fn is_v4l_subdev(&self) -> bool {
+        *self == InterfaceType::V4lSubdev
+    }
+
+    pub fn is_v4l(&self) -> bool {
+        u32::from(*self) & !0xff == media_ffi::MEDIA_INTF_T_V4L_BASE
+    }
+
+    pub fn is_dvb(&self) -> bool {
+        u32::from(*self) & !0xff == media_ffi::MEDIA_INTF_T_DVB_BASE
+    }
+
+    pub fn is_alsa(&self) -> bool {
+        u32::from(*self) & !0xff == media_ffi::MEDIA_INTF_T_ALSA_BASE
+    }
+
+    /// All DVB, V4L and ALSA interfaces are backed by a device node.
+    pub fn is_devnode(&self) -> bool {
+        self.is_dvb() || self.is_v4l() || self.is_alsa()
+    }
+}
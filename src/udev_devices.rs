@@ -0,0 +1,92 @@
+use std::{collections::HashMap, ffi::OsStr, path::PathBuf};
+
+use crate::{discovery::media_index, get_device_info, MediaDeviceInfo, MediaError};
+
+/// A media controller device as reported by udev.
+#[derive(Debug)]
+pub struct UdevMediaDevice {
+    /// Device node, e.g. `/dev/media0`.
+    pub path: PathBuf,
+    /// Device directory in sysfs.
+    pub syspath: PathBuf,
+    pub subsystem: Option<String>,
+    /// Persistent physical location (`ID_PATH`), e.g.
+    /// `pci-0000:00:14.0-usb-0:2:1.0`.
+    pub id_path: Option<String>,
+    /// Nearest USB or PCI ancestor.
+    pub parent: Option<UdevParent>,
+    /// All udev properties of the device.
+    pub properties: HashMap<String, String>,
+    /// Device information, or why the node couldn't be opened or queried.
+    pub info: Result<MediaDeviceInfo, MediaError>,
+}
+
+/// A bus device the media device hangs off.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct UdevParent {
+    /// `usb` or `pci`.
+    pub subsystem: String,
+    pub syspath: PathBuf,
+    /// Kernel name of the device, e.g. `1-2` or `0000:00:14.0`.
+    pub sysname: String,
+}
+
+/// Lists the devices of the `media` subsystem known to udev, ordered by
+/// device number. Devices without a node are left out.
+pub fn enumerate_devices_udev() -> Result<Vec<UdevMediaDevice>, MediaError> {
+    let mut enumerator = udev::Enumerator::new().map_err(MediaError::Udev)?;
+    enumerator
+        .match_subsystem("media")
+        .map_err(MediaError::Udev)?;
+
+    let mut devices: Vec<_> = enumerator
+        .scan_devices()
+        .map_err(MediaError::Udev)?
+        .filter_map(|device| {
+            let path = device.devnode()?.to_path_buf();
+            Some(UdevMediaDevice {
+                info: get_device_info(&path),
+                path,
+                syspath: device.syspath().to_path_buf(),
+                subsystem: device.subsystem().map(|s| s.to_string_lossy().into_owned()),
+                id_path: device
+                    .property_value("ID_PATH")
+                    .map(|s| s.to_string_lossy().into_owned()),
+                parent: bus_parent(&device),
+                properties: device
+                    .properties()
+                    .map(|entry| {
+                        (
+                            entry.name().to_string_lossy().into_owned(),
+                            entry.value().to_string_lossy().into_owned(),
+                        )
+                    })
+                    .collect(),
+            })
+        })
+        .collect();
+    devices.sort_by(|a, b| (media_index(&a.path), &a.path).cmp(&(media_index(&b.path), &b.path)));
+    Ok(devices)
+}
+
+fn bus_parent(device: &udev::Device) -> Option<UdevParent> {
+    let mut parent = device.parent();
+    while let Some(dev) = parent {
+        let subsystem = dev.subsystem().and_then(OsStr::to_str);
+        // USB interfaces share the subsystem with their device, report the device.
+        let is_bus_device = match subsystem {
+            Some("pci") => true,
+            Some("usb") => dev.devtype() == Some(OsStr::new("usb_device")),
+            _ => false,
+        };
+        if let (true, Some(subsystem)) = (is_bus_device, subsystem) {
+            return Some(UdevParent {
+                subsystem: subsystem.to_string(),
+                syspath: dev.syspath().to_path_buf(),
+                sysname: dev.sysname().to_string_lossy().into_owned(),
+            });
+        }
+        parent = dev.parent();
+    }
+    None
+}
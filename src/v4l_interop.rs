@@ -0,0 +1,47 @@
+use crate::{EntityId, MediaError, MediaGraph, MediaV2IntfDevnode};
+
+impl MediaV2IntfDevnode {
+    /// Opens the device node with the `v4l` crate, e.g. to start capturing
+    /// once the pipeline is configured.
+    pub fn open_v4l(&self) -> Result<v4l::Device, MediaError> {
+        let path = self.path().ok_or(MediaError::UnregisteredDevnode {
+            major: self.major,
+            minor: self.minor,
+        })?;
+        v4l::Device::with_path(&path).map_err(|source| MediaError::Open { path, source })
+    }
+}
+
+impl TryFrom<&MediaV2IntfDevnode> for v4l::Device {
+    type Error = MediaError;
+
+    fn try_from(devnode: &MediaV2IntfDevnode) -> Result<v4l::Device, MediaError> {
+        devnode.open_v4l()
+    }
+}
+
+impl MediaGraph {
+    /// Opens the video device node (`/dev/videoN`) of `entity`, usually the
+    /// DMA engine at the end of a capture path, with the `v4l` crate. Fails
+    /// with [`MediaError::NoVideoNode`] if the entity has none.
+    ///
+    /// ```no_run
+    /// use mc_api::{LinkRequest, MediaDevice, MediaGraph};
+    ///
+    /// let device = MediaDevice::by_index(0)?;
+    /// device.apply_links(&LinkRequest::parse_list("'csi':1 -> 'dma':0 [1]")?)?;
+    /// let graph = MediaGraph::new(device.topology()?);
+    /// let dma = graph.entity_by_name("dma").unwrap();
+    /// let capture = graph.v4l_device(dma.id())?;
+    /// println!("{}", capture.query_caps()?.card);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn v4l_device(&self, entity: EntityId) -> Result<v4l::Device, MediaError> {
+        let devnode = self
+            .interfaces(entity)
+            .filter(|intf| intf.intf_type().is_v4l_video())
+            .find_map(|intf| intf.devnode())
+            .ok_or(MediaError::NoVideoNode(entity))?;
+        devnode.open_v4l()
+    }
+}
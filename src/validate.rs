@@ -0,0 +1,277 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+use crate::{
+    plan::pad_ref, EntityId, LinkFlags, LinkId, LinkKind, LinkRequest, MediaDevice, MediaError,
+    MediaGraph, MediaV2Topology, PadFlags, PadId, PadRef,
+};
+
+/// A structural problem found by [`MediaV2Topology::validate`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TopologyFinding {
+    /// Several objects share an ID. IDs are unique across entities,
+    /// interfaces, pads and links.
+    DuplicateId(u32),
+    /// A pad refers to an entity missing from the topology.
+    PadWithoutEntity { pad: PadId, entity: EntityId },
+    /// A link endpoint refers to an object missing from the topology.
+    DanglingLink { link: LinkId, missing_id: u32 },
+    /// A `MUST_CONNECT` sink pad has no enabled link.
+    MustConnectPadUnconnected { pad: PadId },
+}
+
+impl fmt::Display for TopologyFinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TopologyFinding::DuplicateId(id) => write!(f, "id {id} is used by several objects"),
+            TopologyFinding::PadWithoutEntity { pad, entity } => {
+                write!(f, "pad {pad} belongs to missing entity {entity}")
+            }
+            TopologyFinding::DanglingLink { link, missing_id } => {
+                write!(f, "link {link} refers to missing object {missing_id}")
+            }
+            TopologyFinding::MustConnectPadUnconnected { pad } => {
+                write!(f, "MUST_CONNECT sink pad {pad} has no enabled link")
+            }
+        }
+    }
+}
+
+impl MediaV2Topology {
+    /// Checks the topology for structural problems. An empty list means the
+    /// topology is consistent.
+    pub fn validate(&self) -> Vec<TopologyFinding> {
+        let mut findings = Vec::new();
+
+        let mut seen = HashSet::new();
+        let mut reported = HashSet::new();
+        let ids = self
+            .entities
+            .iter()
+            .map(|e| e.id.0)
+            .chain(self.interfaces.iter().map(|i| i.id.0))
+            .chain(self.pads.iter().map(|p| p.id.0))
+            .chain(self.links.iter().map(|l| l.id.0));
+        for id in ids {
+            if !seen.insert(id) && reported.insert(id) {
+                findings.push(TopologyFinding::DuplicateId(id));
+            }
+        }
+
+        let entities: HashSet<EntityId> = self.entities.iter().map(|e| e.id).collect();
+        let pads: HashMap<PadId, PadFlags> =
+            self.pads.iter().map(|p| (p.id, p.pad_flags())).collect();
+        let interfaces: HashSet<u32> = self.interfaces.iter().map(|i| i.id.0).collect();
+
+        for pad in &self.pads {
+            if !entities.contains(&pad.entity_id) {
+                findings.push(TopologyFinding::PadWithoutEntity {
+                    pad: pad.id,
+                    entity: pad.entity_id,
+                });
+            }
+        }
+
+        let mut connected = HashSet::new();
+        for link in &self.links {
+            let (source_exists, sink_exists) = match link.kind() {
+                LinkKind::Data => (
+                    pads.contains_key(&PadId(link.source_id)),
+                    pads.contains_key(&PadId(link.sink_id)),
+                ),
+                LinkKind::Interface => (
+                    interfaces.contains(&link.source_id),
+                    entities.contains(&EntityId(link.sink_id)),
+                ),
+                LinkKind::Ancillary => (
+                    entities.contains(&EntityId(link.source_id)),
+                    entities.contains(&EntityId(link.sink_id)),
+                ),
+                LinkKind::Unknown(_) => continue,
+            };
+            if !source_exists {
+                findings.push(TopologyFinding::DanglingLink {
+                    link: link.id,
+                    missing_id: link.source_id,
+                });
+            }
+            if !sink_exists {
+                findings.push(TopologyFinding::DanglingLink {
+                    link: link.id,
+                    missing_id: link.sink_id,
+                });
+            }
+            if link.kind() == LinkKind::Data && link.link_flags().contains(LinkFlags::ENABLED) {
+                connected.insert(PadId(link.source_id));
+                connected.insert(PadId(link.sink_id));
+            }
+        }
+
+        for pad in &self.pads {
+            let flags = pad.pad_flags();
+            if flags.contains(PadFlags::SINK | PadFlags::MUST_CONNECT)
+                && !connected.contains(&pad.id)
+            {
+                findings.push(TopologyFinding::MustConnectPadUnconnected { pad: pad.id });
+            }
+        }
+
+        findings
+    }
+}
+
+/// A problem with a set of link changes, found by
+/// [`MediaGraph::validate_links`] before any of them is applied.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ValidationIssue {
+    /// The requested pads aren't connected by a data link.
+    MissingLink { request: LinkRequest },
+    /// The request would enable or disable an immutable link.
+    ImmutableLink { request: LinkRequest },
+    /// The sink pad would receive several enabled links. The media controller
+    /// doesn't advertise multiplexing pads, so this is only reported for pads
+    /// that don't already have several enabled links.
+    SinkConflict { sink: PadRef, links: Vec<LinkId> },
+    /// A `MUST_CONNECT` sink pad would be left without an enabled link. Only
+    /// a warning, the pipeline can't stream but the links can be set up.
+    MustConnectUnconnected { pad: PadRef },
+}
+
+impl ValidationIssue {
+    /// Whether the kernel would refuse the changes, as opposed to a warning
+    /// about the resulting pipeline.
+    pub fn is_error(&self) -> bool {
+        !matches!(self, ValidationIssue::MustConnectUnconnected { .. })
+    }
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationIssue::MissingLink { request } => write!(f, "{request}: no such link"),
+            ValidationIssue::ImmutableLink { request } => {
+                write!(f, "{request}: link is immutable")
+            }
+            ValidationIssue::SinkConflict { sink, links } => {
+                write!(
+                    f,
+                    "sink pad {sink} would have {} enabled links",
+                    links.len()
+                )
+            }
+            ValidationIssue::MustConnectUnconnected { pad } => {
+                write!(f, "MUST_CONNECT sink pad {pad} would have no enabled link")
+            }
+        }
+    }
+}
+
+/// Outcome of [`MediaGraph::validate_links`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ValidationReport {
+    pub issues: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    pub fn errors(&self) -> impl Iterator<Item = &ValidationIssue> {
+        self.issues.iter().filter(|issue| issue.is_error())
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &ValidationIssue> {
+        self.issues.iter().filter(|issue| !issue.is_error())
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.errors().next().is_some()
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, issue) in self.issues.iter().enumerate() {
+            if i > 0 {
+                f.write_str("; ")?;
+            }
+            write!(f, "{issue}")?;
+        }
+        Ok(())
+    }
+}
+
+impl MediaGraph {
+    /// Checks `links` against the link constraints of the topology, as if
+    /// they were applied in order. Fails if a pad can't be resolved.
+    pub fn validate_links(&self, links: &[LinkRequest]) -> Result<ValidationReport, MediaError> {
+        let enabled = LinkFlags::ENABLED.bits();
+        let mut issues = Vec::new();
+        let mut state: HashMap<LinkId, u32> = HashMap::new();
+        let mut touched = HashSet::new();
+        for request in links {
+            let source = request.source.resolve(self)?;
+            let sink = request.sink.resolve(self)?;
+            let Some(link) = self
+                .links(source.id)
+                .find(|l| l.sink_pad() == Some(sink.id))
+            else {
+                issues.push(ValidationIssue::MissingLink {
+                    request: request.clone(),
+                });
+                continue;
+            };
+            let flags = state.entry(link.id).or_insert(link.flags);
+            if *flags & enabled == request.flags & enabled {
+                continue;
+            }
+            if link.link_flags().contains(LinkFlags::IMMUTABLE) {
+                issues.push(ValidationIssue::ImmutableLink {
+                    request: request.clone(),
+                });
+                continue;
+            }
+            *flags = (*flags & !enabled) | (request.flags & enabled);
+            touched.insert(sink.id);
+        }
+
+        let is_enabled = |link_id: LinkId, flags: u32| {
+            state.get(&link_id).copied().unwrap_or(flags) & enabled != 0
+        };
+        let mut sinks: Vec<PadId> = touched.into_iter().collect();
+        sinks.sort();
+        for pad_id in sinks {
+            let Some(pad) = self.pad(pad_id) else {
+                continue;
+            };
+            let incoming = || {
+                self.links(pad_id)
+                    .filter(move |l| l.kind() == LinkKind::Data && l.sink_pad() == Some(pad_id))
+            };
+            let before = incoming()
+                .filter(|l| l.link_flags().contains(LinkFlags::ENABLED))
+                .count();
+            let after: Vec<LinkId> = incoming()
+                .filter(|l| is_enabled(l.id, l.flags))
+                .map(|l| l.id)
+                .collect();
+            if after.len() > 1 && before <= 1 {
+                issues.push(ValidationIssue::SinkConflict {
+                    sink: pad_ref(self, pad),
+                    links: after,
+                });
+            } else if after.is_empty() && pad.pad_flags().contains(PadFlags::MUST_CONNECT) {
+                issues.push(ValidationIssue::MustConnectUnconnected {
+                    pad: pad_ref(self, pad),
+                });
+            }
+        }
+        Ok(ValidationReport { issues })
+    }
+}
+
+impl MediaDevice {
+    pub fn validate_links(&self, links: &[LinkRequest]) -> Result<ValidationReport, MediaError> {
+        self.link_graph()?.validate_links(links)
+    }
+}
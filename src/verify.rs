@@ -0,0 +1,47 @@
+use std::fmt;
+
+use crate::{LinkFlags, LinkRequest, MediaDevice, MediaError};
+
+/// A requested link whose state on the device differs from the request,
+/// typically because the driver silently refused the change.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LinkMismatch {
+    pub request: LinkRequest,
+    /// Current flags of the link, `None` if there's no link between the pads.
+    pub actual_flags: Option<u32>,
+}
+
+impl fmt::Display for LinkMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.actual_flags {
+            Some(flags) => write!(f, "{}: link flags are {flags}", self.request),
+            None => write!(f, "{}: no such link", self.request),
+        }
+    }
+}
+
+impl MediaDevice {
+    /// Re-reads the topology and returns the links of `links` that don't
+    /// have the requested ENABLED state. An empty result means every request
+    /// took effect.
+    pub fn verify_links(&self, links: &[LinkRequest]) -> Result<Vec<LinkMismatch>, MediaError> {
+        let graph = self.link_graph()?;
+        let mut mismatches = Vec::new();
+        for link in links {
+            let source = link.source.resolve(&graph)?;
+            let sink = link.sink.resolve(&graph)?;
+            let actual_flags = graph
+                .links(source.id)
+                .find(|l| l.sink_pad() == Some(sink.id))
+                .map(|l| l.flags);
+            let enabled = LinkFlags::ENABLED.bits();
+            if actual_flags.is_none_or(|flags| flags & enabled != link.flags & enabled) {
+                mismatches.push(LinkMismatch {
+                    request: link.clone(),
+                    actual_flags,
+                });
+            }
+        }
+        Ok(mismatches)
+    }
+}
@@ -0,0 +1,44 @@
+use std::fmt;
+
+/// A version packed like the kernel's `KERNEL_VERSION(major, minor, patch)`,
+/// as reported in `media_version` and `driver_version`.
+///
+/// Versions compare numerically, so
+/// `info.media_version() >= KernelVersion::new(5, 10, 0)` works as expected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KernelVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl KernelVersion {
+    pub const fn new(major: u32, minor: u32, patch: u32) -> KernelVersion {
+        KernelVersion {
+            major,
+            minor,
+            patch,
+        }
+    }
+}
+
+impl From<u32> for KernelVersion {
+    fn from(version: u32) -> KernelVersion {
+        KernelVersion::new(version >> 16, (version >> 8) & 0xff, version & 0xff)
+    }
+}
+
+/// Packs the version, saturating `minor` and `patch` at 255 like
+/// `KERNEL_VERSION()` does for the patch level.
+impl From<KernelVersion> for u32 {
+    fn from(version: KernelVersion) -> u32 {
+        (version.major << 16) | (version.minor.min(0xff) << 8) | version.patch.min(0xff)
+    }
+}
+
+impl fmt::Display for KernelVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}